@@ -0,0 +1,279 @@
+/**
+ * Modular arithmetic on big unsigned integers, used by RSA and the elliptic curves.
+ *
+ * Numbers are little-endian vectors of u64 limbs, always `Modulus::len()` limbs long.
+ * Multiplication uses the Montgomery form, so the modulus must be odd.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Modulus {
+    n: Vec<u64>,
+    n0: u64,      // -n^-1 mod 2^64
+    r2: Vec<u64>, // R^2 mod n where R = 2^(64 * len)
+    bits: usize,
+}
+
+impl Modulus {
+    /// Returns None unless `bytes` encodes an odd number greater than 1.
+    pub fn from_be_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut n = limbs_from_be(bytes);
+        while n.len() > 1 && n[n.len() - 1] == 0 {
+            n.pop();
+        }
+        if n[0] & 1 == 0 || (n.len() == 1 && n[0] == 1) {
+            return None;
+        }
+        // Newton iteration doubles the number of correct low bits each round
+        let mut inv = n[0];
+        for _ in 0..6 {
+            inv = inv.wrapping_mul(2u64.wrapping_sub(n[0].wrapping_mul(inv)));
+        }
+        let bits = n.len() * 64 - n[n.len() - 1].leading_zeros() as usize;
+        let mut modulus = Self {
+            n0: inv.wrapping_neg(),
+            r2: vec![],
+            bits,
+            n,
+        };
+        let mut r2 = modulus.one_plain();
+        for _ in 0..2 * 64 * modulus.len() {
+            r2 = modulus.add(&r2, &r2);
+        }
+        modulus.r2 = r2;
+        Some(modulus)
+    }
+
+    /// Number of limbs of every value modulo this modulus.
+    pub fn len(&self) -> usize {
+        self.n.len()
+    }
+
+    pub fn bits(&self) -> usize {
+        self.bits
+    }
+
+    pub fn byte_len(&self) -> usize {
+        self.bits.div_ceil(8)
+    }
+
+    /// Decodes a big-endian number, returning None unless it is less than the modulus.
+    pub fn decode(&self, bytes: &[u8]) -> Option<Vec<u64>> {
+        let mut limbs = limbs_from_be(bytes);
+        if limbs[self.len().min(limbs.len())..].iter().any(|l| *l != 0) {
+            return None;
+        }
+        limbs.resize(self.len(), 0);
+        if compare(&limbs, &self.n) == std::cmp::Ordering::Less {
+            Some(limbs)
+        } else {
+            None
+        }
+    }
+
+    /// Decodes a big-endian number of any length and reduces it modulo n.
+    pub fn reduce(&self, bytes: &[u8]) -> Vec<u64> {
+        let mut acc = vec![0; self.len()];
+        for byte in bytes {
+            for bit in (0..8).rev() {
+                acc = self.add(&acc, &acc);
+                if (byte >> bit) & 1 == 1 {
+                    acc = self.add(&acc, &self.one_plain());
+                }
+            }
+        }
+        acc
+    }
+
+    /// Encodes a value as big-endian bytes, left padded to the byte length of the modulus.
+    pub fn encode(&self, a: &[u64]) -> Vec<u8> {
+        let bytes: Vec<u8> = a.iter().rev().flat_map(|l| l.to_be_bytes()).collect();
+        bytes[bytes.len() - self.byte_len()..].to_vec()
+    }
+
+    pub fn zero(&self) -> Vec<u64> {
+        vec![0; self.len()]
+    }
+
+    fn one_plain(&self) -> Vec<u64> {
+        let mut one = self.zero();
+        one[0] = 1;
+        one
+    }
+
+    /// 1 in Montgomery form.
+    pub fn one(&self) -> Vec<u64> {
+        self.to_mont(&self.one_plain())
+    }
+
+    pub fn is_zero(a: &[u64]) -> bool {
+        a.iter().all(|l| *l == 0)
+    }
+
+    pub fn to_mont(&self, a: &[u64]) -> Vec<u64> {
+        self.mul(a, &self.r2)
+    }
+
+    /// Converts out of Montgomery form.
+    pub fn to_normal(&self, a: &[u64]) -> Vec<u64> {
+        self.mul(a, &self.one_plain())
+    }
+
+    pub fn add(&self, a: &[u64], b: &[u64]) -> Vec<u64> {
+        let mut sum = Vec::with_capacity(self.len());
+        let mut carry = 0u128;
+        for (x, y) in a.iter().zip(b) {
+            let s = *x as u128 + *y as u128 + carry;
+            sum.push(s as u64);
+            carry = s >> 64;
+        }
+        if carry != 0 || compare(&sum, &self.n) != std::cmp::Ordering::Less {
+            sub_in_place(&mut sum, &self.n);
+        }
+        sum
+    }
+
+    pub fn sub(&self, a: &[u64], b: &[u64]) -> Vec<u64> {
+        let mut diff = a.to_vec();
+        if sub_in_place(&mut diff, b) {
+            add_in_place(&mut diff, &self.n);
+        }
+        diff
+    }
+
+    /// Montgomery multiplication: a * b / R mod n.
+    pub fn mul(&self, a: &[u64], b: &[u64]) -> Vec<u64> {
+        let k = self.len();
+        let mut t = vec![0u64; k + 2];
+        for &bi in b {
+            let mut carry = 0u128;
+            for j in 0..k {
+                let s = t[j] as u128 + a[j] as u128 * bi as u128 + carry;
+                t[j] = s as u64;
+                carry = s >> 64;
+            }
+            let s = t[k] as u128 + carry;
+            t[k] = s as u64;
+            t[k + 1] = (s >> 64) as u64;
+
+            let m = t[0].wrapping_mul(self.n0);
+            let mut carry = (t[0] as u128 + m as u128 * self.n[0] as u128) >> 64;
+            for j in 1..k {
+                let s = t[j] as u128 + m as u128 * self.n[j] as u128 + carry;
+                t[j - 1] = s as u64;
+                carry = s >> 64;
+            }
+            let s = t[k] as u128 + carry;
+            t[k - 1] = s as u64;
+            t[k] = t[k + 1] + (s >> 64) as u64;
+        }
+        let overflow = t[k] != 0;
+        t.truncate(k);
+        if overflow || compare(&t, &self.n) != std::cmp::Ordering::Less {
+            sub_in_place(&mut t, &self.n);
+        }
+        t
+    }
+
+    /// base^exponent for a base in Montgomery form; the result is in Montgomery form.
+    pub fn pow(&self, base: &[u64], exponent: &[u8]) -> Vec<u64> {
+        let mut result = self.one();
+        for byte in exponent {
+            for bit in (0..8).rev() {
+                result = self.mul(&result, &result);
+                if (byte >> bit) & 1 == 1 {
+                    result = self.mul(&result, base);
+                }
+            }
+        }
+        result
+    }
+
+    /// Multiplicative inverse by Fermat's little theorem, so n must be prime.
+    pub fn inv(&self, a: &[u64]) -> Vec<u64> {
+        let mut exponent = self.n.clone();
+        sub_in_place(&mut exponent, &[2]);
+        let exponent: Vec<u8> = exponent
+            .iter()
+            .rev()
+            .flat_map(|l| l.to_be_bytes())
+            .collect();
+        self.pow(a, &exponent)
+    }
+}
+
+fn limbs_from_be(bytes: &[u8]) -> Vec<u64> {
+    let mut limbs: Vec<u64> = bytes
+        .rchunks(8)
+        .map(|chunk| {
+            let mut limb = [0u8; 8];
+            limb[8 - chunk.len()..].copy_from_slice(chunk);
+            u64::from_be_bytes(limb)
+        })
+        .collect();
+    if limbs.is_empty() {
+        limbs.push(0);
+    }
+    limbs
+}
+
+fn compare(a: &[u64], b: &[u64]) -> std::cmp::Ordering {
+    for (x, y) in a.iter().rev().zip(b.iter().rev()) {
+        match x.cmp(y) {
+            std::cmp::Ordering::Equal => continue,
+            other => return other,
+        }
+    }
+    std::cmp::Ordering::Equal
+}
+
+/// a -= b, treating missing limbs of b as zero. Returns true on borrow.
+fn sub_in_place(a: &mut [u64], b: &[u64]) -> bool {
+    let mut borrow = false;
+    for (i, x) in a.iter_mut().enumerate() {
+        let y = b.get(i).copied().unwrap_or(0);
+        let (d1, b1) = x.overflowing_sub(y);
+        let (d2, b2) = d1.overflowing_sub(borrow as u64);
+        *x = d2;
+        borrow = b1 || b2;
+    }
+    borrow
+}
+
+/// a += b, discarding the final carry.
+fn add_in_place(a: &mut [u64], b: &[u64]) {
+    let mut carry = 0u128;
+    for (x, y) in a.iter_mut().zip(b) {
+        let s = *x as u128 + *y as u128 + carry;
+        *x = s as u64;
+        carry = s >> 64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_modular_arithmetic() {
+        let m = Modulus::from_be_bytes(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xa3])
+            .unwrap(); // 2^72 - 93, a prime
+        assert_eq!(m.len(), 2);
+        assert_eq!(m.bits(), 72);
+        let a = m.reduce(&[0x01, 0, 0, 0, 0, 0, 0, 0, 0, 0]); // 2^72 mod m = 93
+        assert_eq!(a, vec![93, 0]);
+        let a = m.to_mont(&a);
+        let b = m.to_mont(&m.decode(&[0x10, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap()); // 2^68
+                                                                                // 93 * 2^68 mod (2^72 - 93)
+        assert_eq!(
+            m.encode(&m.to_normal(&m.mul(&a, &b))),
+            vec![0xd0, 0, 0, 0, 0, 0, 0, 0x01, 0xd1]
+        );
+        assert_eq!(m.to_normal(&m.mul(&a, &m.inv(&a))), vec![1, 0]);
+        assert_eq!(m.to_normal(&m.pow(&a, &[2])), m.to_normal(&m.mul(&a, &a)));
+        assert_eq!(
+            m.sub(&m.zero(), &m.one_plain()),
+            vec![0xffffffffffffffa2, 0xff]
+        );
+        assert!(m.decode(&m.encode(&m.n)).is_none());
+        assert!(Modulus::from_be_bytes(&[0x10]).is_none());
+    }
+}
@@ -0,0 +1,287 @@
+use std::sync::OnceLock;
+
+use super::{bigint::Modulus, fill_random};
+
+/**
+ * NIST prime curves y^2 = x^3 - 3x + b (FIPS 186-4) used for ECDHE and ECDSA
+ *
+ * Points are kept in Jacobian coordinates (X, Y, Z) with every coordinate in Montgomery form;
+ * Z = 0 is the point at infinity.
+ */
+#[derive(Debug)]
+pub struct Curve {
+    p: Modulus,
+    n: Modulus,
+    b: Vec<u64>,
+    g: Point,
+}
+
+#[derive(Debug, Clone)]
+struct Point {
+    x: Vec<u64>,
+    y: Vec<u64>,
+    z: Vec<u64>,
+}
+
+impl Curve {
+    pub fn p256() -> &'static Curve {
+        static CURVE: OnceLock<Curve> = OnceLock::new();
+        CURVE.get_or_init(|| {
+            Curve::new(
+                "ffffffff00000001000000000000000000000000ffffffffffffffffffffffff",
+                "ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551",
+                "5ac635d8aa3a93e7b3ebbd55769886bc651d06b0cc53b0f63bce3c3e27d2604b",
+                "6b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296",
+                "4fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5",
+            )
+        })
+    }
+
+    pub fn p384() -> &'static Curve {
+        static CURVE: OnceLock<Curve> = OnceLock::new();
+        CURVE.get_or_init(|| {
+            Curve::new(
+                "fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffeffffffff0000000000000000ffffffff",
+                "ffffffffffffffffffffffffffffffffffffffffffffffffc7634d81f4372ddf581a0db248b0a77aecec196accc52973",
+                "b3312fa7e23ee7e4988e056be3f82d19181d9c6efe8141120314088f5013875ac656398d8a2ed19d2a85c8edd3ec2aef",
+                "aa87ca22be8b05378eb1c71ef320ad746e1d3b628ba79b9859f741e082542a385502f25dbf55296c3a545e3872760ab7",
+                "3617de4a96262c6f5d9e98bf9292dc29f8f41dbd289a147ce9da3113b5f0b8c00a60b1ce1d7e819d7a431d7c90ea0e5f",
+            )
+        })
+    }
+
+    fn new(p: &str, n: &str, b: &str, gx: &str, gy: &str) -> Curve {
+        let p = Modulus::from_be_bytes(&from_hex(p)).unwrap();
+        let n = Modulus::from_be_bytes(&from_hex(n)).unwrap();
+        let b = p.to_mont(&p.decode(&from_hex(b)).unwrap());
+        let g = Point {
+            x: p.to_mont(&p.decode(&from_hex(gx)).unwrap()),
+            y: p.to_mont(&p.decode(&from_hex(gy)).unwrap()),
+            z: p.one(),
+        };
+        Curve { p, n, b, g }
+    }
+
+    /// Size in bytes of a field element or scalar.
+    pub fn byte_len(&self) -> usize {
+        self.p.byte_len()
+    }
+
+    /// Generates an ephemeral key pair for ECDH. Returns the private scalar and the uncompressed public point.
+    pub fn generate_keypair(&self) -> std::io::Result<(Vec<u8>, Vec<u8>)> {
+        loop {
+            let mut secret = vec![0u8; self.n.byte_len()];
+            fill_random(&mut secret)?;
+            match self.n.decode(&secret) {
+                Some(d) if !Modulus::is_zero(&d) => {
+                    let public = self.encode_point(&self.mul(&self.g, &secret));
+                    return Ok((secret, public));
+                }
+                _ => continue,
+            }
+        }
+    }
+
+    /// ECDH: returns the x coordinate of secret * peer, or None if the peer point is invalid.
+    pub fn shared_secret(&self, secret: &[u8], peer: &[u8]) -> Option<Vec<u8>> {
+        let peer = self.decode_point(peer)?;
+        let shared = self.mul(&peer, secret);
+        let (x, _) = self.to_affine(&shared)?;
+        Some(self.p.encode(&self.p.to_normal(&x)))
+    }
+
+    /// ECDSA verification of (r, s) over an already hashed message with the uncompressed public key.
+    pub fn verify(&self, public_key: &[u8], digest: &[u8], r: &[u8], s: &[u8]) -> bool {
+        let (Some(q), Some(r), Some(s)) = (
+            self.decode_point(public_key),
+            self.n.decode(r),
+            self.n.decode(s),
+        ) else {
+            return false;
+        };
+        if Modulus::is_zero(&r) || Modulus::is_zero(&s) {
+            return false;
+        }
+        // use the leftmost bits of the digest when it is longer than the group order
+        let digest = &digest[..digest.len().min(self.n.byte_len())];
+        let e = self.n.to_mont(&self.n.reduce(digest));
+        let w = self.n.inv(&self.n.to_mont(&s));
+        let u1 = self.n.encode(&self.n.to_normal(&self.n.mul(&e, &w)));
+        let u2 = self
+            .n
+            .encode(&self.n.to_normal(&self.n.mul(&self.n.to_mont(&r), &w)));
+        let point = self.add(&self.mul(&self.g, &u1), &self.mul(&q, &u2));
+        match self.to_affine(&point) {
+            Some((x, _)) => {
+                let x = self.p.encode(&self.p.to_normal(&x));
+                self.n.reduce(&x) == r
+            }
+            None => false,
+        }
+    }
+
+    /// Decodes an uncompressed point (0x04 || x || y) and checks that it lies on the curve.
+    fn decode_point(&self, bytes: &[u8]) -> Option<Point> {
+        let len = self.byte_len();
+        if bytes.len() != 1 + 2 * len || bytes[0] != 0x04 {
+            return None;
+        }
+        let x = self.p.to_mont(&self.p.decode(&bytes[1..1 + len])?);
+        let y = self.p.to_mont(&self.p.decode(&bytes[1 + len..])?);
+        let p = &self.p;
+        let y2 = p.mul(&y, &y);
+        let x3 = p.mul(&p.mul(&x, &x), &x);
+        let three_x = p.add(&p.add(&x, &x), &x);
+        if y2 != p.add(&p.sub(&x3, &three_x), &self.b) {
+            return None;
+        }
+        Some(Point { x, y, z: p.one() })
+    }
+
+    fn encode_point(&self, point: &Point) -> Vec<u8> {
+        let (x, y) = self.to_affine(point).expect("public key is never infinity");
+        let mut bytes = vec![0x04];
+        bytes.extend(self.p.encode(&self.p.to_normal(&x)));
+        bytes.extend(self.p.encode(&self.p.to_normal(&y)));
+        bytes
+    }
+
+    fn to_affine(&self, point: &Point) -> Option<(Vec<u64>, Vec<u64>)> {
+        if Modulus::is_zero(&point.z) {
+            return None;
+        }
+        let p = &self.p;
+        let z_inv = p.inv(&point.z);
+        let z_inv2 = p.mul(&z_inv, &z_inv);
+        let x = p.mul(&point.x, &z_inv2);
+        let y = p.mul(&point.y, &p.mul(&z_inv2, &z_inv));
+        Some((x, y))
+    }
+
+    fn infinity(&self) -> Point {
+        Point {
+            x: self.p.one(),
+            y: self.p.one(),
+            z: self.p.zero(),
+        }
+    }
+
+    fn double(&self, point: &Point) -> Point {
+        if Modulus::is_zero(&point.z) {
+            return point.clone();
+        }
+        let p = &self.p;
+        let delta = p.mul(&point.z, &point.z);
+        let gamma = p.mul(&point.y, &point.y);
+        let beta = p.mul(&point.x, &gamma);
+        let t = p.mul(&p.sub(&point.x, &delta), &p.add(&point.x, &delta));
+        let alpha = p.add(&p.add(&t, &t), &t);
+        let beta2 = p.add(&beta, &beta);
+        let beta4 = p.add(&beta2, &beta2);
+        let beta8 = p.add(&beta4, &beta4);
+        let x = p.sub(&p.mul(&alpha, &alpha), &beta8);
+        let yz = p.add(&point.y, &point.z);
+        let z = p.sub(&p.sub(&p.mul(&yz, &yz), &gamma), &delta);
+        let gamma2 = p.mul(&gamma, &gamma);
+        let gamma2_2 = p.add(&gamma2, &gamma2);
+        let gamma2_4 = p.add(&gamma2_2, &gamma2_2);
+        let gamma2_8 = p.add(&gamma2_4, &gamma2_4);
+        let y = p.sub(&p.mul(&alpha, &p.sub(&beta4, &x)), &gamma2_8);
+        Point { x, y, z }
+    }
+
+    fn add(&self, a: &Point, b: &Point) -> Point {
+        if Modulus::is_zero(&a.z) {
+            return b.clone();
+        }
+        if Modulus::is_zero(&b.z) {
+            return a.clone();
+        }
+        let p = &self.p;
+        let z1z1 = p.mul(&a.z, &a.z);
+        let z2z2 = p.mul(&b.z, &b.z);
+        let u1 = p.mul(&a.x, &z2z2);
+        let u2 = p.mul(&b.x, &z1z1);
+        let s1 = p.mul(&p.mul(&a.y, &b.z), &z2z2);
+        let s2 = p.mul(&p.mul(&b.y, &a.z), &z1z1);
+        let h = p.sub(&u2, &u1);
+        let r = p.sub(&s2, &s1);
+        if Modulus::is_zero(&h) {
+            return if Modulus::is_zero(&r) {
+                self.double(a)
+            } else {
+                self.infinity()
+            };
+        }
+        let r = p.add(&r, &r);
+        let h2 = p.add(&h, &h);
+        let i = p.mul(&h2, &h2);
+        let j = p.mul(&h, &i);
+        let v = p.mul(&u1, &i);
+        let x = p.sub(&p.sub(&p.mul(&r, &r), &j), &p.add(&v, &v));
+        let s1j = p.mul(&s1, &j);
+        let y = p.sub(&p.mul(&r, &p.sub(&v, &x)), &p.add(&s1j, &s1j));
+        let zz = p.add(&a.z, &b.z);
+        let z = p.mul(&p.sub(&p.sub(&p.mul(&zz, &zz), &z1z1), &z2z2), &h);
+        Point { x, y, z }
+    }
+
+    fn mul(&self, point: &Point, scalar: &[u8]) -> Point {
+        let mut result = self.infinity();
+        for byte in scalar {
+            for bit in (0..8).rev() {
+                result = self.double(&result);
+                if (byte >> bit) & 1 == 1 {
+                    result = self.add(&result, point);
+                }
+            }
+        }
+        result
+    }
+}
+
+fn from_hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{sha2::Sha256, tests::hex, HashAlgorithm};
+
+    #[test]
+    fn test_p256_ecdh() {
+        let curve = Curve::p256();
+        let (a_secret, a_public) = curve.generate_keypair().unwrap();
+        let (b_secret, b_public) = curve.generate_keypair().unwrap();
+        let shared = curve.shared_secret(&a_secret, &b_public).unwrap();
+        assert_eq!(shared.len(), 32);
+        assert_eq!(curve.shared_secret(&b_secret, &a_public), Some(shared));
+
+        let mut off_curve = a_public.clone();
+        off_curve[64] ^= 1;
+        assert_eq!(curve.shared_secret(&b_secret, &off_curve), None);
+    }
+
+    #[test]
+    fn test_ecdsa_verify() {
+        let public_key = hex("0484c48f38b1f5718a338760bf0e420b975f33ad7ec4a7b17c41ef99b42df999d4e6fca1ff0e4fa3b30f555613a8c76e53d4c8e8ae28618ea87c0f5ab974f678fc");
+        let r = hex("06ba5302c8c39f72372573eaed71963f6877e698d957cbbdedd8234bfe1c23de");
+        let s = hex("f4dd2318a7604fecc36a759d8c98b35f4854212286c877bedf7e96ddd08c3454");
+        let digest = Sha256::digest(b"hello");
+        assert!(Curve::p256().verify(&public_key, &digest, &r, &s));
+        assert!(!Curve::p256().verify(&public_key, &Sha256::digest(b"hellO"), &r, &s));
+        assert!(!Curve::p256().verify(&public_key, &digest, &s, &r));
+        assert!(!Curve::p256().verify(&public_key, &digest, &[0; 32], &s));
+
+        let public_key = hex("04d321d731582b75e5ff429ed48d1e16300686e423565137235b7c0382f1f3b9c03244905d2fd33d64906c889d4b3a39a51fe4431a6bfe575561414b0a63d4ce91dffcf5177c028ec7f3b9adb473e5e1baaee1faef6feb437f5f752c768b7a3984");
+        let r = hex("987d4f1767c54395a791fcc0861b568784a4737abfb21c687745c304cacd7fb6e49f1429744b881e0e90ed7c6b164490");
+        let s = hex("b324ba155a5800f4207009818e5fcf9d1799a83f811341d5f2eec1b666db109555b16b3ab02d8b8c391e4a99361ed4f5");
+        let digest = HashAlgorithm::Sha384.digest(&[b"hello"]);
+        assert!(Curve::p384().verify(&public_key, &digest, &r, &s));
+        assert!(!Curve::p384().verify(&public_key, &digest[1..], &r, &s));
+    }
+}
@@ -0,0 +1,202 @@
+use std::sync::OnceLock;
+
+use super::{bigint::Modulus, sha2::Sha512, x25519::field};
+
+/**
+ * Ed25519 signature verification (RFC 8032 section 5.1)
+ *
+ * Points are in extended coordinates (X : Y : Z : T) with x = X/Z, y = Y/Z and x * y = T/Z, with
+ * the coordinates in Montgomery form.
+ */
+#[derive(Debug, Clone)]
+struct Point {
+    x: Vec<u64>,
+    y: Vec<u64>,
+    z: Vec<u64>,
+    t: Vec<u64>,
+}
+
+struct Constants {
+    /// 2 * d, where d = -121665/121666 is the curve parameter
+    d2: Vec<u64>,
+    d: Vec<u64>,
+    /// A square root of -1, 2^((p-1)/4)
+    sqrt_m1: Vec<u64>,
+    base: Point,
+    /// Order L of the base point
+    order: Modulus,
+}
+
+fn constants() -> &'static Constants {
+    static CONSTANTS: OnceLock<Constants> = OnceLock::new();
+    CONSTANTS.get_or_init(|| {
+        let p = field();
+        let small = |n: u32| p.to_mont(&p.reduce(&n.to_be_bytes()));
+        let d = p.sub(&p.zero(), &p.mul(&small(121665), &p.inv(&small(121666))));
+        // (p - 1) / 4 = 2^253 - 5
+        let mut exponent = [0xff; 32];
+        exponent[0] = 0x1f;
+        exponent[31] = 0xfb;
+        let sqrt_m1 = p.pow(&small(2), &exponent);
+        let mut order = [0u8; 32];
+        order[0] = 0x10;
+        order[16..].copy_from_slice(&[
+            0x14, 0xde, 0xf9, 0xde, 0xa2, 0xf7, 0x9c, 0xd6, 0x58, 0x12, 0x63, 0x1a, 0x5c, 0xf5,
+            0xd3, 0xed,
+        ]);
+        let mut constants = Constants {
+            d2: p.add(&d, &d),
+            d,
+            sqrt_m1,
+            base: identity(),
+            order: Modulus::from_be_bytes(&order).unwrap(),
+        };
+        // y = 4/5 with an even x
+        let mut base = [0x66; 32];
+        base[0] = 0x58;
+        constants.base = decode_point(&constants, &base).unwrap();
+        constants
+    })
+}
+
+fn identity() -> Point {
+    let p = field();
+    Point {
+        x: p.zero(),
+        y: p.one(),
+        z: p.one(),
+        t: p.zero(),
+    }
+}
+
+/// Adds two points, which may be the same (RFC 8032 section 5.1.4).
+fn add(c: &Constants, a: &Point, b: &Point) -> Point {
+    let p = field();
+    let aa = p.mul(&p.sub(&a.y, &a.x), &p.sub(&b.y, &b.x));
+    let bb = p.mul(&p.add(&a.y, &a.x), &p.add(&b.y, &b.x));
+    let cc = p.mul(&p.mul(&a.t, &c.d2), &b.t);
+    let z = p.mul(&a.z, &b.z);
+    let dd = p.add(&z, &z);
+    let (e, f, g, h) = (
+        p.sub(&bb, &aa),
+        p.sub(&dd, &cc),
+        p.add(&dd, &cc),
+        p.add(&bb, &aa),
+    );
+    Point {
+        x: p.mul(&e, &f),
+        y: p.mul(&g, &h),
+        z: p.mul(&f, &g),
+        t: p.mul(&e, &h),
+    }
+}
+
+/// Multiplies `point` by a big-endian scalar.
+fn mul(c: &Constants, point: &Point, scalar: &[u8]) -> Point {
+    let mut result = identity();
+    for byte in scalar {
+        for bit in (0..8).rev() {
+            result = add(c, &result, &result);
+            if (byte >> bit) & 1 == 1 {
+                result = add(c, &result, point);
+            }
+        }
+    }
+    result
+}
+
+fn equal(a: &Point, b: &Point) -> bool {
+    let p = field();
+    p.mul(&a.x, &b.z) == p.mul(&b.x, &a.z) && p.mul(&a.y, &b.z) == p.mul(&b.y, &a.z)
+}
+
+/// Decodes a point from the y-coordinate and the sign of x (RFC 8032 section 5.1.3).
+fn decode_point(c: &Constants, bytes: &[u8]) -> Option<Point> {
+    let p = field();
+    let mut y: [u8; 32] = bytes.try_into().ok()?;
+    let x_odd = y[31] >> 7 == 1;
+    y[31] &= 0x7f;
+    y.reverse();
+    let y = p.to_mont(&p.decode(&y)?);
+    let yy = p.mul(&y, &y);
+    let u = p.sub(&yy, &p.one());
+    let v = p.add(&p.mul(&c.d, &yy), &p.one());
+    // x = u * v^3 * (u * v^7)^((p - 5) / 8), where (p - 5) / 8 = 2^252 - 3
+    let v3 = p.mul(&p.mul(&v, &v), &v);
+    let v7 = p.mul(&p.mul(&v3, &v3), &v);
+    let mut exponent = [0xff; 32];
+    exponent[0] = 0x0f;
+    exponent[31] = 0xfd;
+    let mut x = p.mul(&p.mul(&u, &v3), &p.pow(&p.mul(&u, &v7), &exponent));
+    let vxx = p.mul(&v, &p.mul(&x, &x));
+    if vxx != u {
+        if vxx != p.sub(&p.zero(), &u) {
+            return None;
+        }
+        x = p.mul(&x, &c.sqrt_m1);
+    }
+    let normal = p.to_normal(&x);
+    if Modulus::is_zero(&normal) && x_odd {
+        return None;
+    }
+    if (normal[0] & 1 == 1) != x_odd {
+        x = p.sub(&p.zero(), &x);
+    }
+    Some(Point {
+        t: p.mul(&x, &y),
+        x,
+        y,
+        z: p.one(),
+    })
+}
+
+/// Verifies the 64 byte `signature` of `message` with the 32 byte `public_key`.
+pub fn verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    let c = constants();
+    if signature.len() != 64 {
+        return false;
+    }
+    let (Some(a), Some(r)) = (
+        decode_point(c, public_key),
+        decode_point(c, &signature[..32]),
+    ) else {
+        return false;
+    };
+    let mut s = signature[32..].to_vec();
+    s.reverse();
+    if c.order.decode(&s).is_none() {
+        return false;
+    }
+    let mut hasher = Sha512::new();
+    hasher.update(&signature[..32]);
+    hasher.update(public_key);
+    hasher.update(message);
+    let mut k = hasher.finish();
+    k.reverse();
+    let k = c.order.encode(&c.order.reduce(&k));
+    // [S]B = R + [k]A
+    let left = mul(c, &c.base, &s);
+    let right = add(c, &r, &mul(c, &a, &k));
+    equal(&left, &right)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::tests::hex;
+
+    #[test]
+    fn test_ed25519_rfc8032() {
+        let public_key = hex("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a");
+        let signature = hex("e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b");
+        assert!(verify(&public_key, b"", &signature));
+        assert!(!verify(&public_key, b"x", &signature));
+
+        let public_key = hex("3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c");
+        let signature = hex("92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00");
+        assert!(verify(&public_key, &[0x72], &signature));
+        let mut tampered = signature.clone();
+        tampered[63] ^= 1;
+        assert!(!verify(&public_key, &[0x72], &tampered));
+    }
+}
@@ -0,0 +1,122 @@
+use super::{aes::Aes, constant_time_eq};
+
+pub const TAG_LEN: usize = 16;
+pub const NONCE_LEN: usize = 12;
+
+/**
+ * AES in Galois/Counter Mode (NIST SP 800-38D) with 96-bit nonces and 128-bit tags
+ */
+#[derive(Debug, Clone)]
+pub struct AesGcm {
+    aes: Aes,
+    h: u128,
+}
+
+impl AesGcm {
+    pub fn new(key: &[u8]) -> Option<Self> {
+        let aes = Aes::new(key)?;
+        let mut h = [0u8; 16];
+        aes.encrypt_block(&mut h);
+        Some(Self {
+            aes,
+            h: u128::from_be_bytes(h),
+        })
+    }
+
+    /// Encrypts `plaintext` and returns the ciphertext followed by the authentication tag.
+    pub fn seal(&self, nonce: &[u8; NONCE_LEN], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let mut output = plaintext.to_vec();
+        self.apply_keystream(nonce, &mut output);
+        let tag = self.tag(nonce, aad, &output);
+        output.extend_from_slice(&tag);
+        output
+    }
+
+    /// Verifies and decrypts `ciphertext` (with the tag appended). Returns None when authentication fails.
+    pub fn open(&self, nonce: &[u8; NONCE_LEN], aad: &[u8], ciphertext: &[u8]) -> Option<Vec<u8>> {
+        if ciphertext.len() < TAG_LEN {
+            return None;
+        }
+        let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - TAG_LEN);
+        if !constant_time_eq(&self.tag(nonce, aad, ciphertext), tag) {
+            return None;
+        }
+        let mut output = ciphertext.to_vec();
+        self.apply_keystream(nonce, &mut output);
+        Some(output)
+    }
+
+    fn counter_block(nonce: &[u8; NONCE_LEN], counter: u32) -> [u8; 16] {
+        let mut block = [0u8; 16];
+        block[..NONCE_LEN].copy_from_slice(nonce);
+        block[NONCE_LEN..].copy_from_slice(&counter.to_be_bytes());
+        block
+    }
+
+    fn apply_keystream(&self, nonce: &[u8; NONCE_LEN], data: &mut [u8]) {
+        for (i, chunk) in data.chunks_mut(16).enumerate() {
+            // counter 1 is reserved for the tag
+            let mut keystream = Self::counter_block(nonce, i as u32 + 2);
+            self.aes.encrypt_block(&mut keystream);
+            for (b, k) in chunk.iter_mut().zip(keystream) {
+                *b ^= k;
+            }
+        }
+    }
+
+    fn tag(&self, nonce: &[u8; NONCE_LEN], aad: &[u8], ciphertext: &[u8]) -> [u8; TAG_LEN] {
+        let mut y = 0u128;
+        for data in [aad, ciphertext] {
+            for chunk in data.chunks(16) {
+                let mut block = [0u8; 16];
+                block[..chunk.len()].copy_from_slice(chunk);
+                y = gf_mul(y ^ u128::from_be_bytes(block), self.h);
+            }
+        }
+        let lengths = ((aad.len() as u128 * 8) << 64) | (ciphertext.len() as u128 * 8);
+        y = gf_mul(y ^ lengths, self.h);
+        let mut mask = Self::counter_block(nonce, 1);
+        self.aes.encrypt_block(&mut mask);
+        (y ^ u128::from_be_bytes(mask)).to_be_bytes()
+    }
+}
+
+/// Multiplication in GF(2^128) using GCM's reflected bit order.
+fn gf_mul(x: u128, y: u128) -> u128 {
+    const R: u128 = 0xe1 << 120;
+    let mut z = 0;
+    let mut v = y;
+    for i in 0..128 {
+        if (x >> (127 - i)) & 1 == 1 {
+            z ^= v;
+        }
+        v = if v & 1 == 1 { (v >> 1) ^ R } else { v >> 1 };
+    }
+    z
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::tests::hex;
+
+    #[test]
+    fn test_aes_gcm() {
+        // test case 4 of the original GCM specification
+        let gcm = AesGcm::new(&hex("feffe9928665731c6d6a8f9467308308")).unwrap();
+        let nonce: [u8; 12] = hex("cafebabefacedbaddecaf888").try_into().unwrap();
+        let aad = hex("feedfacedeadbeeffeedfacedeadbeefabaddad2");
+        let plaintext = hex("d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39");
+        let sealed = gcm.seal(&nonce, &aad, &plaintext);
+        assert_eq!(
+            sealed,
+            hex("42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e0915bc94fbc3221a5db94fae95ae7121a47")
+        );
+        assert_eq!(gcm.open(&nonce, &aad, &sealed), Some(plaintext));
+
+        let mut tampered = sealed.clone();
+        tampered[0] ^= 1;
+        assert_eq!(gcm.open(&nonce, &aad, &tampered), None);
+        assert_eq!(gcm.open(&nonce, b"", &sealed), None);
+    }
+}
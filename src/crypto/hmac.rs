@@ -0,0 +1,93 @@
+use super::HashAlgorithm;
+
+/// HMAC (RFC 2104)
+pub fn hmac(hash: HashAlgorithm, key: &[u8], message: &[&[u8]]) -> Vec<u8> {
+    let mut key = if key.len() > hash.block_len() {
+        hash.digest(&[key])
+    } else {
+        key.to_vec()
+    };
+    key.resize(hash.block_len(), 0);
+    let ipad: Vec<u8> = key.iter().map(|b| b ^ 0x36).collect();
+    let opad: Vec<u8> = key.iter().map(|b| b ^ 0x5c).collect();
+    let mut inner_parts: Vec<&[u8]> = vec![&ipad];
+    inner_parts.extend_from_slice(message);
+    let inner = hash.digest(&inner_parts);
+    hash.digest(&[&opad, &inner])
+}
+
+/// HKDF-Extract (RFC 5869)
+pub fn hkdf_extract(hash: HashAlgorithm, salt: &[u8], ikm: &[u8]) -> Vec<u8> {
+    let zeros;
+    let salt = if salt.is_empty() {
+        zeros = vec![0; hash.output_len()];
+        &zeros
+    } else {
+        salt
+    };
+    hmac(hash, salt, &[ikm])
+}
+
+/// HKDF-Expand (RFC 5869)
+pub fn hkdf_expand(hash: HashAlgorithm, prk: &[u8], info: &[u8], length: usize) -> Vec<u8> {
+    let mut okm = Vec::with_capacity(length);
+    let mut previous = vec![];
+    let mut counter = 1u8;
+    while okm.len() < length {
+        previous = hmac(hash, prk, &[&previous, info, &[counter]]);
+        okm.extend_from_slice(&previous);
+        counter += 1;
+    }
+    okm.truncate(length);
+    okm
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::tests::hex;
+
+    #[test]
+    fn test_hmac() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            hmac(
+                HashAlgorithm::Sha256,
+                b"Jefe",
+                &[b"what do ya want ", b"for nothing?"]
+            ),
+            hex("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843")
+        );
+        assert_eq!(
+            hmac(HashAlgorithm::Sha384, b"Jefe", &[b"what do ya want for nothing?"]),
+            hex("af45d2e376484031617f78d2b58a6b1b9c7ef464f5a01b47e42ec3736322445e8e2240ca5e69e2c78b3239ecfab21649")
+        );
+        // RFC 4231 test case 6: key longer than the block size
+        assert_eq!(
+            hmac(
+                HashAlgorithm::Sha256,
+                &[0xaa; 131],
+                &[b"Test Using Larger Than Block-Size Key - Hash Key First"]
+            ),
+            hex("60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54")
+        );
+    }
+
+    #[test]
+    fn test_hkdf() {
+        // RFC 5869 test case 1
+        let prk = hkdf_extract(
+            HashAlgorithm::Sha256,
+            &hex("000102030405060708090a0b0c"),
+            &[0x0b; 22],
+        );
+        assert_eq!(
+            prk,
+            hex("077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5")
+        );
+        assert_eq!(
+            hkdf_expand(HashAlgorithm::Sha256, &prk, &hex("f0f1f2f3f4f5f6f7f8f9"), 42),
+            hex("3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865")
+        );
+    }
+}
@@ -0,0 +1,85 @@
+//!
+//! Cryptographic primitives needed by the TLS client and DNSSEC, implemented without 3rd party
+//! libraries.
+//!
+//! These implementations favor readability over speed and are not hardened against timing
+//! side channels.
+//!
+pub mod aes;
+pub mod bigint;
+pub mod ec;
+pub mod ed25519;
+pub mod gcm;
+pub mod hmac;
+pub mod rsa;
+pub mod sha1;
+pub mod sha2;
+pub mod x25519;
+
+use std::{fs::File, io::Read};
+
+use self::sha2::{Sha256, Sha512};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl HashAlgorithm {
+    pub fn output_len(&self) -> usize {
+        match self {
+            HashAlgorithm::Sha256 => 32,
+            HashAlgorithm::Sha384 => 48,
+            HashAlgorithm::Sha512 => 64,
+        }
+    }
+
+    pub fn block_len(&self) -> usize {
+        match self {
+            HashAlgorithm::Sha256 => Sha256::BLOCK_LEN,
+            HashAlgorithm::Sha384 | HashAlgorithm::Sha512 => Sha512::BLOCK_LEN,
+        }
+    }
+
+    /// Hashes the concatenation of `parts`.
+    pub fn digest(&self, parts: &[&[u8]]) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Sha256 => {
+                let mut hasher = Sha256::new();
+                parts.iter().for_each(|part| hasher.update(part));
+                hasher.finish()
+            }
+            HashAlgorithm::Sha384 | HashAlgorithm::Sha512 => {
+                let mut hasher = match self {
+                    HashAlgorithm::Sha384 => Sha512::new_384(),
+                    _ => Sha512::new(),
+                };
+                parts.iter().for_each(|part| hasher.update(part));
+                hasher.finish()
+            }
+        }
+    }
+}
+
+/// Fills `buf` with random bytes from the operating system.
+pub fn fill_random(buf: &mut [u8]) -> std::io::Result<()> {
+    // TODO: this should not work on Windows
+    File::open("/dev/urandom")?.read_exact(buf)
+}
+
+/// Compares two byte strings without returning early on the first difference.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    pub fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+}
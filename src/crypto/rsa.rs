@@ -0,0 +1,149 @@
+use super::{bigint::Modulus, constant_time_eq, HashAlgorithm};
+
+/// Smallest modulus accepted for signature verification.
+const MIN_MODULUS_BITS: usize = 1024;
+
+/**
+ * RSA public key for signature verification (RFC 8017)
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RsaPublicKey {
+    n: Modulus,
+    e: Vec<u8>,
+}
+
+impl RsaPublicKey {
+    pub fn new(n: &[u8], e: &[u8]) -> Option<Self> {
+        let n = Modulus::from_be_bytes(n)?;
+        let e: Vec<u8> = e.iter().copied().skip_while(|b| *b == 0).collect();
+        if n.bits() < MIN_MODULUS_BITS || e.is_empty() || e[e.len() - 1] & 1 == 0 {
+            return None;
+        }
+        Some(Self { n, e })
+    }
+
+    /// RSAVP1: signature^e mod n as big-endian bytes of the modulus length.
+    fn public_op(&self, signature: &[u8]) -> Option<Vec<u8>> {
+        if signature.len() != self.n.byte_len() {
+            return None;
+        }
+        let s = self.n.to_mont(&self.n.decode(signature)?);
+        let m = self.n.to_normal(&self.n.pow(&s, &self.e));
+        Some(self.n.encode(&m))
+    }
+
+    /// RSASSA-PKCS1-v1_5 verification
+    pub fn verify_pkcs1v15(&self, hash: HashAlgorithm, message: &[u8], signature: &[u8]) -> bool {
+        let Some(em) = self.public_op(signature) else {
+            return false;
+        };
+        let digest_info = digest_info_prefix(hash);
+        let t_len = digest_info.len() + hash.output_len();
+        if em.len() < t_len + 11 {
+            return false;
+        }
+        let mut expected = vec![0x00, 0x01];
+        expected.resize(em.len() - t_len - 1, 0xff);
+        expected.push(0x00);
+        expected.extend_from_slice(digest_info);
+        expected.extend(hash.digest(&[message]));
+        constant_time_eq(&em, &expected)
+    }
+
+    /// RSASSA-PSS verification with MGF1 using the same hash and a salt as long as the hash
+    pub fn verify_pss(&self, hash: HashAlgorithm, message: &[u8], signature: &[u8]) -> bool {
+        let Some(em) = self.public_op(signature) else {
+            return false;
+        };
+        let h_len = hash.output_len();
+        let s_len = h_len;
+        let em_bits = self.n.bits() - 1;
+        let em_len = em_bits.div_ceil(8);
+        // the encoded message is one byte shorter than the modulus when em_bits is a multiple of 8
+        let (leading, em) = em.split_at(em.len() - em_len);
+        if leading.iter().any(|b| *b != 0) || em_len < h_len + s_len + 2 || em[em_len - 1] != 0xbc {
+            return false;
+        }
+        let (masked_db, h) = em[..em_len - 1].split_at(em_len - h_len - 1);
+        let unused_bits = 8 * em_len - em_bits;
+        let top_mask = 0xffu8.checked_shr(unused_bits as u32).unwrap_or(0);
+        if masked_db[0] & !top_mask != 0 {
+            return false;
+        }
+        let mut db: Vec<u8> = masked_db
+            .iter()
+            .zip(mgf1(hash, h, masked_db.len()))
+            .map(|(a, b)| a ^ b)
+            .collect();
+        db[0] &= top_mask;
+        let ps_len = em_len - h_len - s_len - 2;
+        if db[..ps_len].iter().any(|b| *b != 0) || db[ps_len] != 0x01 {
+            return false;
+        }
+        let salt = &db[ps_len + 1..];
+        let m_hash = hash.digest(&[message]);
+        let expected = hash.digest(&[&[0; 8], &m_hash, salt]);
+        constant_time_eq(h, &expected)
+    }
+}
+
+fn mgf1(hash: HashAlgorithm, seed: &[u8], length: usize) -> Vec<u8> {
+    let mut mask = Vec::with_capacity(length + hash.output_len());
+    let mut counter = 0u32;
+    while mask.len() < length {
+        mask.extend(hash.digest(&[seed, &counter.to_be_bytes()]));
+        counter += 1;
+    }
+    mask.truncate(length);
+    mask
+}
+
+/// DER encoding of DigestInfo up to the digest itself (RFC 8017 section 9.2)
+fn digest_info_prefix(hash: HashAlgorithm) -> &'static [u8] {
+    match hash {
+        HashAlgorithm::Sha256 => &[
+            0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02,
+            0x01, 0x05, 0x00, 0x04, 0x20,
+        ],
+        HashAlgorithm::Sha384 => &[
+            0x30, 0x41, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02,
+            0x02, 0x05, 0x00, 0x04, 0x30,
+        ],
+        HashAlgorithm::Sha512 => &[
+            0x30, 0x51, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02,
+            0x03, 0x05, 0x00, 0x04, 0x40,
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::tests::hex;
+
+    fn public_key() -> RsaPublicKey {
+        let n = hex("a8c1362662222e37c7742d3413bcb55b4151002b5c2c3ac71fd33f5c1d86a0a226098111a6c8cf33f4bb20068f507dff889a29199b91c45c772106a57263aaa1cf446bc2539fffcfcd921fdb31315cb0de3f353fd3335cbd00d83b5245b8ef00cf94943c7cc3a54e76ed0bc370e38539b28d7d615307b4bc2c189e328f8426f796748c77eca21ef688b9ad88c2697e60ed50ef9c11c5f2ae93c89c8b2ec2226b746c17ea6d3ed09a2811aa330003ff0047bdc60f0779d976bc76664c92b76fff75d96d598e56e07803af8cf238c302dcdc68536e0519db07a61984e52ebf9a4627821c57964d62795825be20b0d686823ca1f3c5ae3443a6512c03d5200e7841");
+        RsaPublicKey::new(&n, &[0x01, 0x00, 0x01]).unwrap()
+    }
+
+    #[test]
+    fn test_rsa_pkcs1v15_verify() {
+        let signature = hex("7161cdc99d2e4ca6e4ae783238cafd7ada7aacb2aba019f958891fff1c7c7f5655be9f93dcf4c2f6fa4b2026fea1bbf6e02f58caba892f9970afba5d1ab1c783cbccb75253a7130a6745b617ffbe81511e7cecb5fea38859fb09bfe428f6c760e0b94e11446d9dcb11ce762befe0094cb56d59e7cefb6b9e1a0d420b1cda701cb0377efe2edfd205b8913662e4152e3fcb72eb0d2e069a2ee6de2f57431fc24dba361ac21f5878227dc313f995508418eba96fb8ae20348b4b93d6283fdc2a97ce259303d7e0443ab3dcd1a5b4cc6d2435b20f913e2b684676d35578e0f4c93b120c908dd9f258d94147a3889f2772d71d3d8bbb944249cac858e0a525179a1e");
+        let key = public_key();
+        assert_eq!(key.n.bits(), 2048);
+        assert!(key.verify_pkcs1v15(HashAlgorithm::Sha256, b"hello", &signature));
+        assert!(!key.verify_pkcs1v15(HashAlgorithm::Sha256, b"hellO", &signature));
+        assert!(!key.verify_pkcs1v15(HashAlgorithm::Sha384, b"hello", &signature));
+        assert!(!key.verify_pss(HashAlgorithm::Sha256, b"hello", &signature));
+    }
+
+    #[test]
+    fn test_rsa_pss_verify() {
+        let signature = hex("7ac5ffcecf242f6eb763b42aa3d48a307fbcb11038e088d761d487138c0f19184e8c4ec19b106e403fc6b60a89d8262ff019ba998476bb51740f1d1e22112f07b61493da65e9d03d4e238af54143bb775871b39d12e323ecc3918e3fb5264ab6a3aa377a72576774ab3aa4b3ea46428eeab314155e45b546a1a5116d6d54a86731b075410fdd09c66ab2269f3fd12919cd317d6b5a319fa20a02fbedd8b3d46f951fcfe54eacde13b8d1f70b30e90094395298920d60c9a6c211d1ac85223f5382d291d0f1fcfbf22962003e77f204553f6ab021f01613bd53de500c470316ba9172d5d061392502fd7b9dde6ab44830182684124629f98a101960eff25a13ef");
+        let key = public_key();
+        assert!(key.verify_pss(HashAlgorithm::Sha384, b"hello", &signature));
+        assert!(!key.verify_pss(HashAlgorithm::Sha384, b"hellO", &signature));
+        assert!(!key.verify_pss(HashAlgorithm::Sha256, b"hello", &signature));
+        assert!(!key.verify_pss(HashAlgorithm::Sha384, b"hello", &signature[1..]));
+    }
+}
@@ -0,0 +1,97 @@
+use std::sync::OnceLock;
+
+use super::{bigint::Modulus, fill_random};
+
+/**
+ * X25519 Diffie-Hellman function (RFC 7748)
+ */
+pub(super) fn field() -> &'static Modulus {
+    static P: OnceLock<Modulus> = OnceLock::new();
+    P.get_or_init(|| {
+        let mut p = [0xff; 32];
+        p[0] = 0x7f;
+        p[31] = 0xed;
+        Modulus::from_be_bytes(&p).unwrap()
+    })
+}
+
+/// Generates an ephemeral key pair. Returns the private scalar and the public u-coordinate.
+pub fn generate_keypair() -> std::io::Result<([u8; 32], [u8; 32])> {
+    let mut secret = [0u8; 32];
+    fill_random(&mut secret)?;
+    let mut base = [0u8; 32];
+    base[0] = 9;
+    Ok((secret, x25519(&secret, &base)))
+}
+
+pub fn x25519(scalar: &[u8; 32], u: &[u8; 32]) -> [u8; 32] {
+    let p = field();
+    let mut k = *scalar;
+    k[0] &= 248;
+    k[31] &= 127;
+    k[31] |= 64;
+    let mut u = *u;
+    u[31] &= 127;
+    u.reverse();
+    let x1 = p.to_mont(&p.reduce(&u));
+    let a24 = p.to_mont(&p.reduce(&121665u32.to_be_bytes()));
+
+    let (mut x2, mut z2) = (p.one(), p.zero());
+    let (mut x3, mut z3) = (x1.clone(), p.one());
+    let mut swap = false;
+    for t in (0..255).rev() {
+        let bit = (k[t / 8] >> (t % 8)) & 1 == 1;
+        if swap != bit {
+            std::mem::swap(&mut x2, &mut x3);
+            std::mem::swap(&mut z2, &mut z3);
+        }
+        swap = bit;
+
+        let a = p.add(&x2, &z2);
+        let aa = p.mul(&a, &a);
+        let b = p.sub(&x2, &z2);
+        let bb = p.mul(&b, &b);
+        let e = p.sub(&aa, &bb);
+        let c = p.add(&x3, &z3);
+        let d = p.sub(&x3, &z3);
+        let da = p.mul(&d, &a);
+        let cb = p.mul(&c, &b);
+        let sum = p.add(&da, &cb);
+        x3 = p.mul(&sum, &sum);
+        let diff = p.sub(&da, &cb);
+        z3 = p.mul(&x1, &p.mul(&diff, &diff));
+        x2 = p.mul(&aa, &bb);
+        z2 = p.mul(&e, &p.add(&aa, &p.mul(&a24, &e)));
+    }
+    if swap {
+        std::mem::swap(&mut x2, &mut x3);
+        std::mem::swap(&mut z2, &mut z3);
+    }
+    let result = p.to_normal(&p.mul(&x2, &p.inv(&z2)));
+    let mut output: [u8; 32] = p.encode(&result).try_into().unwrap();
+    output.reverse();
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::tests::hex;
+
+    #[test]
+    fn test_x25519_rfc7748() {
+        let scalar = hex("a546e36bf0527c9d3b16154b82465edd62144c0ac1fc5a18506a2244ba449ac4");
+        let u = hex("e6db6867583030db3594c1a424b15f7c726624ec26b3353b10a903a6d0ab1c4c");
+        assert_eq!(
+            x25519(&scalar.try_into().unwrap(), &u.try_into().unwrap()).to_vec(),
+            hex("c3da55379de9c6908e94ea4df28d084f32eccf03491c71f754b4075577a28552")
+        );
+    }
+
+    #[test]
+    fn test_x25519_key_agreement() {
+        let (a_secret, a_public) = generate_keypair().unwrap();
+        let (b_secret, b_public) = generate_keypair().unwrap();
+        assert_eq!(x25519(&a_secret, &b_public), x25519(&b_secret, &a_public));
+    }
+}
@@ -1,44 +1,754 @@
+mod cache;
+mod dnssec;
+mod doh;
+mod dot;
+mod hosts;
+mod resolv_conf;
+
+use std::collections::HashMap;
 use std::fmt::Display;
-use std::net::{IpAddr, Ipv4Addr, SocketAddrV4, UdpSocket};
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, UdpSocket};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub use cache::{Cache, CachedAnswer};
+pub use dnssec::{Dnssec, TrustAnchor};
+pub use doh::Doh;
+pub use dot::{Dot, DOT_PORT};
+pub use hosts::Hosts;
+pub use resolv_conf::ResolvConf;
+
+use crate::{crypto::fill_random, Error};
+
+/// Most CNAME records followed for one name, which also ends loops of aliases
+const MAX_CNAME_CHAIN: usize = 16;
 
 ///
 /// DNS resolver struct that resolve IP address for passed URL
 ///
+#[derive(Debug, Clone)]
 pub struct Resolver {
-    server: SocketAddrV4,
-    client: SocketAddrV4,
+    config: ResolvConf,
+    hosts: Option<Hosts>,
+    cache: Option<Cache>,
+    encrypted: Option<Encrypted>,
+    dnssec: Option<TrustAnchor>,
+    // the server to start with next when the servers are rotated, shared by clones
+    next_server: Arc<AtomicUsize>,
 }
 
 impl Resolver {
-    pub fn new(ip: Option<Ipv4Addr>, port: Option<u16>) -> Self {
+    /// Uses the DNS server at `ip`, which may be an IPv4 or IPv6 address, and `port`.
+    pub fn new(ip: Option<IpAddr>, port: Option<u16>) -> Self {
         let (ip, port) = match (ip, port) {
             (Some(ip), Some(port)) => (ip, port),
             (Some(ip), None) => (ip, 53),
             (None, Some(port)) => ([8, 8, 8, 8].into(), port),
             (None, None) => ([8, 8, 8, 8].into(), 53),
         };
+        Self::with_config(ResolvConf {
+            nameservers: vec![SocketAddr::new(ip, port)],
+            ..ResolvConf::default()
+        })
+    }
+
+    /// Uses the servers and search domains of /etc/resolv.conf, or the defaults of
+    /// `ResolvConf` if it can't be read.
+    pub fn from_system() -> Self {
+        Self::with_config(ResolvConf::load("/etc/resolv.conf").unwrap_or_default())
+    }
+
+    /// Uses the servers and search domains of the resolv.conf file at `path`.
+    pub fn from_resolv_conf(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Ok(Self::with_config(ResolvConf::load(path)?))
+    }
+
+    pub fn with_config(config: ResolvConf) -> Self {
         Self {
-            server: SocketAddrV4::new(ip, port),
-            client: SocketAddrV4::new([0, 0, 0, 0].into(), 0),
+            config,
+            hosts: Some(Hosts::system()),
+            cache: Some(Cache::default()),
+            encrypted: None,
+            dnssec: None,
+            next_server: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn config(&self) -> &ResolvConf {
+        &self.config
+    }
+
+    /// Looks names up in `hosts` instead of /etc/hosts before querying the server, or only
+    /// queries the server if `hosts` is None.
+    pub fn with_hosts(mut self, hosts: Option<Hosts>) -> Self {
+        self.hosts = hosts;
+        self
+    }
+
+    /// Keeps answers in `cache`, which may be shared with other resolvers, or doesn't keep them
+    /// if `cache` is None.
+    pub fn with_cache(mut self, cache: Option<Cache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    pub fn cache(&self) -> Option<&Cache> {
+        self.cache.as_ref()
+    }
+
+    /// Sends queries to a DNS-over-HTTPS server instead of the nameservers, or to the nameservers
+    /// if `doh` is None. The timeout, attempts and search list of the configuration still apply.
+    pub fn with_doh(mut self, doh: Option<Doh>) -> Self {
+        self.encrypted = doh.map(Encrypted::Https);
+        self
+    }
+
+    /// Like `with_doh`, but for a DNS-over-TLS server.
+    pub fn with_dot(mut self, dot: Option<Dot>) -> Self {
+        self.encrypted = dot.map(Encrypted::Tls);
+        self
+    }
+
+    /// Asks for DNSSEC records and validates answers with chains of trust from `anchor`, or
+    /// doesn't if `anchor` is None. EDNS is turned on if the configuration turned it off.
+    ///
+    /// `lookup` reports the result with its records, and fails on bogus proofs that there are
+    /// none. Other lookups fail on all bogus answers, like a validating server would.
+    pub fn with_dnssec(mut self, anchor: Option<TrustAnchor>) -> Self {
+        if anchor.is_some() {
+            let size = self.config.udp_payload_size.unwrap_or(1232);
+            self.config.udp_payload_size = Some(size);
         }
+        self.dnssec = anchor;
+        self
     }
 
-    pub fn resolve(&self, id: u16, host: &str) -> Result<IpAddr, String> {
-        let sock = UdpSocket::bind(self.client).map_err(|err| err.to_string())?;
-        let query = Query::new(id, host);
-        sock.send_to(&Vec::from(query), self.server)
-            .map_err(|err| err.to_string())?;
-        let mut buf = [0; 512];
-        sock.recv_from(&mut buf).map_err(|err| err.to_string())?;
-        let response = Response::try_from(&buf)?;
+    /// Answers without a query for IP literals, names in the hosts file and localhost.
+    fn lookup_static(&self, host: &str) -> Option<Vec<IpAddr>> {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if let Ok(ip) = host.parse() {
+            return Some(vec![ip]);
+        }
+        if let Some(addrs) = self.hosts.as_ref().and_then(|hosts| hosts.lookup(host)) {
+            return Some(addrs);
+        }
+        // localhost names always refer to the loopback interface (RFC 6761 section 6.3)
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        if host == "localhost" || host.ends_with(".localhost") {
+            return Some(vec![Ipv6Addr::LOCALHOST.into(), Ipv4Addr::LOCALHOST.into()]);
+        }
+        None
+    }
 
-        if !response.answers.is_empty() {
-            match response.answers[0].rdata {
-                RData::A(v) => Ok(IpAddr::V4(v.into())),
-                RData::AAAA(_) => unimplemented!(),
+    /// Returns an IPv4 address of `host`, or an IPv6 address if it has none.
+    pub fn resolve(&self, id: u16, host: &str) -> Result<IpAddr, Error> {
+        if let Some(addrs) = self.lookup_static(host) {
+            return Ok(*addrs.iter().find(|ip| ip.is_ipv4()).unwrap_or(&addrs[0]));
+        }
+        self.search(host, |name| match self.query(id, name, QueryType::A) {
+            Ok(lookup) => Ok(lookup.addrs[0]),
+            Err(Error::NoRecords(_)) => {
+                Ok(self.query(id.wrapping_add(1), name, QueryType::AAAA)?.addrs[0])
             }
-        } else {
-            Err("no answers in the DNS response from the server".to_string())
+            Err(e) => Err(e),
+        })
+    }
+
+    /// Returns the IPv6 and then the IPv4 addresses of `host`. The AAAA and A queries are sent
+    /// concurrently with `id` and `id + 1` as transaction IDs.
+    pub fn resolve_all(&self, id: u16, host: &str) -> Result<Vec<IpAddr>, Error> {
+        Ok(self.lookup_ip(id, host)?.addrs)
+    }
+
+    /// Like `resolve_all`, but also returns the canonical name that `host` is an alias of.
+    pub fn lookup_ip(&self, id: u16, host: &str) -> Result<LookupIp, Error> {
+        if let Some(addrs) = self.lookup_static(host) {
+            return Ok(LookupIp {
+                canonical_name: host.trim_end_matches('.').to_ascii_lowercase(),
+                addrs,
+            });
+        }
+        self.search(host, |name| {
+            let (v6, v4) = std::thread::scope(|scope| {
+                let v6 = scope.spawn(|| self.query(id, name, QueryType::AAAA));
+                let v4 = self.query(id.wrapping_add(1), name, QueryType::A);
+                (v6.join().expect("query thread panicked"), v4)
+            });
+            match (v6, v4) {
+                (Ok(mut v6), Ok(v4)) => {
+                    v6.addrs.extend(v4.addrs);
+                    Ok(v6)
+                }
+                (Ok(lookup), Err(_)) | (Err(_), Ok(lookup)) => Ok(lookup),
+                // the IPv4 error is the one worth reporting, most names have A records
+                (Err(_), Err(e)) => Err(e),
+            }
+        })
+    }
+
+    /// Returns the records of `rtype` for `name`, following CNAME records unless `rtype` is CNAME.
+    ///
+    /// Unlike `resolve`, this always asks the servers, and the answer isn't cached.
+    pub fn lookup(&self, id: u16, name: &str, rtype: RecordType) -> Result<Lookup, Error> {
+        self.search(name, |name| {
+            let name = name.trim_end_matches('.').to_ascii_lowercase();
+            match self.query_uncached(id, &name, rtype)? {
+                Answer::Found(lookup, _) => Ok(lookup),
+                answer @ (Answer::NoSuchDomain(..) | Answer::NoRecords(..))
+                    if matches!(answer.dnssec(), Some(Dnssec::Bogus(_))) =>
+                {
+                    Err(bogus(&name, answer.dnssec()))
+                }
+                Answer::NoSuchDomain(..) => Err(Error::Rcode {
+                    name,
+                    rcode: Rcode::NXDomain,
+                }),
+                Answer::NoRecords(..) => Err(Error::NoRecords(name)),
+            }
+        })
+    }
+
+    /// Returns the names that `ip` points back to with PTR records.
+    pub fn reverse(&self, id: u16, ip: IpAddr) -> Result<Vec<String>, Error> {
+        // the trailing dot keeps the search list out
+        let name = format!("{}.", reverse_name(ip));
+        let lookup = self.lookup(id, &name, QueryType::PTR)?;
+        let names = lookup
+            .records
+            .into_iter()
+            .filter_map(|record| match record.rdata {
+                RData::PTR(name) => Some(name),
+                _ => None,
+            })
+            .collect();
+        Ok(names)
+    }
+
+    /// Like `reverse`, but only returns the names that resolve to `ip` again. Anyone can point
+    /// the PTR records of their addresses at any name, but not the addresses of others' names.
+    pub fn reverse_confirmed(&self, id: u16, ip: IpAddr) -> Result<Vec<String>, Error> {
+        let qtype = match ip {
+            IpAddr::V4(_) => QueryType::A,
+            IpAddr::V6(_) => QueryType::AAAA,
+        };
+        let names = self.reverse(id, ip)?;
+        let confirmed: Vec<String> = names
+            .into_iter()
+            .filter(|name| {
+                self.query(id.wrapping_add(1), name, qtype)
+                    .is_ok_and(|lookup| lookup.addrs.contains(&ip))
+            })
+            .collect();
+        if confirmed.is_empty() {
+            return Err(Error::NoRecords(reverse_name(ip)));
+        }
+        Ok(confirmed)
+    }
+
+    /// Returns the servers of a service such as "_http._tcp.example.com" in the order they
+    /// should be tried (RFC 2782). No servers means that the service isn't available at all.
+    pub fn lookup_srv(&self, id: u16, name: &str) -> Result<Vec<Srv>, Error> {
+        let lookup = self.lookup(id, name, QueryType::SRV)?;
+        let srvs: Vec<Srv> = lookup
+            .records
+            .into_iter()
+            .filter_map(|record| match record.rdata {
+                RData::SRV {
+                    priority,
+                    weight,
+                    port,
+                    target,
+                } => Some(Srv {
+                    priority,
+                    weight,
+                    port,
+                    target,
+                }),
+                _ => None,
+            })
+            .collect();
+        // a single record with the root as target says the service isn't available
+        if srvs.iter().any(|srv| srv.target.is_empty()) {
+            return Ok(vec![]);
+        }
+        let mut random = vec![0u8; srvs.len() * 4];
+        fill_random(&mut random)?;
+        let mut numbers = random
+            .chunks(4)
+            .map(|chunk| u32::from_be_bytes(chunk.try_into().unwrap()));
+        Ok(order_srv(srvs, || numbers.next().unwrap_or(0)))
+    }
+
+    /// Calls `resolve` with the names of the search list for `host` until one of them exists.
+    fn search<T>(
+        &self,
+        host: &str,
+        resolve: impl Fn(&str) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let mut last_error = None;
+        for name in self.config.candidates(host) {
+            match resolve(&name) {
+                Err(
+                    e @ (Error::NoRecords(_)
+                    | Error::Rcode {
+                        rcode: Rcode::NXDomain,
+                        ..
+                    }),
+                ) => last_error = Some(e),
+                result => return result,
+            }
+        }
+        Err(last_error.unwrap_or_else(|| Error::NoRecords(host.to_string())))
+    }
+
+    /// Queries the A or AAAA records of `host`, following CNAME records to the addresses. A chain
+    /// that doesn't end in the answer section is followed with another query.
+    ///
+    /// Answers are kept in the cache for the lowest TTL of the records they come from, and
+    /// negative answers for the TTL in the SOA record of the response (RFC 2308 section 5).
+    fn query(&self, id: u16, host: &str, qtype: QueryType) -> Result<LookupIp, Error> {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        let cached = self
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.get(&host, qtype));
+        let answer = match cached {
+            Some((answer, _)) => answer,
+            None => {
+                let answer = self.query_uncached(id, &host, qtype)?;
+                if let Some(Dnssec::Bogus(_)) = answer.dnssec() {
+                    return Err(bogus(&host, answer.dnssec()));
+                }
+                let (answer, ttl) = match answer {
+                    Answer::Found(lookup, ttl) => (CachedAnswer::Found(lookup.into()), Some(ttl)),
+                    Answer::NoSuchDomain(ttl, _) => (CachedAnswer::NoSuchDomain, ttl),
+                    Answer::NoRecords(ttl, _) => (CachedAnswer::NoRecords, ttl),
+                };
+                if let (Some(cache), Some(ttl)) = (&self.cache, ttl) {
+                    cache.store(&host, qtype, answer.clone(), ttl);
+                }
+                answer
+            }
+        };
+        match answer {
+            CachedAnswer::Found(lookup) => Ok(lookup),
+            CachedAnswer::NoSuchDomain => Err(Error::Rcode {
+                name: host,
+                rcode: Rcode::NXDomain,
+            }),
+            CachedAnswer::NoRecords => Err(Error::NoRecords(host)),
+        }
+    }
+
+    /// Queries the records of `host`, following CNAME records. Found records, and the proofs that
+    /// there are none, are validated if DNSSEC is enabled.
+    fn query_uncached(&self, id: u16, host: &str, qtype: QueryType) -> Result<Answer, Error> {
+        let mut name = host.to_string();
+        let mut hops = 0;
+        let mut ttl = u32::MAX;
+        // the answer and authority sections of all responses, with the RRSIG records
+        let (mut answers, mut authorities) = (vec![], vec![]);
+        loop {
+            let response = self.send(id, &name, qtype)?;
+            let (canonical_name, records, chain_ttl) = chase(&response, &name, qtype, &mut hops)?;
+            ttl = ttl.min(chain_ttl);
+            answers.extend(response.answers.iter().cloned());
+            authorities.extend(response.authorities.iter().cloned());
+            let validate_denial = |qtype| {
+                self.dnssec
+                    .as_ref()
+                    .map(|anchor| {
+                        let name = &canonical_name;
+                        self.validate_denial(id, anchor, name, qtype, &answers, &authorities)
+                    })
+                    .transpose()
+            };
+            if response.rcode() == Rcode::NXDomain {
+                let dnssec = validate_denial(None)?;
+                return Ok(Answer::NoSuchDomain(negative_ttl(&response), dnssec));
+            }
+            if !records.is_empty() {
+                let dnssec = self
+                    .dnssec
+                    .as_ref()
+                    .map(|anchor| self.validate(id, anchor, &answers, &authorities))
+                    .transpose()?;
+                let lookup = Lookup {
+                    canonical_name,
+                    records,
+                    dnssec,
+                };
+                return Ok(Answer::Found(lookup, ttl));
+            }
+            if canonical_name == name {
+                let dnssec = validate_denial(Some(qtype))?;
+                return Ok(Answer::NoRecords(negative_ttl(&response), dnssec));
+            }
+            name = canonical_name;
+        }
+    }
+
+    /// Sends a query and returns the response if its response code is NOERROR or NXDOMAIN.
+    ///
+    /// The servers are tried in order, or starting with the next one in turn if `rotate` is set,
+    /// up to `attempts` times each. The timeout doubles with every round through the servers.
+    fn send(&self, id: u16, host: &str, qtype: QueryType) -> Result<Response, Error> {
+        if let Some(encrypted) = &self.encrypted {
+            // the ID is 0 so that HTTP caches can share responses (RFC 8484 section 4.1)
+            let query = match encrypted {
+                Encrypted::Https(_) => self.encode_query(0, host, qtype)?,
+                Encrypted::Tls(_) => self.encode_query(id, host, qtype)?,
+            };
+            let mut last_error = None;
+            for round in 0..self.config.attempts.max(1) {
+                let timeout = self.config.timeout.saturating_mul(1 << round.min(16));
+                let result = match encrypted {
+                    Encrypted::Https(doh) => doh.exchange(&query, timeout),
+                    Encrypted::Tls(dot) => dot.exchange(&query, id, timeout),
+                };
+                match result.and_then(|response| check_rcode(response, host)) {
+                    Err(e) if is_retryable(&e) => last_error = Some(e),
+                    result => return result,
+                }
+            }
+            return Err(last_error.expect("the server was tried"));
+        }
+        let servers = &self.config.nameservers;
+        if servers.is_empty() {
+            let e = io::Error::new(io::ErrorKind::NotFound, "no DNS servers configured");
+            return Err(e.into());
+        }
+        let first = match self.config.rotate {
+            true => self.next_server.fetch_add(1, Ordering::Relaxed),
+            false => 0,
+        };
+        let plain = Vec::try_from(Query::new(id, host, qtype))?;
+        let query = self.encode_query(id, host, qtype)?;
+        let mut last_error = None;
+        for round in 0..self.config.attempts.max(1) {
+            let timeout = self.config.timeout.saturating_mul(1 << round.min(16));
+            for i in 0..servers.len() {
+                let server = servers[(first + i) % servers.len()];
+                let result = exchange(&query, server, timeout, &self.config)
+                    .and_then(|response| {
+                        // a server without EDNS rejects the OPT record (RFC 6891 section 7)
+                        let rejected =
+                            response.rcode() == Rcode::FormErr && response.edns.is_none();
+                        if rejected && query != plain {
+                            return exchange(&plain, server, timeout, &self.config);
+                        }
+                        Ok(response)
+                    })
+                    .and_then(|response| check_rcode(response, host));
+                match result {
+                    // another server may know better
+                    Err(e) if is_retryable(&e) => last_error = Some(e),
+                    result => return result,
+                }
+            }
+        }
+        Err(last_error.expect("at least one server was tried"))
+    }
+
+    /// The query for `host` with an OPT record if EDNS or DNSSEC is enabled, with the DO bit set
+    /// if DNSSEC is (RFC 3225).
+    fn encode_query(&self, id: u16, host: &str, qtype: QueryType) -> Result<Vec<u8>, Error> {
+        let query = Query::new(id, host, qtype);
+        let query = match (self.config.udp_payload_size, &self.dnssec) {
+            // without EDNS, DNSSEC records aren't sent. 512 bytes is the size without it.
+            (size, Some(_)) => query.with_edns(size.unwrap_or(512)).with_dnssec_ok(),
+            (Some(size), None) => query.with_edns(size),
+            (None, None) => query,
+        };
+        query.try_into()
+    }
+}
+
+/// A server queried over an encrypted transport instead of the nameservers
+#[derive(Debug, Clone)]
+enum Encrypted {
+    Https(Doh),
+    Tls(Dot),
+}
+
+/// Passes on responses that answer the query for `host`, with or without records.
+fn check_rcode(response: Response, host: &str) -> Result<Response, Error> {
+    match response.rcode() {
+        Rcode::NoError | Rcode::NXDomain => Ok(response),
+        rcode => Err(Error::Rcode {
+            name: host.to_string(),
+            rcode,
+        }),
+    }
+}
+
+/// Whether a query that failed with `e` may succeed when sent again or to another server.
+fn is_retryable(e: &Error) -> bool {
+    matches!(
+        e,
+        Error::Timeout
+            | Error::Io(_)
+            | Error::Dns(_)
+            | Error::Rcode {
+                rcode: Rcode::ServFail | Rcode::NotImp | Rcode::Refused,
+                ..
+            }
+    )
+}
+
+/// Sorts `srvs` by priority, and servers of the same priority by a weighted random selection
+/// with numbers from `random` (RFC 2782, "Usage rules").
+fn order_srv(mut srvs: Vec<Srv>, mut random: impl FnMut() -> u32) -> Vec<Srv> {
+    // servers with weight 0 go first, so that they are only chosen when the random number is 0
+    srvs.sort_by_key(|srv| (srv.priority, srv.weight != 0));
+    let mut ordered = Vec::with_capacity(srvs.len());
+    while let Some(priority) = srvs.first().map(|srv| srv.priority) {
+        let count = srvs
+            .iter()
+            .take_while(|srv| srv.priority == priority)
+            .count();
+        let mut group: Vec<Srv> = srvs.drain(..count).collect();
+        while !group.is_empty() {
+            let total: u32 = group.iter().map(|srv| u32::from(srv.weight)).sum();
+            let pick = random() % (total + 1);
+            let mut sum = 0;
+            let index = group
+                .iter()
+                .position(|srv| {
+                    sum += u32::from(srv.weight);
+                    sum >= pick
+                })
+                .unwrap_or(0);
+            ordered.push(group.remove(index));
+        }
+    }
+    ordered
+}
+
+/// The name of the PTR records for `ip` under in-addr.arpa or ip6.arpa (RFC 1035 section 3.5,
+/// RFC 3596 section 2.5).
+pub fn reverse_name(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, d] = ip.octets();
+            format!("{d}.{c}.{b}.{a}.in-addr.arpa")
+        }
+        IpAddr::V6(ip) => {
+            let mut name = String::with_capacity(72);
+            for byte in ip.octets().iter().rev() {
+                name.push_str(&format!("{:x}.{:x}.", byte & 0xf, byte >> 4));
+            }
+            name + "ip6.arpa"
+        }
+    }
+}
+
+/// Sends `query` to `server` and waits up to `timeout` for its response, over TCP if `config`
+/// says so or the response over UDP was truncated.
+fn exchange(
+    query: &[u8],
+    server: SocketAddr,
+    timeout: Duration,
+    config: &ResolvConf,
+) -> Result<Response, Error> {
+    if !config.tcp {
+        let max_len = config.udp_payload_size.unwrap_or(512).max(512);
+        let response = exchange_udp(query, server, timeout, max_len.into())?;
+        if !response.header.tc {
+            return Ok(response);
+        }
+    }
+    exchange_tcp(query, server, timeout)
+}
+
+/// Datagrams from other addresses, with another transaction ID or for another question are
+/// ignored, so that nobody but the server can answer the query (RFC 5452 section 9.1).
+fn exchange_udp(
+    query: &[u8],
+    server: SocketAddr,
+    timeout: Duration,
+    max_len: usize,
+) -> Result<Response, Error> {
+    let client: IpAddr = match server {
+        SocketAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+        SocketAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+    };
+    let questions = Response::try_from(query)?.questions;
+    let sock = UdpSocket::bind(SocketAddr::new(client, 0))?;
+    sock.send_to(query, server)?;
+    let deadline = Instant::now() + timeout;
+    let mut buf = vec![0; max_len];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(Error::Timeout);
+        }
+        sock.set_read_timeout(Some(remaining))?;
+        let (len, from) = sock.recv_from(&mut buf)?;
+        let is_response = len >= 12 && buf[2] & 0x80 != 0;
+        if from == server && is_response && buf[..2] == query[..2] {
+            let response = Response::try_from(&buf[..len])?;
+            if response.questions == questions {
+                return Ok(response);
+            }
+        }
+    }
+}
+
+/// Messages over TCP are prefixed with their length as two bytes (RFC 1035 section 4.2.2).
+fn exchange_tcp(query: &[u8], server: SocketAddr, timeout: Duration) -> Result<Response, Error> {
+    let mut sock = TcpStream::connect_timeout(&server, timeout)?;
+    sock.set_read_timeout(Some(timeout))?;
+    sock.set_write_timeout(Some(timeout))?;
+    let mut message = (query.len() as u16).to_be_bytes().to_vec();
+    message.extend_from_slice(query);
+    sock.write_all(&message)?;
+    let mut len = [0; 2];
+    sock.read_exact(&mut len)?;
+    let mut buf = vec![0; u16::from_be_bytes(len) as usize];
+    sock.read_exact(&mut buf)?;
+    if buf.get(..2) != Some(&query[..2]) {
+        return Err(Error::Dns(
+            "response has another transaction ID".to_string(),
+        ));
+    }
+    let response = Response::try_from(&buf[..])?;
+    if response.questions != Response::try_from(query)?.questions {
+        return Err(Error::Dns("response is for another question".to_string()));
+    }
+    Ok(response)
+}
+
+/// Follows the CNAME records in the answer section from `name`, and returns the name at the end
+/// of the chain with its records of `qtype`, which are empty if the chain continues elsewhere,
+/// and the lowest TTL of the records.
+fn chase(
+    response: &Response,
+    name: &str,
+    qtype: QueryType,
+    hops: &mut usize,
+) -> Result<(String, Vec<ResourceRecord>, u32), Error> {
+    let mut name = name.to_string();
+    let mut ttl = u32::MAX;
+    // the records of a chain may come in any order, and a query for CNAME records ends at the first
+    while let Some((target, cname_ttl)) =
+        response
+            .answers
+            .iter()
+            .find_map(|answer| match &answer.rdata {
+                RData::CNAME(target) if answer.name == name && qtype != QueryType::CNAME => {
+                    Some((target, answer.ttl))
+                }
+                _ => None,
+            })
+    {
+        *hops += 1;
+        if *hops > MAX_CNAME_CHAIN {
+            return Err(Error::Dns(format!(
+                "more than {MAX_CNAME_CHAIN} CNAME records in a row at {name}"
+            )));
+        }
+        ttl = ttl.min(cname_ttl);
+        name = target.clone();
+    }
+    let records: Vec<ResourceRecord> = response
+        .answers
+        .iter()
+        .filter(|answer| answer.name == name && answer.query_type == qtype)
+        .cloned()
+        .collect();
+    for record in &records {
+        ttl = ttl.min(record.ttl);
+    }
+    Ok((name, records, ttl))
+}
+
+/// How long a negative answer may be cached, from the SOA record in the authority section.
+/// Without one it isn't cached (RFC 2308 section 5).
+fn negative_ttl(response: &Response) -> Option<u32> {
+    response
+        .authorities
+        .iter()
+        .find_map(|record| match record.rdata {
+            RData::SOA { minimum, .. } => Some(record.ttl.min(minimum)),
+            _ => None,
+        })
+}
+
+/**
+ * Records of a name and the name they actually belong to
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lookup {
+    /// The end of the CNAME chain, or the name itself if it isn't an alias
+    pub canonical_name: String,
+    pub records: Vec<ResourceRecord>,
+    /// Whether the records are authenticated, or None if DNSSEC isn't enabled
+    pub dnssec: Option<Dnssec>,
+}
+
+/**
+ * A server of a service, from an SRV record
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Srv {
+    /// Servers with a lower priority are tried first
+    pub priority: u16,
+    /// Relative share of the connections among servers of the same priority
+    pub weight: u16,
+    pub port: u16,
+    pub target: String,
+}
+
+/// What the servers answered, with how many seconds it may be cached, and whether the proofs
+/// that there are no records are authenticated
+enum Answer {
+    Found(Lookup, u32),
+    NoSuchDomain(Option<u32>, Option<Dnssec>),
+    NoRecords(Option<u32>, Option<Dnssec>),
+}
+
+impl Answer {
+    fn dnssec(&self) -> Option<&Dnssec> {
+        match self {
+            Answer::Found(lookup, _) => lookup.dnssec.as_ref(),
+            Answer::NoSuchDomain(_, dnssec) | Answer::NoRecords(_, dnssec) => dnssec.as_ref(),
+        }
+    }
+}
+
+/// The error of an answer that failed DNSSEC validation
+fn bogus(name: &str, dnssec: Option<&Dnssec>) -> Error {
+    Error::Dns(format!("DNSSEC validation of {name} failed: {dnssec:?}"))
+}
+
+/**
+ * Addresses of a name and the name they actually belong to
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LookupIp {
+    /// The end of the CNAME chain, or the name itself if it isn't an alias
+    pub canonical_name: String,
+    pub addrs: Vec<IpAddr>,
+}
+
+impl From<Lookup> for LookupIp {
+    fn from(lookup: Lookup) -> Self {
+        let addrs = lookup
+            .records
+            .iter()
+            .filter_map(|record| match record.rdata {
+                RData::A(v) => Some(IpAddr::V4(v.into())),
+                RData::AAAA(v) => Some(IpAddr::V6(v.into())),
+                _ => None,
+            })
+            .collect();
+        LookupIp {
+            canonical_name: lookup.canonical_name,
+            addrs,
         }
     }
 }
@@ -50,25 +760,95 @@ impl Resolver {
 pub struct Query {
     header: Header,
     questions: Vec<Question>,
+    edns: Option<Edns>,
 }
 
 impl Query {
-    pub fn new(id: u16, host: &str) -> Self {
+    pub fn new(id: u16, host: &str, qtype: QueryType) -> Self {
         let header = Header::new_query(id, 1);
-        let questions = vec![Question::new(host, QueryType::A)];
-        Query { header, questions }
+        let questions = vec![Question::new(host, qtype)];
+        Query {
+            header,
+            questions,
+            edns: None,
+        }
+    }
+
+    /// Adds an OPT record telling the server it may answer with up to `udp_payload_size` bytes.
+    pub fn with_edns(mut self, udp_payload_size: u16) -> Self {
+        self.header.arcount = 1;
+        self.edns = Some(Edns {
+            udp_payload_size,
+            extended_rcode: 0,
+            version: 0,
+            dnssec_ok: false,
+        });
+        self
+    }
+
+    /// Sets the DO bit in the OPT record added by `with_edns`, asking for DNSSEC records.
+    pub fn with_dnssec_ok(mut self) -> Self {
+        if let Some(edns) = &mut self.edns {
+            edns.dnssec_ok = true;
+        }
+        self
     }
 }
 
-impl From<Query> for Vec<u8> {
-    fn from(query: Query) -> Self {
-        query
-            .questions
-            .into_iter()
-            .fold(query.header.into(), |mut acc, cur| {
-                acc.extend::<Vec<u8>>(cur.into());
-                acc
-            })
+impl TryFrom<Query> for Vec<u8> {
+    type Error = Error;
+
+    /// Encodes the query, which fails if a name in it can't be encoded.
+    fn try_from(query: Query) -> Result<Self, Self::Error> {
+        let mut message: Vec<u8> = query.header.into();
+        let mut names = NameEncoder::default();
+        for question in &query.questions {
+            question.write(&mut message, &mut names)?;
+        }
+        if let Some(edns) = query.edns {
+            message.extend::<Vec<u8>>(edns.into());
+        }
+        Ok(message)
+    }
+}
+
+/**
+ * EDNS(0) parameters carried in the OPT pseudo-record of a message (RFC 6891 section 6.1)
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edns {
+    /// Largest UDP payload the sender can receive
+    pub udp_payload_size: u16,
+    /// Upper 8 bits of the 12-bit response code
+    pub extended_rcode: u8,
+    pub version: u8,
+    /// Whether the sender wants DNSSEC records (RFC 3225)
+    pub dnssec_ok: bool,
+}
+
+impl From<&ResourceRecord> for Edns {
+    /// The CLASS and TTL fields of an OPT record hold the parameters instead.
+    fn from(record: &ResourceRecord) -> Self {
+        Edns {
+            udp_payload_size: record.query_class.into(),
+            extended_rcode: (record.ttl >> 24) as u8,
+            version: (record.ttl >> 16) as u8,
+            dnssec_ok: record.ttl & 0x8000 != 0,
+        }
+    }
+}
+
+impl From<Edns> for Vec<u8> {
+    fn from(edns: Edns) -> Self {
+        let ttl = (edns.extended_rcode as u32) << 24
+            | (edns.version as u32) << 16
+            | (edns.dnssec_ok as u32) << 15;
+        let mut v = vec![0]; // the root domain
+        v.extend_from_slice(&u16::from(QueryType::OPT).to_be_bytes());
+        v.extend_from_slice(&edns.udp_payload_size.to_be_bytes());
+        v.extend_from_slice(&ttl.to_be_bytes());
+        v.extend_from_slice(&[0, 0]); // no options
+        v
     }
 }
 
@@ -153,8 +933,11 @@ impl From<Header> for Vec<u8> {
     }
 }
 
-impl From<&[u8; 512]> for Header {
-    fn from(value: &[u8; 512]) -> Self {
+impl TryFrom<&[u8]> for Header {
+    type Error = Error;
+
+    fn try_from(value: &[u8]) -> Result<Self, Error> {
+        let value = slice(value, 0, 12)?;
         let id = (value[0] as u16) << 8 | value[1] as u16;
         let qr = (value[2] & 0x80) != 0;
         let opcode = (value[2] & 0x78) >> 3;
@@ -167,13 +950,13 @@ impl From<&[u8; 512]> for Header {
         let ancount = (value[6] as u16) << 8 | value[7] as u16;
         let nscount = (value[8] as u16) << 8 | value[9] as u16;
         let arcount = (value[10] as u16) << 8 | value[11] as u16;
-        Header::new(
+        Ok(Header::new(
             id, qr, opcode, aa, tc, rd, ra, rcode, qdcount, ancount, nscount, arcount,
-        )
+        ))
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Question {
     qname: String,
     qtype: QueryType,
@@ -190,96 +973,572 @@ impl Question {
     }
 }
 
-impl From<Question> for Vec<u8> {
-    fn from(question: Question) -> Self {
+impl Question {
+    fn write(&self, message: &mut Vec<u8>, names: &mut NameEncoder) -> Result<(), Error> {
+        names.write(message, &self.qname)?;
+        message.extend_from_slice(&u16::from(self.qtype).to_be_bytes());
+        message.extend_from_slice(&u16::from(self.qclass).to_be_bytes());
+        Ok(())
+    }
+}
+
+impl TryFrom<Question> for Vec<u8> {
+    type Error = Error;
+
+    fn try_from(question: Question) -> Result<Self, Self::Error> {
         let mut v = vec![];
-        for c in question.qname.split('.') {
-            v.push(c.len() as u8);
-            v.extend(c.bytes());
-        }
-        v.push(0);
-        match question.qtype {
-            QueryType::A => v.extend_from_slice(&[0, 1]),
-            QueryType::AAAA => v.extend_from_slice(&[0, 28]),
-        }
-        match question.qclass {
-            QueryClass::IN => v.extend_from_slice(&[0, 1]),
-        }
-        v
+        question.write(&mut v, &mut NameEncoder::default())?;
+        Ok(v)
     }
 }
 
-impl TryFrom<(&[u8; 512], &mut usize)> for Question {
-    type Error = String;
+impl TryFrom<(&[u8], &mut usize)> for Question {
+    type Error = Error;
 
-    fn try_from((bytes, offset): (&[u8; 512], &mut usize)) -> Result<Self, String> {
-        let mut qname: Vec<String> = vec![];
-        while bytes[*offset] != 0 {
-            let n = bytes[*offset] as usize;
-            *offset += 1;
-            let end = *offset + n;
-            if bytes.len() < end {
-                return Err("Invalid question".to_string());
-            }
-            let label = String::from_utf8_lossy(&bytes[*offset..end]).to_ascii_lowercase();
-            qname.push(label);
-            *offset = end;
-        }
-        let qname = qname.join(".");
-        *offset += 1;
-        let qtype = match (&bytes[*offset], &bytes[*offset + 1]) {
-            (0, 1) => QueryType::A,
-            (0, 28) => QueryType::AAAA,
-            _ => return Err("Invalid question".to_string()),
-        };
-        *offset += 4;
+    fn try_from((bytes, offset): (&[u8], &mut usize)) -> Result<Self, Error> {
+        let qname = get_name(bytes, offset)?;
+        let qtype = QueryType::from(read_u16(bytes, offset)?);
+        let qclass = QueryClass::from(read_u16(bytes, offset)?);
         Ok(Question {
             qname,
             qtype,
-            qclass: QueryClass::IN,
+            qclass,
         })
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// The type of the records to look up
+pub type RecordType = QueryType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum QueryType {
     A,
-    #[allow(dead_code)]
+    NS,
+    CNAME,
+    SOA,
+    PTR,
+    MX,
+    TXT,
     AAAA,
+    SRV,
+    CAA,
+    /// EDNS(0) pseudo-record
+    OPT,
+    /// Digest of a DNSKEY of a child zone (RFC 4034)
+    DS,
+    /// Signature of the records of a name and type
+    RRSIG,
+    /// The next name of a zone and the types of the owner, proving that others don't exist
+    NSEC,
+    /// Public key of a zone
+    DNSKEY,
+    /// Like NSEC, with hashed names (RFC 5155)
+    NSEC3,
+    /// Any other type, by its numeric value
+    Other(u16),
+}
+
+impl From<u16> for QueryType {
+    fn from(value: u16) -> Self {
+        match value {
+            1 => QueryType::A,
+            2 => QueryType::NS,
+            5 => QueryType::CNAME,
+            6 => QueryType::SOA,
+            12 => QueryType::PTR,
+            15 => QueryType::MX,
+            16 => QueryType::TXT,
+            28 => QueryType::AAAA,
+            33 => QueryType::SRV,
+            257 => QueryType::CAA,
+            41 => QueryType::OPT,
+            43 => QueryType::DS,
+            46 => QueryType::RRSIG,
+            47 => QueryType::NSEC,
+            48 => QueryType::DNSKEY,
+            50 => QueryType::NSEC3,
+            other => QueryType::Other(other),
+        }
+    }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+impl From<QueryType> for u16 {
+    fn from(value: QueryType) -> Self {
+        match value {
+            QueryType::A => 1,
+            QueryType::NS => 2,
+            QueryType::CNAME => 5,
+            QueryType::SOA => 6,
+            QueryType::PTR => 12,
+            QueryType::MX => 15,
+            QueryType::TXT => 16,
+            QueryType::AAAA => 28,
+            QueryType::SRV => 33,
+            QueryType::CAA => 257,
+            QueryType::OPT => 41,
+            QueryType::DS => 43,
+            QueryType::RRSIG => 46,
+            QueryType::NSEC => 47,
+            QueryType::DNSKEY => 48,
+            QueryType::NSEC3 => 50,
+            QueryType::Other(other) => other,
+        }
+    }
+}
+
+impl Display for QueryType {
+    /// The mnemonic of zone files, or the generic form of RFC 3597 section 5 such as TYPE65.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryType::Other(value) => write!(f, "TYPE{value}"),
+            known => write!(f, "{known:?}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 enum QueryClass {
     IN,
+    /// Any other class, or the UDP payload size of an OPT record
+    Other(u16),
+}
+
+impl From<u16> for QueryClass {
+    fn from(value: u16) -> Self {
+        match value {
+            1 => QueryClass::IN,
+            other => QueryClass::Other(other),
+        }
+    }
+}
+
+impl From<QueryClass> for u16 {
+    fn from(value: QueryClass) -> Self {
+        match value {
+            QueryClass::IN => 1,
+            QueryClass::Other(other) => other,
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RData {
     A([u8; 4]),
     AAAA([u8; 16]),
+    /// An authoritative name server of the zone
+    NS(String),
+    /// The name that the owner of the record is an alias of
+    CNAME(String),
+    /// The name of the host with the address in a reverse lookup
+    PTR(String),
+    /// A mail server, the lowest preference is tried first
+    MX {
+        preference: u16,
+        exchange: String,
+    },
+    /// Strings of arbitrary bytes
+    TXT(Vec<Vec<u8>>),
+    /// Location of a service (RFC 2782)
+    SRV {
+        priority: u16,
+        weight: u16,
+        port: u16,
+        target: String,
+    },
+    /// Which certificate authorities may issue certificates for the name (RFC 8659)
+    CAA {
+        flags: u8,
+        tag: String,
+        value: Vec<u8>,
+    },
+    /// Start of a zone of authority (RFC 1035 section 3.3.13)
+    SOA {
+        mname: String,
+        rname: String,
+        serial: u32,
+        refresh: u32,
+        retry: u32,
+        expire: u32,
+        /// TTL of negative answers from the zone (RFC 2308 section 4)
+        minimum: u32,
+    },
+    /// Digest of a DNSKEY of the child zone named by the owner (RFC 4034 section 5)
+    DS {
+        key_tag: u16,
+        algorithm: u8,
+        digest_type: u8,
+        digest: Vec<u8>,
+    },
+    /// Public key of the zone (RFC 4034 section 2)
+    DNSKEY {
+        flags: u16,
+        protocol: u8,
+        algorithm: u8,
+        public_key: Vec<u8>,
+    },
+    /// Signature over the records of the owner of `type_covered` (RFC 4034 section 3)
+    RRSIG {
+        type_covered: QueryType,
+        algorithm: u8,
+        labels: u8,
+        original_ttl: u32,
+        /// Seconds since the Unix epoch, in serial number arithmetic
+        expiration: u32,
+        inception: u32,
+        key_tag: u16,
+        signer_name: String,
+        signature: Vec<u8>,
+    },
+    /// The next name in the zone and the types of the owner (RFC 4034 section 4)
+    NSEC {
+        next_name: String,
+        types: Vec<QueryType>,
+    },
+    /// The next hashed name in the zone and the types of the owner (RFC 5155 section 3)
+    NSEC3 {
+        hash_algorithm: u8,
+        /// Bit 0 is the Opt-Out flag
+        flags: u8,
+        iterations: u16,
+        salt: Vec<u8>,
+        next_hashed_owner: Vec<u8>,
+        types: Vec<QueryType>,
+    },
+    /// Data of a record type that isn't decoded
+    Unknown(Vec<u8>),
 }
 
 impl Display for RData {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            RData::A(ip) => write!(
+            RData::A(ip) => write!(f, "{}", Ipv4Addr::from(*ip)),
+            // RFC 5952 text form, such as 2001:db8::1
+            RData::AAAA(ip) => write!(f, "{}", Ipv6Addr::from(*ip)),
+            RData::NS(name) | RData::CNAME(name) | RData::PTR(name) => write!(f, "{name}."),
+            RData::MX {
+                preference,
+                exchange,
+            } => write!(f, "{preference} {exchange}."),
+            RData::TXT(strings) => {
+                for (i, string) in strings.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write_quoted(f, string)?;
+                }
+                Ok(())
+            }
+            RData::SRV {
+                priority,
+                weight,
+                port,
+                target,
+            } => write!(f, "{priority} {weight} {port} {target}."),
+            RData::CAA { flags, tag, value } => {
+                write!(f, "{flags} {tag} ")?;
+                write_quoted(f, value)
+            }
+            RData::SOA {
+                mname,
+                rname,
+                serial,
+                refresh,
+                retry,
+                expire,
+                minimum,
+            } => write!(
+                f,
+                "{mname}. {rname}. {serial} {refresh} {retry} {expire} {minimum}"
+            ),
+            RData::DS {
+                key_tag,
+                algorithm,
+                digest_type,
+                digest,
+            } => {
+                write!(f, "{key_tag} {algorithm} {digest_type} ")?;
+                digest.iter().try_for_each(|b| write!(f, "{b:02X}"))
+            }
+            RData::DNSKEY {
+                flags,
+                protocol,
+                algorithm,
+                public_key,
+            } => write!(
                 f,
-                "{}",
-                ip.iter()
-                    .map(|b| b.to_string())
-                    .collect::<Vec<String>>()
-                    .join(".")
+                "{flags} {protocol} {algorithm} {}",
+                base64(public_key, false)
             ),
-            RData::AAAA(ip) => write!(
+            RData::RRSIG {
+                type_covered,
+                algorithm,
+                labels,
+                original_ttl,
+                expiration,
+                inception,
+                key_tag,
+                signer_name,
+                signature,
+            } => write!(
                 f,
-                "{}",
-                ip.iter()
-                    .map(|b| b.to_string())
-                    .collect::<Vec<String>>()
-                    .join(":")
+                "{type_covered} {algorithm} {labels} {original_ttl} {expiration} {inception} \
+                 {key_tag} {signer_name}. {}",
+                base64(signature, false)
             ),
+            RData::NSEC { next_name, types } => {
+                write!(f, "{next_name}.")?;
+                types.iter().try_for_each(|t| write!(f, " {t}"))
+            }
+            RData::NSEC3 {
+                hash_algorithm,
+                flags,
+                iterations,
+                salt,
+                next_hashed_owner,
+                types,
+            } => {
+                write!(f, "{hash_algorithm} {flags} {iterations} ")?;
+                if salt.is_empty() {
+                    write!(f, "-")?;
+                }
+                salt.iter().try_for_each(|b| write!(f, "{b:02X}"))?;
+                write!(f, " {}", base32hex(next_hashed_owner))?;
+                types.iter().try_for_each(|t| write!(f, " {t}"))
+            }
+            // the generic format of RFC 3597 section 5
+            RData::Unknown(data) => {
+                write!(f, "\\# {}", data.len())?;
+                if !data.is_empty() {
+                    write!(f, " ")?;
+                }
+                data.iter().try_for_each(|b| write!(f, "{b:02x}"))
+            }
+        }
+    }
+}
+
+/// Writes a character string in the quoted form of zone files, with other bytes than printable
+/// ASCII as decimal escapes (RFC 1035 section 5.1).
+fn write_quoted(f: &mut std::fmt::Formatter<'_>, bytes: &[u8]) -> std::fmt::Result {
+    write!(f, "\"")?;
+    for &b in bytes {
+        match b {
+            b'"' | b'\\' => write!(f, "\\{}", b as char)?,
+            0x20..=0x7e => write!(f, "{}", b as char)?,
+            _ => write!(f, "\\{b:03}")?,
+        }
+    }
+    write!(f, "\"")
+}
+
+impl TryFrom<&RData> for Vec<u8> {
+    type Error = Error;
+
+    /// Encodes the RDATA in the canonical form of RFC 4034 section 6.2, with names lowercase
+    /// and without compression.
+    fn try_from(rdata: &RData) -> Result<Self, Self::Error> {
+        let mut v = vec![];
+        match rdata {
+            RData::A(ip) => v.extend_from_slice(ip),
+            RData::AAAA(ip) => v.extend_from_slice(ip),
+            RData::NS(name) | RData::CNAME(name) | RData::PTR(name) => write_name(&mut v, name)?,
+            RData::MX {
+                preference,
+                exchange,
+            } => {
+                v.extend_from_slice(&preference.to_be_bytes());
+                write_name(&mut v, exchange)?;
+            }
+            RData::TXT(strings) => {
+                for string in strings {
+                    v.push(string.len() as u8);
+                    v.extend_from_slice(string);
+                }
+            }
+            RData::SRV {
+                priority,
+                weight,
+                port,
+                target,
+            } => {
+                for n in [priority, weight, port] {
+                    v.extend_from_slice(&n.to_be_bytes());
+                }
+                write_name(&mut v, target)?;
+            }
+            RData::CAA { flags, tag, value } => {
+                v.extend_from_slice(&[*flags, tag.len() as u8]);
+                v.extend_from_slice(tag.as_bytes());
+                v.extend_from_slice(value);
+            }
+            RData::SOA {
+                mname,
+                rname,
+                serial,
+                refresh,
+                retry,
+                expire,
+                minimum,
+            } => {
+                write_name(&mut v, mname)?;
+                write_name(&mut v, rname)?;
+                for n in [serial, refresh, retry, expire, minimum] {
+                    v.extend_from_slice(&n.to_be_bytes());
+                }
+            }
+            RData::DS {
+                key_tag,
+                algorithm,
+                digest_type,
+                digest,
+            } => {
+                v.extend_from_slice(&key_tag.to_be_bytes());
+                v.extend_from_slice(&[*algorithm, *digest_type]);
+                v.extend_from_slice(digest);
+            }
+            RData::DNSKEY {
+                flags,
+                protocol,
+                algorithm,
+                public_key,
+            } => {
+                v.extend_from_slice(&flags.to_be_bytes());
+                v.extend_from_slice(&[*protocol, *algorithm]);
+                v.extend_from_slice(public_key);
+            }
+            RData::RRSIG {
+                type_covered,
+                algorithm,
+                labels,
+                original_ttl,
+                expiration,
+                inception,
+                key_tag,
+                signer_name,
+                signature,
+            } => {
+                v.extend_from_slice(&u16::from(*type_covered).to_be_bytes());
+                v.extend_from_slice(&[*algorithm, *labels]);
+                for n in [original_ttl, expiration, inception] {
+                    v.extend_from_slice(&n.to_be_bytes());
+                }
+                v.extend_from_slice(&key_tag.to_be_bytes());
+                write_name(&mut v, signer_name)?;
+                v.extend_from_slice(signature);
+            }
+            RData::NSEC { next_name, types } => {
+                write_name(&mut v, next_name)?;
+                write_type_bitmap(&mut v, types);
+            }
+            RData::NSEC3 {
+                hash_algorithm,
+                flags,
+                iterations,
+                salt,
+                next_hashed_owner,
+                types,
+            } => {
+                v.extend_from_slice(&[*hash_algorithm, *flags]);
+                v.extend_from_slice(&iterations.to_be_bytes());
+                v.push(salt.len() as u8);
+                v.extend_from_slice(salt);
+                v.push(next_hashed_owner.len() as u8);
+                v.extend_from_slice(next_hashed_owner);
+                write_type_bitmap(&mut v, types);
+            }
+            RData::Unknown(data) => v.extend_from_slice(data),
+        }
+        Ok(v)
+    }
+}
+
+/// Writes `name` lowercase and without compression.
+fn write_name(message: &mut Vec<u8>, name: &str) -> Result<(), Error> {
+    // compression only points to names written before with the same encoder
+    NameEncoder::default().write(message, &name.to_ascii_lowercase())
+}
+
+/// Decodes the type bit maps of NSEC and NSEC3 records (RFC 4034 section 4.1.2).
+fn read_type_bitmap(mut data: &[u8]) -> Result<Vec<QueryType>, Error> {
+    let mut types = vec![];
+    while let [window, len, rest @ ..] = data {
+        let len = *len as usize;
+        if !(1..=32).contains(&len) || rest.len() < len {
+            return Err(Error::Dns("invalid type bit map".to_string()));
+        }
+        for (i, byte) in rest[..len].iter().enumerate() {
+            for bit in 0..8 {
+                if byte & (0x80 >> bit) != 0 {
+                    let value = (*window as u16) << 8 | (i * 8 + bit) as u16;
+                    types.push(QueryType::from(value));
+                }
+            }
+        }
+        data = &rest[len..];
+    }
+    if !data.is_empty() {
+        return Err(Error::Dns("invalid type bit map".to_string()));
+    }
+    Ok(types)
+}
+
+fn write_type_bitmap(message: &mut Vec<u8>, types: &[QueryType]) {
+    let mut values: Vec<u16> = types.iter().map(|t| u16::from(*t)).collect();
+    values.sort_unstable();
+    values.dedup();
+    for window in values.chunk_by(|a, b| a >> 8 == b >> 8) {
+        let len = (window[window.len() - 1] & 0xff) as usize / 8 + 1;
+        let mut bitmap = vec![0u8; len];
+        for value in window {
+            let low = (value & 0xff) as usize;
+            bitmap[low / 8] |= 0x80 >> (low % 8);
+        }
+        message.extend_from_slice(&[(window[0] >> 8) as u8, len as u8]);
+        message.extend(bitmap);
+    }
+}
+
+/// Base64 (RFC 4648 section 4), or its URL and filename safe variant without padding (section 5).
+fn base64(bytes: &[u8], url_safe: bool) -> String {
+    let alphabet: &[u8; 64] = match url_safe {
+        true => b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_",
+        false => b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/",
+    };
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(alphabet[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else if !url_safe {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Base32 with the extended hex alphabet and without padding, as in the owner names of NSEC3
+/// records (RFC 4648 section 7).
+fn base32hex(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"0123456789abcdefghijklmnopqrstuv";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for &b in bytes {
+        buffer = buffer << 8 | u32::from(b);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(ALPHABET[(buffer >> bits & 0x1f) as usize] as char);
         }
     }
+    if bits > 0 {
+        encoded.push(ALPHABET[(buffer << (5 - bits) & 0x1f) as usize] as char);
+    }
+    encoded
 }
 
 /**
@@ -292,13 +1551,23 @@ pub struct Response {
     pub answers: Vec<ResourceRecord>,
     pub authorities: Vec<ResourceRecord>,
     pub additionals: Vec<ResourceRecord>,
+    /// From the OPT record in the additional section
+    pub edns: Option<Edns>,
 }
 
-impl TryFrom<&[u8; 512]> for Response {
-    type Error = String;
+impl Response {
+    /// The response code, including the upper bits from the OPT record.
+    pub fn rcode(&self) -> Rcode {
+        let extended = self.edns.map_or(0, |edns| edns.extended_rcode);
+        Rcode::from((extended as u16) << 4 | self.header.rcode as u16)
+    }
+}
 
-    fn try_from(value: &[u8; 512]) -> Result<Self, String> {
-        let header = Header::from(value);
+impl TryFrom<&[u8]> for Response {
+    type Error = Error;
+
+    fn try_from(value: &[u8]) -> Result<Self, Error> {
+        let header = Header::try_from(value)?;
         let mut questions = vec![];
         let mut answers = vec![];
         let mut authorities = vec![];
@@ -317,12 +1586,17 @@ impl TryFrom<&[u8; 512]> for Response {
             additionals.push(ResourceRecord::try_from((value, &mut offset))?);
         }
 
+        let edns = additionals
+            .iter()
+            .find(|record| record.query_type == QueryType::OPT)
+            .map(Edns::from);
         Ok(Response {
             header,
             questions,
             answers,
             authorities,
             additionals,
+            edns,
         })
     }
 }
@@ -354,7 +1628,7 @@ Each resource record has the following format:
     /                                               /
     +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
  */
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ResourceRecord {
     pub name: String,
     pub query_type: QueryType,
@@ -385,45 +1659,18 @@ impl ResourceRecord {
     }
 }
 
-impl TryFrom<(&[u8; 512], &mut usize)> for ResourceRecord {
-    type Error = String;
+impl TryFrom<(&[u8], &mut usize)> for ResourceRecord {
+    type Error = Error;
 
-    fn try_from((bytes, offset): (&[u8; 512], &mut usize)) -> Result<Self, Self::Error> {
-        if *offset + 12 >= bytes.len() {
-            return Err("data is too short".to_string());
-        }
-        let name = if bytes[*offset] == 192 {
-            // message compression
-            let mut tmp_offset = bytes[*offset + 1] as usize;
-            *offset += 2;
-            get_name(bytes, &mut tmp_offset)?
-        } else {
-            get_name(bytes, offset)?
-        };
-        let query_type = match ((bytes[*offset] as u16) << 8) + (bytes[*offset + 1] as u16) {
-            1 => QueryType::A,
-            28 => QueryType::AAAA,
-            _ => panic!(),
-        };
-        *offset += 2;
-        let query_class = QueryClass::IN;
-        *offset += 2;
-        let ttl = ((bytes[*offset] as u32) << 24)
-            + ((bytes[*offset + 1] as u32) << 16)
-            + ((bytes[*offset + 2] as u32) << 8)
-            + (bytes[*offset + 3] as u32);
+    fn try_from((bytes, offset): (&[u8], &mut usize)) -> Result<Self, Self::Error> {
+        let name = get_name(bytes, offset)?;
+        let query_type = QueryType::from(read_u16(bytes, offset)?);
+        let query_class = QueryClass::from(read_u16(bytes, offset)?);
+        let ttl = u32::from_be_bytes(slice(bytes, *offset, 4)?.try_into().unwrap());
         *offset += 4;
-        let rdlength = ((bytes[*offset] as u16) << 8) + bytes[*offset + 1] as u16;
-        *offset += 2;
-        let rdata = match query_type {
-            QueryType::A => RData::A([
-                bytes[*offset],
-                bytes[*offset + 1],
-                bytes[*offset + 2],
-                bytes[*offset + 3],
-            ]),
-            _ => unimplemented!(),
-        };
+        let rdlength = read_u16(bytes, offset)?;
+        slice(bytes, *offset, rdlength as usize)?;
+        let rdata = read_rdata(bytes, *offset, rdlength as usize, query_type)?;
         *offset += rdlength as usize;
 
         Ok(ResourceRecord {
@@ -437,27 +1684,301 @@ impl TryFrom<(&[u8; 512], &mut usize)> for ResourceRecord {
     }
 }
 
-fn get_name(bytes: &[u8; 512], offset: &mut usize) -> Result<String, String> {
+/// Decodes the `len` bytes of RDATA at `start`. Names in it may point to other parts of the
+/// message.
+fn read_rdata(bytes: &[u8], start: usize, len: usize, rtype: QueryType) -> Result<RData, Error> {
+    let data = &bytes[start..start + len];
+    let mut pos = start;
+    let rdata = match rtype {
+        QueryType::A => RData::A(data.try_into().map_err(|_| invalid_rdata(len, rtype))?),
+        QueryType::AAAA => RData::AAAA(data.try_into().map_err(|_| invalid_rdata(len, rtype))?),
+        QueryType::CNAME => RData::CNAME(get_name(bytes, &mut pos)?),
+        QueryType::NS => RData::NS(get_name(bytes, &mut pos)?),
+        QueryType::PTR => RData::PTR(get_name(bytes, &mut pos)?),
+        QueryType::MX => RData::MX {
+            preference: read_u16(bytes, &mut pos)?,
+            exchange: get_name(bytes, &mut pos)?,
+        },
+        QueryType::SRV => RData::SRV {
+            priority: read_u16(bytes, &mut pos)?,
+            weight: read_u16(bytes, &mut pos)?,
+            port: read_u16(bytes, &mut pos)?,
+            target: get_name(bytes, &mut pos)?,
+        },
+        QueryType::SOA => {
+            let mname = get_name(bytes, &mut pos)?;
+            let rname = get_name(bytes, &mut pos)?;
+            let mut numbers = [0u32; 5];
+            for number in &mut numbers {
+                *number = u32::from_be_bytes(slice(bytes, pos, 4)?.try_into().unwrap());
+                pos += 4;
+            }
+            let [serial, refresh, retry, expire, minimum] = numbers;
+            RData::SOA {
+                mname,
+                rname,
+                serial,
+                refresh,
+                retry,
+                expire,
+                minimum,
+            }
+        }
+        // one or more strings with a length byte each
+        QueryType::TXT => {
+            let mut strings = vec![];
+            while pos < start + len {
+                let n = data[pos - start] as usize;
+                strings.push(slice(data, pos - start + 1, n)?.to_vec());
+                pos += 1 + n;
+            }
+            RData::TXT(strings)
+        }
+        // RFC 8659 section 4.1
+        QueryType::CAA => {
+            let (flags, tag_len) = match data {
+                [flags, tag_len, ..] => (*flags, *tag_len as usize),
+                _ => return Err(invalid_rdata(len, rtype)),
+            };
+            let tag = slice(data, 2, tag_len)?;
+            pos = start + len;
+            RData::CAA {
+                flags,
+                tag: String::from_utf8_lossy(tag).into_owned(),
+                value: data[2 + tag_len..].to_vec(),
+            }
+        }
+        QueryType::DS | QueryType::DNSKEY if len < 4 => return Err(invalid_rdata(len, rtype)),
+        QueryType::DS => {
+            pos = start + len;
+            RData::DS {
+                key_tag: u16::from_be_bytes([data[0], data[1]]),
+                algorithm: data[2],
+                digest_type: data[3],
+                digest: data[4..].to_vec(),
+            }
+        }
+        QueryType::DNSKEY => {
+            pos = start + len;
+            RData::DNSKEY {
+                flags: u16::from_be_bytes([data[0], data[1]]),
+                protocol: data[2],
+                algorithm: data[3],
+                public_key: data[4..].to_vec(),
+            }
+        }
+        QueryType::RRSIG => {
+            let fixed = data.get(..18).ok_or_else(|| invalid_rdata(len, rtype))?;
+            let u32_at = |i: usize| u32::from_be_bytes(fixed[i..i + 4].try_into().unwrap());
+            pos += 18;
+            let signer_name = get_name(bytes, &mut pos)?;
+            let signature = slice(bytes, pos, (start + len).saturating_sub(pos))?.to_vec();
+            pos = pos.max(start + len);
+            RData::RRSIG {
+                type_covered: QueryType::from(u16::from_be_bytes([fixed[0], fixed[1]])),
+                algorithm: fixed[2],
+                labels: fixed[3],
+                original_ttl: u32_at(4),
+                expiration: u32_at(8),
+                inception: u32_at(12),
+                key_tag: u16::from_be_bytes([fixed[16], fixed[17]]),
+                signer_name,
+                signature,
+            }
+        }
+        QueryType::NSEC => {
+            let next_name = get_name(bytes, &mut pos)?;
+            let types = read_type_bitmap(slice(bytes, pos, (start + len).saturating_sub(pos))?)?;
+            pos = pos.max(start + len);
+            RData::NSEC { next_name, types }
+        }
+        QueryType::NSEC3 => {
+            let invalid = || invalid_rdata(len, rtype);
+            let salt_len = *data.get(4).ok_or_else(invalid)? as usize;
+            let salt = data.get(5..5 + salt_len).ok_or_else(invalid)?;
+            let hash_len = *data.get(5 + salt_len).ok_or_else(invalid)? as usize;
+            let hash = data
+                .get(6 + salt_len..6 + salt_len + hash_len)
+                .ok_or_else(invalid)?;
+            pos = start + len;
+            RData::NSEC3 {
+                hash_algorithm: data[0],
+                flags: data[1],
+                iterations: u16::from_be_bytes([data[2], data[3]]),
+                salt: salt.to_vec(),
+                next_hashed_owner: hash.to_vec(),
+                types: read_type_bitmap(&data[6 + salt_len + hash_len..])?,
+            }
+        }
+        QueryType::OPT | QueryType::Other(_) => return Ok(RData::Unknown(data.to_vec())),
+    };
+    match rdata {
+        RData::A(_) | RData::AAAA(_) => Ok(rdata),
+        _ if pos == start + len => Ok(rdata),
+        _ => Err(invalid_rdata(len, rtype)),
+    }
+}
+
+fn invalid_rdata(len: usize, rtype: QueryType) -> Error {
+    Error::Dns(format!("invalid RDATA length {len} for {rtype:?}"))
+}
+
+/// Returns `len` bytes at `offset`, or an error instead of reading past the end of the message.
+fn slice(bytes: &[u8], offset: usize, len: usize) -> Result<&[u8], Error> {
+    bytes
+        .get(offset..offset + len)
+        .ok_or_else(|| Error::Dns("message is truncated".to_string()))
+}
+
+fn read_u16(bytes: &[u8], offset: &mut usize) -> Result<u16, Error> {
+    let value = u16::from_be_bytes(slice(bytes, *offset, 2)?.try_into().unwrap());
+    *offset += 2;
+    Ok(value)
+}
+
+/// Reads a name that may end with a pointer to a name elsewhere in the message (RFC 1035
+/// section 4.1.4), and moves `offset` past the name or its first pointer.
+fn get_name(bytes: &[u8], offset: &mut usize) -> Result<String, Error> {
     let mut name: Vec<String> = vec![];
-    while bytes[*offset] != 0 {
-        let n = bytes[*offset] as usize;
-        *offset += 1;
-        let end = *offset + n;
-        if bytes.len() < end {
-            return Err("Invalid name".to_string());
+    let mut pos = *offset;
+    // where the labels being read start, pointers have to lead to data before it
+    let mut start = pos;
+    let mut end = None;
+    let mut len = 1; // length on the wire, including the root label
+    loop {
+        let n = slice(bytes, pos, 1)?[0] as usize;
+        match n & 0xc0 {
+            0x00 => {}
+            0xc0 => {
+                let target = (read_u16(bytes, &mut pos.clone())? & 0x3fff) as usize;
+                if target >= start {
+                    return Err(Error::Dns(format!(
+                        "compression pointer at {pos} doesn't point backwards"
+                    )));
+                }
+                end.get_or_insert(pos + 2);
+                pos = target;
+                start = target;
+                continue;
+            }
+            _ => return Err(Error::Dns(format!("invalid label length {n}"))),
+        }
+        pos += 1;
+        if n == 0 {
+            break;
+        }
+        len += n + 1;
+        if len > 255 {
+            return Err(Error::Dns("name is longer than 255 bytes".to_string()));
+        }
+        let label = slice(bytes, pos, n)?;
+        // such labels would be ambiguous or garbled as text
+        if !label.is_ascii() || label.contains(&b'.') {
+            return Err(Error::Dns(format!(
+                "unsupported label {:?}",
+                String::from_utf8_lossy(label)
+            )));
+        }
+        name.push(String::from_utf8_lossy(label).to_ascii_lowercase());
+        pos += n;
+    }
+    *offset = end.unwrap_or(pos);
+    Ok(name.join("."))
+}
+
+/**
+ * Writes names into a message, replacing the parts written before with pointers to them
+ */
+#[derive(Debug, Default)]
+struct NameEncoder {
+    // where each name written so far and its suffixes start in the message
+    offsets: HashMap<String, u16>,
+}
+
+impl NameEncoder {
+    /// Writes `name`, which must have labels of 1 to 63 bytes and be at most 255 bytes long on
+    /// the wire (RFC 1035 section 2.3.4). Nothing is written if it doesn't.
+    fn write(&mut self, message: &mut Vec<u8>, name: &str) -> Result<(), Error> {
+        let mut rest = name.strip_suffix('.').unwrap_or(name);
+        if !rest.is_empty() {
+            let mut len = 1; // the root label
+            for label in rest.split('.') {
+                if label.is_empty() || label.len() > 63 {
+                    return Err(Error::Dns(format!("invalid label {label:?} in {name}")));
+                }
+                len += label.len() + 1;
+            }
+            if len > 255 {
+                return Err(Error::Dns(format!("{name} is longer than 255 bytes")));
+            }
+        }
+        while !rest.is_empty() {
+            let suffix = rest.to_ascii_lowercase();
+            if let Some(offset) = self.offsets.get(&suffix) {
+                message.extend_from_slice(&(0xc000 | offset).to_be_bytes());
+                return Ok(());
+            }
+            // a pointer has 14 bits for the offset
+            if let Ok(offset @ 0..0x4000) = u16::try_from(message.len()) {
+                self.offsets.insert(suffix, offset);
+            }
+            let (label, next) = rest.split_once('.').unwrap_or((rest, ""));
+            message.push(label.len() as u8);
+            message.extend_from_slice(label.as_bytes());
+            rest = next;
+        }
+        message.push(0);
+        Ok(())
+    }
+}
+
+/**
+ * Response code of a DNS message (RFC 1035 section 4.1.1)
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rcode {
+    NoError,
+    FormErr,
+    ServFail,
+    NXDomain,
+    NotImp,
+    Refused,
+    /// Any other code, which may use the upper bits of EDNS (RFC 6891 section 6.1.3)
+    Other(u16),
+}
+
+impl From<u16> for Rcode {
+    fn from(value: u16) -> Self {
+        match value {
+            0 => Rcode::NoError,
+            1 => Rcode::FormErr,
+            2 => Rcode::ServFail,
+            3 => Rcode::NXDomain,
+            4 => Rcode::NotImp,
+            5 => Rcode::Refused,
+            other => Rcode::Other(other),
+        }
+    }
+}
+
+impl Display for Rcode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rcode::NoError => write!(f, "no error"),
+            Rcode::FormErr => write!(f, "format error"),
+            Rcode::ServFail => write!(f, "server failure"),
+            Rcode::NXDomain => write!(f, "no such domain"),
+            Rcode::NotImp => write!(f, "not implemented"),
+            Rcode::Refused => write!(f, "query refused"),
+            Rcode::Other(code) => write!(f, "response code {code}"),
         }
-        let label = String::from_utf8_lossy(&bytes[*offset..end]).to_ascii_lowercase();
-        name.push(label);
-        *offset = end;
     }
-    *offset += 1;
-    let name = name.join(".");
-    Ok(name)
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use crate::dns::*;
+    use std::net::TcpListener;
 
     #[test]
     fn test_from_bytes_to_question() {
@@ -476,8 +1997,8 @@ mod tests {
         }
         offset = initial_offset; // reset offset
         assert_eq!(
-            Question::try_from((&bytes, &mut offset)),
-            Ok(Question::new("example.com", QueryType::A)),
+            Question::try_from((&bytes[..], &mut offset)).unwrap(),
+            Question::new("example.com", QueryType::A),
         );
         assert_eq!(offset, header_payload.len() + initial_offset);
     }
@@ -500,16 +2021,771 @@ mod tests {
             offset += 1;
         }
         assert_eq!(
-            ResourceRecord::try_from((&bytes, &mut start_offset)),
-            Ok(ResourceRecord::new(
+            ResourceRecord::try_from((&bytes[..], &mut start_offset)).unwrap(),
+            ResourceRecord::new(
                 "dns.google".to_string(),
                 QueryType::A,
                 QueryClass::IN,
                 691,
                 4,
                 RData::A([8, 8, 4, 4])
-            )),
+            ),
         );
         assert_eq!(offset, rr_payload.len());
     }
+
+    #[test]
+    fn test_malformed_response_is_an_error() {
+        // an invalid label, a record past the end of the message, an unknown type and a bad RDATA
+        let mut bytes: [u8; 512] = [0; 512];
+        bytes[0] = 0x50; // longer than a label can be
+        assert!(ResourceRecord::try_from((&bytes[..], &mut 0)).is_err());
+        assert!(ResourceRecord::try_from((&bytes[..], &mut 510)).is_err());
+
+        let record = [
+            0xc0, 0x0c, 0xff, 0x00, 0x00, 0x01, 0, 0, 0, 60, 0x00, 0x02, b'o', b'k',
+        ];
+        bytes[12] = 0;
+        bytes[20..20 + record.len()].copy_from_slice(&record);
+        let parsed = ResourceRecord::try_from((&bytes[..], &mut 20)).unwrap();
+        assert_eq!(parsed.query_type, QueryType::Other(0xff00));
+        assert_eq!(parsed.rdata.to_string(), "\\# 2 6f6b");
+
+        bytes[20 + 2..20 + 4].copy_from_slice(&[0, 1]); // type A with 2 bytes of data
+        assert!(ResourceRecord::try_from((&bytes[..], &mut 20)).is_err());
+        // more questions than fit into the message
+        let mut header = [0u8; 512];
+        header[4..6].copy_from_slice(&[0xff, 0xff]);
+        assert!(Response::try_from(&header[..]).is_err());
+        assert!(Response::try_from(&header[..11]).is_err());
+    }
+
+    #[test]
+    fn test_rdata_display() {
+        assert_eq!(RData::A([8, 8, 4, 4]).to_string(), "8.8.4.4");
+        let mut ip = [0u8; 16];
+        ip[..4].copy_from_slice(&[0x20, 0x01, 0x0d, 0xb8]);
+        ip[15] = 1;
+        assert_eq!(RData::AAAA(ip).to_string(), "2001:db8::1");
+        let txt = RData::TXT(vec![b"v=spf1 -all".to_vec(), b"say \"hi\"\n".to_vec()]);
+        assert_eq!(txt.to_string(), r#""v=spf1 -all" "say \"hi\"\010""#);
+        let caa = RData::CAA {
+            flags: 0,
+            tag: "issue".to_string(),
+            value: b"letsencrypt.org".to_vec(),
+        };
+        assert_eq!(caa.to_string(), r#"0 issue "letsencrypt.org""#);
+    }
+
+    /// Returns the header and question of `query` as a response without records. The OPT record
+    /// is left out, since records of other sections go before it.
+    pub fn response_to(query: &[u8]) -> Vec<u8> {
+        let len = match query[11] {
+            1 => query.len() - 11,
+            _ => query.len(),
+        };
+        let mut response = query[..len].to_vec();
+        response[2] |= 0x80; // QR
+        response[11] = 0; // ARCOUNT
+        response
+    }
+
+    /// Answers queries on `sock` with an A record for 192.0.2.1 and an AAAA record for 2001:db8::1
+    /// for names under example.com, and with NXDOMAIN for any other name.
+    fn serve(sock: UdpSocket, queries: usize) {
+        for _ in 0..queries {
+            let mut buf = [0u8; 512];
+            let (len, peer) = sock.recv_from(&mut buf).unwrap();
+            sock.send_to(&answer(&buf[..len]), peer).unwrap();
+        }
+    }
+
+    /// An A or AAAA record for names under example.com, NXDOMAIN for others.
+    pub fn answer(query: &[u8]) -> Vec<u8> {
+        let mut response = response_to(query);
+        let len = response.len();
+        if !response[..len - 4].ends_with(b"\x07example\x03com\x00") {
+            response[3] |= 3; // NXDOMAIN
+            return response;
+        }
+        response[7] = 1; // ANCOUNT
+        let qtype = u16::from_be_bytes([response[len - 4], response[len - 3]]);
+        response.extend_from_slice(&[0xc0, 0x0c]);
+        response.extend_from_slice(&qtype.to_be_bytes());
+        response.extend_from_slice(&[0, 1, 0, 0, 0, 60]);
+        match QueryType::from(qtype) {
+            QueryType::A => response.extend_from_slice(&[0, 4, 192, 0, 2, 1]),
+            _ => {
+                response.extend_from_slice(&[0, 16, 0x20, 0x01, 0x0d, 0xb8]);
+                response.extend_from_slice(&[0; 11]);
+                response.push(1);
+            }
+        }
+        response
+    }
+
+    #[test]
+    fn test_resolve_over_ipv6() {
+        let sock = UdpSocket::bind("[::1]:0").unwrap();
+        let addr = sock.local_addr().unwrap();
+        let server = std::thread::spawn(move || serve(sock, 3));
+        let resolver = Resolver::new(Some(addr.ip()), Some(addr.port())).with_cache(None);
+        assert_eq!(
+            resolver.resolve_all(1, "example.com").unwrap(),
+            vec![
+                "2001:db8::1".parse::<IpAddr>().unwrap(),
+                "192.0.2.1".parse().unwrap()
+            ]
+        );
+        assert_eq!(
+            resolver.resolve(3, "example.com").unwrap(),
+            "192.0.2.1".parse::<IpAddr>().unwrap()
+        );
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_resolve_without_queries() {
+        // nothing answers on this port, so any query would fail
+        let resolver = Resolver::new(Some([127, 0, 0, 1].into()), Some(9)).with_hosts(None);
+        assert_eq!(
+            resolver.resolve_all(1, "192.0.2.1").unwrap(),
+            ["192.0.2.1".parse::<IpAddr>().unwrap()]
+        );
+        assert_eq!(
+            resolver.resolve_all(1, "[2001:db8::1]").unwrap(),
+            ["2001:db8::1".parse::<IpAddr>().unwrap()]
+        );
+        assert_eq!(
+            resolver.resolve(1, "app.localhost").unwrap(),
+            "127.0.0.1".parse::<IpAddr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_resolve_with_search_list() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = sock.local_addr().unwrap();
+        let server = std::thread::spawn(move || serve(sock, 3));
+        let resolver = Resolver::with_config(ResolvConf {
+            nameservers: vec![addr],
+            search: vec!["corp.invalid".to_string(), "example.com".to_string()],
+            ..ResolvConf::default()
+        })
+        .with_hosts(None);
+        // www.corp.invalid doesn't exist, www.example.com does
+        assert_eq!(
+            resolver.resolve(1, "www").unwrap(),
+            "192.0.2.1".parse::<IpAddr>().unwrap()
+        );
+        // absolute names aren't expanded
+        assert!(matches!(
+            resolver.resolve(3, "www."),
+            Err(Error::Rcode {
+                rcode: Rcode::NXDomain,
+                ..
+            })
+        ));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_resolver_from_resolv_conf() {
+        let path = std::env::temp_dir().join(format!("fetch-resolv-{}", std::process::id()));
+        std::fs::write(&path, "nameserver 192.0.2.53\nsearch example.com\n").unwrap();
+        let resolver = Resolver::from_resolv_conf(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            resolver.config().nameservers,
+            ["192.0.2.53:53".parse().unwrap()]
+        );
+        assert_eq!(resolver.config().search, ["example.com"]);
+        assert!(Resolver::from_resolv_conf(&path).is_err());
+    }
+
+    #[test]
+    fn test_query_retries_and_ignores_forged_responses() {
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let resolver = Resolver::with_config(ResolvConf {
+            nameservers: vec![silent.local_addr().unwrap(), sock.local_addr().unwrap()],
+            timeout: Duration::from_millis(100),
+            ..ResolvConf::default()
+        });
+        let server = std::thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            // the first query is lost
+            sock.recv_from(&mut buf).unwrap();
+            let (len, peer) = sock.recv_from(&mut buf).unwrap();
+            let mut response = answer(&buf[..len]);
+            let forger = UdpSocket::bind("127.0.0.1:0").unwrap();
+            forger.send_to(&response, peer).unwrap();
+            response[1] ^= 1;
+            sock.send_to(&response, peer).unwrap();
+            response[1] ^= 1;
+            // the same ID, but for another question
+            let mut forged = response.clone();
+            forged[26] = 28;
+            *forged.last_mut().unwrap() = 66;
+            sock.send_to(&forged, peer).unwrap();
+            sock.send_to(&response, peer).unwrap();
+        });
+        assert_eq!(
+            resolver
+                .query(1, "example.com", QueryType::A)
+                .unwrap()
+                .addrs,
+            ["192.0.2.1".parse::<IpAddr>().unwrap()]
+        );
+        server.join().unwrap();
+
+        let resolver = Resolver::with_config(ResolvConf {
+            attempts: 1,
+            ..resolver.config().clone()
+        });
+        assert!(matches!(
+            resolver.query(1, "example.com", QueryType::A),
+            Err(Error::Timeout)
+        ));
+    }
+
+    #[test]
+    fn test_query_rotates_servers() {
+        let socks = [
+            UdpSocket::bind("127.0.0.1:0").unwrap(),
+            UdpSocket::bind("127.0.0.1:0").unwrap(),
+        ];
+        let resolver = Resolver::with_config(ResolvConf {
+            nameservers: socks.iter().map(|s| s.local_addr().unwrap()).collect(),
+            rotate: true,
+            ..ResolvConf::default()
+        })
+        .with_cache(None);
+        // each server answers exactly one query
+        let servers = socks.map(|sock| std::thread::spawn(move || serve(sock, 1)));
+        for id in 0..2 {
+            resolver.query(id, "example.com", QueryType::A).unwrap();
+        }
+        for server in servers {
+            server.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_query_falls_back_to_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let sock = UdpSocket::bind(addr).unwrap();
+        let server = std::thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            let (len, peer) = sock.recv_from(&mut buf).unwrap();
+            let mut truncated = buf[..len].to_vec();
+            truncated[2] |= 0x82; // QR and TC
+            sock.send_to(&truncated, peer).unwrap();
+            // the truncated query again, then a query in TCP-only mode
+            for _ in 0..2 {
+                let (mut conn, _) = listener.accept().unwrap();
+                let mut len = [0; 2];
+                conn.read_exact(&mut len).unwrap();
+                let mut query = vec![0; u16::from_be_bytes(len) as usize];
+                conn.read_exact(&mut query).unwrap();
+                // more addresses than fit into 512 bytes
+                let mut response = response_to(&query);
+                response[7] = 40;
+                for i in 0..40 {
+                    response.extend_from_slice(&[0xc0, 0x0c, 0, 1, 0, 1, 0, 0, 0, 60]);
+                    response.extend_from_slice(&[0, 4, 192, 0, 2, i]);
+                }
+                conn.write_all(&(response.len() as u16).to_be_bytes())
+                    .unwrap();
+                conn.write_all(&response).unwrap();
+            }
+        });
+        let mut config = ResolvConf {
+            nameservers: vec![addr],
+            ..ResolvConf::default()
+        };
+        let resolver = Resolver::with_config(config.clone());
+        let addrs = resolver
+            .query(1, "example.com", QueryType::A)
+            .unwrap()
+            .addrs;
+        assert_eq!(addrs.len(), 40);
+        config.tcp = true;
+        let resolver = Resolver::with_config(config);
+        let addrs = resolver
+            .query(2, "example.com", QueryType::A)
+            .unwrap()
+            .addrs;
+        assert_eq!(addrs[39], "192.0.2.39".parse::<IpAddr>().unwrap());
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_edns() {
+        let query =
+            Vec::try_from(Query::new(1, "example.com", QueryType::A).with_edns(1232)).unwrap();
+        assert_eq!(query[11], 1); // ARCOUNT
+        assert!(query.ends_with(&[0, 0, 41, 0x04, 0xd0, 0, 0, 0, 0, 0, 0]));
+
+        // BADVERS is 16, with the upper bits in the OPT record
+        let mut response = response_to(&query);
+        response[11] = 1;
+        response.extend_from_slice(&[0, 0, 41, 0x10, 0x00, 1, 0, 0x80, 0, 0, 0]);
+        let response = Response::try_from(&response[..]).unwrap();
+        assert_eq!(
+            response.edns,
+            Some(Edns {
+                udp_payload_size: 4096,
+                extended_rcode: 1,
+                version: 0,
+                dnssec_ok: true
+            })
+        );
+        assert_eq!(response.rcode(), Rcode::Other(16));
+
+        // the DO bit is set even if EDNS is turned off
+        let mut resolver = Resolver::with_config(ResolvConf {
+            udp_payload_size: None,
+            ..ResolvConf::default()
+        });
+        resolver.dnssec = Some(TrustAnchor::root());
+        let query = resolver
+            .encode_query(1, "example.com", QueryType::A)
+            .unwrap();
+        assert!(query.ends_with(&[0, 0, 41, 0x02, 0x00, 0, 0, 0x80, 0, 0, 0]));
+    }
+
+    #[test]
+    fn test_query_without_edns_after_formerr() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let resolver = Resolver::with_config(ResolvConf {
+            nameservers: vec![sock.local_addr().unwrap()],
+            ..ResolvConf::default()
+        });
+        let server = std::thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            let (len, peer) = sock.recv_from(&mut buf).unwrap();
+            assert_eq!(buf[11], 1);
+            let mut response = response_to(&buf[..len]);
+            response[3] |= 1; // FORMERR
+            sock.send_to(&response, peer).unwrap();
+            let (len, peer) = sock.recv_from(&mut buf).unwrap();
+            assert_eq!(buf[11], 0);
+            sock.send_to(&answer(&buf[..len]), peer).unwrap();
+        });
+        assert_eq!(
+            resolver
+                .query(1, "example.com", QueryType::A)
+                .unwrap()
+                .addrs,
+            ["192.0.2.1".parse::<IpAddr>().unwrap()]
+        );
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_name_compression() {
+        let mut bytes = vec![0u8; 260];
+        bytes.extend_from_slice(b"\x07example\x03com\x00");
+        // a label followed by a pointer above offset 255
+        bytes.extend_from_slice(b"\x03www\xc1\x04");
+        let mut offset = 273;
+        assert_eq!(get_name(&bytes, &mut offset).unwrap(), "www.example.com");
+        assert_eq!(offset, 279);
+
+        // pointers to the name itself or to later data
+        bytes.extend_from_slice(b"\x01a\xc1\x17\xc1\x1e\xc1\x1c");
+        assert!(get_name(&bytes, &mut 279).is_err());
+        assert!(get_name(&bytes, &mut 283).is_err());
+        let mut long = vec![];
+        for _ in 0..4 {
+            long.push(63);
+            long.extend_from_slice(&[b'a'; 63]);
+        }
+        long.push(0);
+        assert!(get_name(&long, &mut 0).is_err());
+        // a dot or a byte that isn't ASCII in a label
+        assert!(get_name(b"\x07example\x03com\x00", &mut 0).is_ok());
+        assert!(get_name(b"\x0bexample.com\x00", &mut 0).is_err());
+        assert!(get_name(b"\x08ex\xc3\xa4mple\x03com\x00", &mut 0).is_err());
+
+        let mut message = vec![0u8; 12];
+        let mut names = NameEncoder::default();
+        names.write(&mut message, "www.example.com").unwrap();
+        names.write(&mut message, "mail.Example.com.").unwrap();
+        names.write(&mut message, "example.com").unwrap();
+        assert_eq!(&message[29..], b"\x04mail\xc0\x10\xc0\x10");
+        let mut offset = 29;
+        assert_eq!(get_name(&message, &mut offset).unwrap(), "mail.example.com");
+        assert_eq!(get_name(&message, &mut offset).unwrap(), "example.com");
+    }
+
+    #[test]
+    fn test_encode_name_limits() {
+        let write = |name: &str| {
+            let mut message = vec![];
+            let result = NameEncoder::default().write(&mut message, name);
+            (result, message)
+        };
+        // a length of 192 or more would read as a compression pointer
+        let (result, message) = write(&format!("{}.com", "a".repeat(192)));
+        assert!(result.is_err());
+        assert!(message.is_empty());
+        assert!(write(&format!("{}.com", "a".repeat(64))).0.is_err());
+        assert!(write(&format!("{}.com", "a".repeat(63))).0.is_ok());
+        // an empty label would end the name early
+        assert!(write("a..b").0.is_err());
+        assert!(write(".com").0.is_err());
+        assert_eq!(write(".").1, [0]);
+        // 4 labels of 63 bytes are 257 bytes on the wire
+        let long = vec!["a".repeat(63); 4].join(".");
+        assert!(write(&long).0.is_err());
+        assert!(write(&long[2..]).0.is_ok());
+        assert!(Vec::try_from(Query::new(1, &long, QueryType::A)).is_err());
+    }
+
+    /// Appends a record of `rtype` for `name` to the answer section of `response`.
+    fn add_answer(response: &mut Vec<u8>, name: &str, rtype: QueryType, rdata: &[u8]) {
+        NameEncoder::default().write(response, name).unwrap();
+        response.extend_from_slice(&u16::from(rtype).to_be_bytes());
+        response.extend_from_slice(&[0, 1, 0, 0, 0, 60]);
+        response.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        response.extend_from_slice(rdata);
+        response[7] += 1;
+    }
+
+    fn encode_name(name: &str) -> Vec<u8> {
+        let mut v = vec![];
+        NameEncoder::default().write(&mut v, name).unwrap();
+        v
+    }
+
+    #[test]
+    fn test_query_follows_cnames() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let resolver = Resolver::with_config(ResolvConf {
+            nameservers: vec![sock.local_addr().unwrap()],
+            ..ResolvConf::default()
+        });
+        let server = std::thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            // a chain that leaves the answer section, a query for its end and a loop
+            for (name, records) in [
+                (
+                    "www.example.com",
+                    vec![
+                        (
+                            "cdn.example.net",
+                            QueryType::CNAME,
+                            encode_name("edge.example.org"),
+                        ),
+                        (
+                            "www.example.com",
+                            QueryType::CNAME,
+                            encode_name("cdn.example.net"),
+                        ),
+                    ],
+                ),
+                (
+                    "edge.example.org",
+                    vec![
+                        ("other.example.org", QueryType::A, vec![192, 0, 2, 9]),
+                        ("edge.example.org", QueryType::A, vec![192, 0, 2, 7]),
+                    ],
+                ),
+                (
+                    "loop.example.com",
+                    vec![(
+                        "loop.example.com",
+                        QueryType::CNAME,
+                        encode_name("loop.example.com"),
+                    )],
+                ),
+            ] {
+                let (len, peer) = sock.recv_from(&mut buf).unwrap();
+                let mut response = response_to(&buf[..len]);
+                assert!(response.ends_with(&[&encode_name(name)[..], &[0, 1, 0, 1]].concat()));
+                for (owner, rtype, rdata) in records {
+                    add_answer(&mut response, owner, rtype, &rdata);
+                }
+                sock.send_to(&response, peer).unwrap();
+            }
+        });
+        assert_eq!(
+            resolver.query(1, "WWW.example.com", QueryType::A).unwrap(),
+            LookupIp {
+                canonical_name: "edge.example.org".to_string(),
+                addrs: vec!["192.0.2.7".parse().unwrap()]
+            }
+        );
+        assert!(matches!(
+            resolver.query(2, "loop.example.com", QueryType::A),
+            Err(Error::Dns(_))
+        ));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_query_caches_answers() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let resolver = Resolver::with_config(ResolvConf {
+            nameservers: vec![sock.local_addr().unwrap()],
+            ..ResolvConf::default()
+        });
+        let server = std::thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            let (len, peer) = sock.recv_from(&mut buf).unwrap();
+            sock.send_to(&answer(&buf[..len]), peer).unwrap();
+            // NXDOMAIN with the SOA record of the zone
+            let (len, peer) = sock.recv_from(&mut buf).unwrap();
+            let mut response = answer(&buf[..len]);
+            let mut soa = encode_name("ns.invalid");
+            soa.extend(encode_name("hostmaster.invalid"));
+            soa.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4, 0, 0, 0, 30]);
+            add_answer(&mut response, "invalid", QueryType::SOA, &soa);
+            response[7] = 0;
+            response[9] = 1; // NSCOUNT
+            sock.send_to(&response, peer).unwrap();
+        });
+        for id in 0..2 {
+            let lookup = resolver.query(id, "example.com", QueryType::A).unwrap();
+            assert_eq!(lookup.addrs, ["192.0.2.1".parse::<IpAddr>().unwrap()]);
+            assert!(resolver.query(id, "www.invalid", QueryType::A).is_err());
+        }
+        server.join().unwrap();
+
+        let cache = resolver.cache().unwrap();
+        let (answer, ttl) = cache.get("www.invalid", QueryType::A).unwrap();
+        assert_eq!(answer, CachedAnswer::NoSuchDomain);
+        assert!(ttl <= Duration::from_secs(30));
+        // seeded answers are returned without a query
+        let seeded = LookupIp {
+            canonical_name: "seeded.example".to_string(),
+            addrs: vec!["192.0.2.80".parse().unwrap()],
+        };
+        cache.insert(
+            "seeded.example",
+            QueryType::A,
+            CachedAnswer::Found(seeded.clone()),
+            Duration::from_secs(60),
+        );
+        assert_eq!(
+            resolver.query(3, "seeded.example", QueryType::A).unwrap(),
+            seeded
+        );
+        cache.clear();
+        assert!(cache.get("example.com", QueryType::A).is_none());
+    }
+
+    #[test]
+    fn test_lookup_record_types() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let resolver = Resolver::with_config(ResolvConf {
+            nameservers: vec![sock.local_addr().unwrap()],
+            ..ResolvConf::default()
+        });
+        let server = std::thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            for _ in 0..4 {
+                let (len, peer) = sock.recv_from(&mut buf).unwrap();
+                let mut response = response_to(&buf[..len]);
+                let qtype = QueryType::from(u16::from_be_bytes([buf[25], buf[26]]));
+                let rdata = match qtype {
+                    // the exchange points to the name in the question
+                    QueryType::MX => b"\x00\x0a\x04mail\xc0\x0c".to_vec(),
+                    QueryType::TXT => b"\x0bv=spf1 -all\x00".to_vec(),
+                    QueryType::SRV => [&[0, 1, 0, 5, 0x1f, 0x90][..], &encode_name("web")].concat(),
+                    _ => b"\x80\x05issue;".to_vec(),
+                };
+                add_answer(&mut response, "example.com", qtype, &rdata);
+                sock.send_to(&response, peer).unwrap();
+            }
+        });
+        let records = |rtype| {
+            let lookup = resolver.lookup(1, "example.com", rtype).unwrap();
+            assert_eq!(lookup.canonical_name, "example.com");
+            lookup.records[0].rdata.clone()
+        };
+        assert_eq!(
+            records(RecordType::MX),
+            RData::MX {
+                preference: 10,
+                exchange: "mail.example.com".to_string()
+            }
+        );
+        assert_eq!(
+            records(RecordType::TXT),
+            RData::TXT(vec![b"v=spf1 -all".to_vec(), vec![]])
+        );
+        assert_eq!(records(RecordType::SRV).to_string(), "1 5 8080 web.");
+        assert_eq!(
+            records(RecordType::CAA),
+            RData::CAA {
+                flags: 128,
+                tag: "issue".to_string(),
+                value: b";".to_vec()
+            }
+        );
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_reverse_name() {
+        assert_eq!(
+            reverse_name("192.0.2.1".parse().unwrap()),
+            "1.2.0.192.in-addr.arpa"
+        );
+        assert_eq!(
+            reverse_name("2001:db8::1".parse().unwrap()),
+            "1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa"
+        );
+    }
+
+    #[test]
+    fn test_reverse_confirmed() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let resolver = Resolver::with_config(ResolvConf {
+            nameservers: vec![sock.local_addr().unwrap()],
+            search: vec!["example.com".to_string()],
+            ..ResolvConf::default()
+        });
+        let server = std::thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            let (len, peer) = sock.recv_from(&mut buf).unwrap();
+            let mut response = response_to(&buf[..len]);
+            let owner = "1.2.0.192.in-addr.arpa";
+            assert!(response.ends_with(&[&encode_name(owner)[..], &[0, 12, 0, 1]].concat()));
+            for name in ["host.example.com", "spoofed.example.net"] {
+                add_answer(&mut response, owner, QueryType::PTR, &encode_name(name));
+            }
+            sock.send_to(&response, peer).unwrap();
+            // only the name under example.com resolves to 192.0.2.1
+            serve(sock, 2);
+        });
+        let ip = "192.0.2.1".parse().unwrap();
+        assert_eq!(
+            resolver.reverse_confirmed(1, ip).unwrap(),
+            ["host.example.com"]
+        );
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_order_srv() {
+        let srv = |priority, weight, target: &str| Srv {
+            priority,
+            weight,
+            port: 80,
+            target: target.to_string(),
+        };
+        let srvs = vec![
+            srv(20, 0, "backup.example.com"),
+            srv(10, 60, "a.example.com"),
+            srv(10, 0, "c.example.com"),
+            srv(10, 40, "b.example.com"),
+        ];
+        let targets = |numbers: [u32; 4]| {
+            let mut numbers = numbers.into_iter();
+            order_srv(srvs.clone(), || numbers.next().unwrap())
+                .into_iter()
+                .map(|srv| srv.target)
+                .collect::<Vec<_>>()
+        };
+        // running sums are c: 0, a: 60, b: 100
+        assert_eq!(
+            targets([0, 0, 0, 0]),
+            [
+                "c.example.com",
+                "a.example.com",
+                "b.example.com",
+                "backup.example.com"
+            ]
+        );
+        assert_eq!(
+            targets([61, 0, 0, 0]),
+            [
+                "b.example.com",
+                "c.example.com",
+                "a.example.com",
+                "backup.example.com"
+            ]
+        );
+        // the number is taken modulo the total weight + 1
+        assert_eq!(
+            targets([101 + 30, 1, 0, 0])[..2],
+            ["a.example.com", "b.example.com"]
+        );
+    }
+
+    #[test]
+    fn test_dnssec_rdata() {
+        let records = [
+            RData::DS {
+                key_tag: 34259,
+                algorithm: 15,
+                digest_type: 2,
+                digest: vec![0x78, 0x41, 0x62],
+            },
+            RData::RRSIG {
+                type_covered: QueryType::A,
+                algorithm: 15,
+                labels: 3,
+                original_ttl: 3600,
+                expiration: 2840140800,
+                inception: 1577836800,
+                key_tag: 34259,
+                signer_name: "example.com".to_string(),
+                signature: vec![0xfb, 0xff],
+            },
+            RData::NSEC {
+                next_name: "www.example.com".to_string(),
+                types: vec![QueryType::A, QueryType::RRSIG, QueryType::CAA],
+            },
+            RData::NSEC3 {
+                hash_algorithm: 1,
+                flags: 1,
+                iterations: 0,
+                salt: vec![],
+                next_hashed_owner: vec![0xff; 5],
+                types: vec![QueryType::NS],
+            },
+        ];
+        for rdata in &records {
+            let bytes = Vec::try_from(rdata).unwrap();
+            let rtype = match rdata {
+                RData::DS { .. } => QueryType::DS,
+                RData::RRSIG { .. } => QueryType::RRSIG,
+                RData::NSEC { .. } => QueryType::NSEC,
+                _ => QueryType::NSEC3,
+            };
+            assert_eq!(&read_rdata(&bytes, 0, bytes.len(), rtype).unwrap(), rdata);
+        }
+        // CAA (257) is in the second window of the type bitmap
+        assert_eq!(
+            Vec::try_from(&records[2]).unwrap()[17..],
+            [0, 6, 0x40, 0, 0, 0, 0, 0x02, 1, 1, 0x40]
+        );
+        assert_eq!(records[0].to_string(), "34259 15 2 784162");
+        assert_eq!(
+            records[1].to_string(),
+            "A 15 3 3600 2840140800 1577836800 34259 example.com. +/8="
+        );
+        assert_eq!(records[2].to_string(), "www.example.com. A RRSIG CAA");
+        assert_eq!(records[3].to_string(), "1 1 0 - vvvvvvvv NS");
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b"", true), "");
+        assert_eq!(base64(b"f", true), "Zg");
+        assert_eq!(base64(b"fo", true), "Zm8");
+        assert_eq!(base64(b"foo", true), "Zm9v");
+        assert_eq!(base64(&[0xfb, 0xff], true), "-_8");
+        assert_eq!(base64(b"f", false), "Zg==");
+        assert_eq!(base64(b"fo", false), "Zm8=");
+        assert_eq!(base64(&[0xfb, 0xff], false), "+/8=");
+        assert_eq!(base32hex(b"f"), "co");
+        assert_eq!(base32hex(b"foobar"), "cpnmuoj1e8");
+    }
 }
@@ -0,0 +1,176 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use super::{LookupIp, QueryType};
+
+/**
+ * Answers of earlier queries, kept until their time to live runs out
+ *
+ * Clones share the entries, so a cache can be inspected or flushed while a resolver uses it.
+ */
+#[derive(Debug, Clone)]
+pub struct Cache {
+    entries: Arc<Mutex<HashMap<(String, QueryType), Entry>>>,
+    min_ttl: Duration,
+    max_ttl: Duration,
+    negative_max_ttl: Duration,
+}
+
+#[derive(Debug)]
+struct Entry {
+    answer: CachedAnswer,
+    expires: Instant,
+}
+
+/**
+ * What a server answered for a name and record type
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CachedAnswer {
+    Found(LookupIp),
+    /// The name doesn't exist (NXDOMAIN)
+    NoSuchDomain,
+    /// The name exists but has no records of the type (NODATA)
+    NoRecords,
+}
+
+impl Default for Cache {
+    /// Keeps answers for at most a day, and negative answers for at most 3 hours (RFC 2308
+    /// section 5).
+    fn default() -> Self {
+        Self::new(Duration::ZERO, Duration::from_secs(24 * 60 * 60))
+            .negative_max_ttl(Duration::from_secs(3 * 60 * 60))
+    }
+}
+
+impl Cache {
+    /// Keeps answers for their TTL, but at least `min_ttl` and at most `max_ttl`.
+    pub fn new(min_ttl: Duration, max_ttl: Duration) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            min_ttl,
+            max_ttl,
+            negative_max_ttl: max_ttl,
+        }
+    }
+
+    /// Sets how long negative answers are kept at most.
+    pub fn negative_max_ttl(mut self, ttl: Duration) -> Self {
+        self.negative_max_ttl = ttl;
+        self
+    }
+
+    /// Returns the answer for `name` and `qtype` with the time it has left, unless it expired.
+    pub fn get(&self, name: &str, qtype: QueryType) -> Option<(CachedAnswer, Duration)> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let key = (normalize(name), qtype);
+        let remaining = entries
+            .get(&key)?
+            .expires
+            .checked_duration_since(Instant::now());
+        match remaining {
+            Some(ttl) if !ttl.is_zero() => Some((entries[&key].answer.clone(), ttl)),
+            _ => {
+                entries.remove(&key);
+                None
+            }
+        }
+    }
+
+    /// Keeps `answer` for exactly `ttl`, such as to seed the cache before any query.
+    pub fn insert(&self, name: &str, qtype: QueryType, answer: CachedAnswer, ttl: Duration) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        // drop what expired now and then, so that names queried once don't pile up
+        if entries.len().is_power_of_two() {
+            entries.retain(|_, entry| entry.expires > now);
+        }
+        let entry = Entry {
+            answer,
+            expires: now + ttl,
+        };
+        entries.insert((normalize(name), qtype), entry);
+    }
+
+    /// Keeps the answer of a server for `ttl` seconds, within the limits of the cache.
+    pub(crate) fn store(&self, name: &str, qtype: QueryType, answer: CachedAnswer, ttl: u32) {
+        let max_ttl = match answer {
+            CachedAnswer::Found(_) => self.max_ttl,
+            _ => self.negative_max_ttl,
+        };
+        let ttl = Duration::from_secs(ttl.into()).clamp(self.min_ttl, max_ttl.max(self.min_ttl));
+        if !ttl.is_zero() {
+            self.insert(name, qtype, answer, ttl);
+        }
+    }
+
+    /// Forgets the answers for `name` of any record type.
+    pub fn remove(&self, name: &str) {
+        let name = normalize(name);
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|(entry_name, _), _| *entry_name != name);
+    }
+
+    /// Forgets all answers.
+    pub fn clear(&self) {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+
+    /// Number of answers in the cache, including expired ones not removed yet.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Names are case-insensitive and may be written with a trailing dot.
+fn normalize(name: &str) -> String {
+    name.trim_end_matches('.').to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_clamps_ttls() {
+        let cache = Cache::new(Duration::from_secs(60), Duration::from_secs(3600))
+            .negative_max_ttl(Duration::from_secs(300));
+        let found = CachedAnswer::Found(LookupIp {
+            canonical_name: "example.com".to_string(),
+            addrs: vec!["192.0.2.1".parse().unwrap()],
+        });
+        cache.store("Example.com.", QueryType::A, found.clone(), 1);
+        cache.store(
+            "example.com",
+            QueryType::AAAA,
+            CachedAnswer::NoRecords,
+            86400,
+        );
+        cache.store("example.net", QueryType::A, found.clone(), 86400);
+
+        let (answer, ttl) = cache.get("example.com", QueryType::A).unwrap();
+        assert_eq!(answer, found);
+        assert!(ttl > Duration::from_secs(59) && ttl <= Duration::from_secs(60));
+        let (answer, ttl) = cache.get("example.com", QueryType::AAAA).unwrap();
+        assert_eq!(answer, CachedAnswer::NoRecords);
+        assert!(ttl <= Duration::from_secs(300));
+        assert!(cache.get("example.net", QueryType::A).unwrap().1 <= Duration::from_secs(3600));
+
+        cache.remove("EXAMPLE.com");
+        assert_eq!(cache.len(), 1);
+        cache.insert("gone.example", QueryType::A, found, Duration::ZERO);
+        assert!(cache.get("gone.example", QueryType::A).is_none());
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+}
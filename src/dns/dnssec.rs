@@ -0,0 +1,1542 @@
+use std::{
+    cmp::Ordering,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{base32hex, write_name, QueryType, RData, Resolver, ResourceRecord};
+use crate::{
+    crypto::{ec::Curve, ed25519, rsa::RsaPublicKey, sha1::Sha1, HashAlgorithm},
+    Error,
+};
+
+/// DNSKEY flag of keys that sign the records of the zone (RFC 4034 section 2.1.1)
+const ZONE_KEY: u16 = 0x0100;
+
+/// NSEC3 flag of spans that may hide unsigned delegations (RFC 5155 section 3.1.2.1)
+const OPT_OUT: u8 = 1;
+
+/// Type of records that alias the names below their owner (RFC 6672)
+const DNAME: QueryType = QueryType::Other(39);
+
+/// Above this, NSEC3 records are treated as insecure (RFC 9276 section 3.2)
+const MAX_NSEC3_ITERATIONS: u16 = 150;
+
+/// The result of validating records with DNSSEC (RFC 4035 section 4.3)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Dnssec {
+    /// Signed with a chain of trust from the trust anchor
+    Secure,
+    /// In a zone that is provably unsigned, or signed with algorithms that aren't supported
+    Insecure,
+    /// Signed badly or not at all where signatures were expected, with the reason
+    Bogus(String),
+}
+
+/**
+ * The keys that chains of trust start from: DS or DNSKEY records of a zone
+ *
+ * The default is the root zone's key signing keys published by IANA.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrustAnchor {
+    zone: String,
+    records: Vec<RData>,
+}
+
+impl TrustAnchor {
+    /// Trusts the keys of `zone`, such as "example.com", that are in `records` or whose digest is
+    /// in them. Records other than DS and DNSKEY are ignored.
+    pub fn new(zone: &str, records: Vec<RData>) -> Self {
+        Self {
+            zone: zone.trim_end_matches('.').to_ascii_lowercase(),
+            records: records
+                .into_iter()
+                .filter(|r| matches!(r, RData::DS { .. } | RData::DNSKEY { .. }))
+                .collect(),
+        }
+    }
+
+    /// The root KSK-2017 and KSK-2024 (https://data.iana.org/root-anchors/root-anchors.xml)
+    pub fn root() -> Self {
+        let ds = |key_tag, digest: &str| RData::DS {
+            key_tag,
+            algorithm: 8,
+            digest_type: 2,
+            digest: (0..digest.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&digest[i..i + 2], 16).unwrap())
+                .collect(),
+        };
+        Self::new(
+            "",
+            vec![
+                ds(
+                    20326,
+                    "E06D44B80B8F1D39A95C0B0D7C65D08458E880409BBC683457104237C7F8EC8D",
+                ),
+                ds(
+                    38696,
+                    "683D2D0ACB8C9B712A1948B27F741219298D0A450D612C483AF444A4C0FB2B16",
+                ),
+            ],
+        )
+    }
+}
+
+impl Default for TrustAnchor {
+    fn default() -> Self {
+        Self::root()
+    }
+}
+
+/// How far a chain of trust reaches down to a name
+enum Trust {
+    /// The deepest zone above the name that is signed, with its keys
+    Secure(String, Vec<RData>),
+    Insecure,
+    Bogus(String),
+}
+
+impl Resolver {
+    /// Validates the RRsets of `records`, the answers to a query. Each RRset must be signed by
+    /// its zone, unless a zone above it is provably unsigned, and `authorities` must prove that
+    /// the ones synthesized from wildcards had no closer match.
+    pub(super) fn validate(
+        &self,
+        id: u16,
+        anchor: &TrustAnchor,
+        records: &[ResourceRecord],
+        authorities: &[ResourceRecord],
+    ) -> Result<Dnssec, Error> {
+        let mut status = Dnssec::Secure;
+        for rrset in rrsets(records) {
+            // a signer that isn't above the records fails verification
+            let signer = match rrset.signatures.first() {
+                Some(RData::RRSIG { signer_name, .. })
+                    if subdomain_labels(&rrset.name, signer_name).is_some() =>
+                {
+                    signer_name.as_str()
+                }
+                _ => rrset.name.as_str(),
+            };
+            let result = match self.trust(id, anchor, signer)? {
+                Trust::Secure(_, _) if rrset.signatures.is_empty() => Dnssec::Bogus(format!(
+                    "the {} records of {} aren't signed",
+                    rrset.rtype, rrset.name
+                )),
+                Trust::Secure(zone, _) if zone != signer => {
+                    Dnssec::Bogus(format!("{signer} isn't a signed zone"))
+                }
+                Trust::Secure(zone, keys) => verify_answer(&rrset, &zone, &keys, authorities),
+                Trust::Insecure => Dnssec::Insecure,
+                Trust::Bogus(reason) => Dnssec::Bogus(reason),
+            };
+            match result {
+                Dnssec::Bogus(_) => return Ok(result),
+                Dnssec::Insecure => status = Dnssec::Insecure,
+                Dnssec::Secure => {}
+            }
+        }
+        Ok(status)
+    }
+
+    /// Validates a negative answer, whose `authorities` must prove that `name` doesn't exist, or
+    /// if `qtype` is set, that it has no such records. `answers` are the CNAME records that led to
+    /// `name`, which are validated like positive answers.
+    pub(super) fn validate_denial(
+        &self,
+        id: u16,
+        anchor: &TrustAnchor,
+        name: &str,
+        qtype: Option<QueryType>,
+        answers: &[ResourceRecord],
+        authorities: &[ResourceRecord],
+    ) -> Result<Dnssec, Error> {
+        let status = self.validate(id, anchor, answers, authorities)?;
+        if let Dnssec::Bogus(_) = status {
+            return Ok(status);
+        }
+        // the zone that signed the proof, or else the one with the SOA record
+        let signer = authorities
+            .iter()
+            .find_map(|r| match &r.rdata {
+                RData::RRSIG { signer_name, .. }
+                    if subdomain_labels(name, signer_name).is_some() =>
+                {
+                    Some(signer_name.as_str())
+                }
+                _ => None,
+            })
+            .or_else(|| {
+                authorities
+                    .iter()
+                    .find(|r| r.query_type == QueryType::SOA)
+                    .filter(|r| subdomain_labels(name, &r.name).is_some())
+                    .map(|r| r.name.as_str())
+            })
+            .unwrap_or(name);
+        let denial = match self.trust(id, anchor, signer)? {
+            Trust::Secure(zone, _) if zone != signer => {
+                Dnssec::Bogus(format!("{signer} isn't a signed zone"))
+            }
+            Trust::Secure(zone, keys) => {
+                let denials = Denials::new(&zone, &keys, authorities);
+                match qtype {
+                    None => denials.no_name(name),
+                    Some(qtype) => denials.no_type(name, qtype),
+                }
+            }
+            Trust::Insecure => Dnssec::Insecure,
+            Trust::Bogus(reason) => Dnssec::Bogus(reason),
+        };
+        Ok(match (status, denial) {
+            (_, Dnssec::Bogus(reason)) => Dnssec::Bogus(reason),
+            (Dnssec::Insecure, _) | (_, Dnssec::Insecure) => Dnssec::Insecure,
+            _ => Dnssec::Secure,
+        })
+    }
+
+    /// Follows the delegations from the anchor down to `name` with DS queries (RFC 4035 section
+    /// 5.1).
+    fn trust(&self, id: u16, anchor: &TrustAnchor, name: &str) -> Result<Trust, Error> {
+        let Some(below) = subdomain_labels(name, &anchor.zone) else {
+            return Ok(Trust::Insecure);
+        };
+        let (mut zone, mut keys) = match self.keys(id, &anchor.zone, &anchor.records)? {
+            Trust::Secure(zone, keys) => (zone, keys),
+            other => return Ok(other),
+        };
+        let labels: Vec<&str> = name.split('.').collect();
+        for i in (0..below).rev() {
+            let child = labels[i..].join(".");
+            let response = self.send(id, &child, QueryType::DS)?;
+            let ds = rrsets(&response.answers)
+                .into_iter()
+                .find(|s| s.rtype == QueryType::DS && s.name == child);
+            if let Some(rrset) = ds {
+                if let Err(reason) = verify_rrset(&rrset, &zone, &keys) {
+                    return Ok(Trust::Bogus(reason));
+                }
+                let ds: Vec<RData> = rrset.records.into_iter().cloned().collect();
+                match self.keys(id, &child, &ds)? {
+                    Trust::Secure(child, child_keys) => (zone, keys) = (child, child_keys),
+                    other => return Ok(other),
+                }
+                continue;
+            }
+            // an alias can't have a zone below it
+            let cname = rrsets(&response.answers)
+                .into_iter()
+                .find(|s| s.rtype == QueryType::CNAME && s.name == child);
+            if let Some(rrset) = cname {
+                match verify_answer(&rrset, &zone, &keys, &response.authorities) {
+                    Dnssec::Secure => continue,
+                    Dnssec::Insecure => return Ok(Trust::Insecure),
+                    Dnssec::Bogus(reason) => return Ok(Trust::Bogus(reason)),
+                }
+            }
+            match no_ds(&child, &zone, &keys, &response.authorities) {
+                Ok(true) => continue,
+                Ok(false) => return Ok(Trust::Insecure),
+                Err(reason) => return Ok(Trust::Bogus(reason)),
+            }
+        }
+        Ok(Trust::Secure(zone, keys))
+    }
+
+    /// Queries the keys of `zone` and checks that they are signed by one of `trusted`, DNSKEY
+    /// records or DS records with their digests.
+    fn keys(&self, id: u16, zone: &str, trusted: &[RData]) -> Result<Trust, Error> {
+        let supported = trusted.iter().any(|r| match r {
+            RData::DS {
+                algorithm,
+                digest_type,
+                ..
+            } => supports_algorithm(*algorithm) && supports_digest(*digest_type),
+            RData::DNSKEY { algorithm, .. } => supports_algorithm(*algorithm),
+            _ => false,
+        });
+        // the zone is treated as unsigned (RFC 4035 section 5.2)
+        if !supported {
+            return Ok(Trust::Insecure);
+        }
+        let response = self.send(id, zone, QueryType::DNSKEY)?;
+        let Some(rrset) = rrsets(&response.answers)
+            .into_iter()
+            .find(|s| s.rtype == QueryType::DNSKEY && s.name == zone)
+        else {
+            return Ok(Trust::Bogus(format!("{zone} has no DNSKEY records")));
+        };
+        let is_trusted = |key: &RData| {
+            trusted.iter().any(|t| match t {
+                RData::DS { .. } => ds_matches(zone, key, t),
+                _ => t == key,
+            })
+        };
+        let entry_keys: Vec<RData> = rrset
+            .records
+            .iter()
+            .copied()
+            .filter(|key| is_trusted(key))
+            .cloned()
+            .collect();
+        if entry_keys.is_empty() {
+            return Ok(Trust::Bogus(format!("no trusted DNSKEY for {zone}")));
+        }
+        Ok(match verify_rrset(&rrset, zone, &entry_keys) {
+            Ok(_) => Trust::Secure(
+                zone.to_string(),
+                rrset.records.into_iter().cloned().collect(),
+            ),
+            Err(reason) => Trust::Bogus(reason),
+        })
+    }
+}
+
+/// The records of a name and type, with their signatures
+struct RRset<'a> {
+    name: String,
+    rtype: QueryType,
+    records: Vec<&'a RData>,
+    signatures: Vec<&'a RData>,
+}
+
+/// Groups `records` into RRsets by owner and type, in the order they first appear.
+fn rrsets(records: &[ResourceRecord]) -> Vec<RRset<'_>> {
+    let mut rrsets: Vec<RRset> = vec![];
+    for record in records {
+        let rtype = match &record.rdata {
+            RData::RRSIG { type_covered, .. } => *type_covered,
+            _ => record.query_type,
+        };
+        let index = match rrsets
+            .iter()
+            .position(|s| s.name == record.name && s.rtype == rtype)
+        {
+            Some(index) => index,
+            None => {
+                rrsets.push(RRset {
+                    name: record.name.clone(),
+                    rtype,
+                    records: vec![],
+                    signatures: vec![],
+                });
+                rrsets.len() - 1
+            }
+        };
+        let rrset = &mut rrsets[index];
+        match record.query_type {
+            QueryType::RRSIG => rrset.signatures.push(&record.rdata),
+            _ => rrset.records.push(&record.rdata),
+        }
+    }
+    // signatures without records prove nothing
+    rrsets.retain(|s| !s.records.is_empty());
+    rrsets
+}
+
+/// Verifies `rrset` like `verify_rrset`, and if its records were synthesized from a wildcard,
+/// checks the proof in `authorities` that no closer name exists (RFC 4035 section 5.3.4).
+fn verify_answer(
+    rrset: &RRset,
+    zone: &str,
+    keys: &[RData],
+    authorities: &[ResourceRecord],
+) -> Dnssec {
+    match verify_rrset(rrset, zone, keys) {
+        Ok(labels) if labels < label_count(&rrset.name) => {
+            Denials::new(zone, keys, authorities).no_closer_name(&rrset.name, labels)
+        }
+        Ok(_) => Dnssec::Secure,
+        Err(reason) => Dnssec::Bogus(reason),
+    }
+}
+
+/// Checks that one of the signatures of `rrset` was made by one of the `keys` of `zone` and is
+/// currently valid (RFC 4035 section 5.3). Returns the labels field of that signature, which is
+/// below the number of labels of the owner if the records were synthesized from a wildcard.
+fn verify_rrset(rrset: &RRset, zone: &str, keys: &[RData]) -> Result<usize, String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as u32);
+    let owner_labels = label_count(&rrset.name);
+    let mut reason = format!(
+        "the {} records of {} aren't signed",
+        rrset.rtype, rrset.name
+    );
+    for signature in &rrset.signatures {
+        let RData::RRSIG {
+            type_covered,
+            algorithm,
+            labels,
+            expiration,
+            inception,
+            key_tag,
+            signer_name,
+            signature: signature_bytes,
+            ..
+        } = signature
+        else {
+            continue;
+        };
+        if signer_name != zone
+            || *type_covered != rrset.rtype
+            || subdomain_labels(&rrset.name, zone).is_none()
+            || *labels as usize > owner_labels
+        {
+            continue;
+        }
+        // serial number arithmetic (RFC 1982), so that the times wrap around in 2106
+        if (now.wrapping_sub(*inception) as i32) < 0 || (expiration.wrapping_sub(now) as i32) < 0 {
+            reason = format!(
+                "the signature of the {} records of {} isn't valid now",
+                rrset.rtype, rrset.name
+            );
+            continue;
+        }
+        let Ok(data) = signed_data(rrset, signature, *labels as usize) else {
+            continue;
+        };
+        for key in keys {
+            let RData::DNSKEY {
+                flags,
+                protocol: 3,
+                algorithm: key_algorithm,
+                public_key,
+            } = key
+            else {
+                continue;
+            };
+            if flags & ZONE_KEY == 0 || key_algorithm != algorithm || key_tag_of(key) != *key_tag {
+                continue;
+            }
+            match verify_signature(*algorithm, public_key, &data, signature_bytes) {
+                Some(true) => return Ok(*labels as usize),
+                Some(false) => {
+                    reason = format!(
+                        "bad signature over the {} records of {}",
+                        rrset.rtype, rrset.name
+                    );
+                }
+                None => {}
+            }
+        }
+    }
+    Err(reason)
+}
+
+/// The data that `signature` signs: its RDATA without the signature, and the records in canonical
+/// form and order (RFC 4034 section 3.1.8.1).
+fn signed_data(rrset: &RRset, signature: &RData, labels: usize) -> Result<Vec<u8>, Error> {
+    let RData::RRSIG { original_ttl, .. } = signature else {
+        unreachable!("signatures are RRSIG records");
+    };
+    let mut unsigned = signature.clone();
+    if let RData::RRSIG { signature, .. } = &mut unsigned {
+        signature.clear();
+    }
+    let mut data = Vec::try_from(&unsigned)?;
+    // records matched by a wildcard are signed with the wildcard as their owner
+    let owner = match label_count(&rrset.name) > labels {
+        true => wildcard(&rrset.name, labels),
+        false => rrset.name.clone(),
+    };
+    let mut owner_wire = vec![];
+    write_name(&mut owner_wire, &owner)?;
+    let mut records = rrset
+        .records
+        .iter()
+        .map(|r| Vec::try_from(*r))
+        .collect::<Result<Vec<_>, _>>()?;
+    records.sort();
+    records.dedup();
+    for rdata in records {
+        data.extend_from_slice(&owner_wire);
+        data.extend_from_slice(&u16::from(rrset.rtype).to_be_bytes());
+        data.extend_from_slice(&1u16.to_be_bytes()); // IN
+        data.extend_from_slice(&original_ttl.to_be_bytes());
+        data.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        data.extend(rdata);
+    }
+    Ok(data)
+}
+
+/// Checks the proof in `authorities`, signed by `zone`, that `child` has no DS records (RFC 4035
+/// section 5.2, RFC 5155 section 8.6). Returns whether `child` is still in `zone`, rather than
+/// delegated to an unsigned zone.
+fn no_ds(
+    child: &str,
+    zone: &str,
+    keys: &[RData],
+    authorities: &[ResourceRecord],
+) -> Result<bool, String> {
+    for rrset in rrsets(authorities) {
+        // proofs aren't synthesized from wildcards
+        if !matches!(rrset.rtype, QueryType::NSEC | QueryType::NSEC3)
+            || verify_rrset(&rrset, zone, keys) != Ok(label_count(&rrset.name))
+        {
+            continue;
+        }
+        for rdata in &rrset.records {
+            match rdata {
+                RData::NSEC { next_name, types } => {
+                    if rrset.name == child {
+                        return in_zone(child, types);
+                    }
+                    // an empty non-terminal
+                    let key = canonical_key;
+                    if covers(key(&rrset.name), key(next_name), key(child)) {
+                        return Ok(true);
+                    }
+                }
+                RData::NSEC3 {
+                    hash_algorithm: 1,
+                    flags,
+                    iterations,
+                    salt,
+                    next_hashed_owner,
+                    types,
+                } => {
+                    let (owner_hash, nsec3_zone) =
+                        rrset.name.split_once('.').unwrap_or((&rrset.name, ""));
+                    if nsec3_zone != zone {
+                        continue;
+                    }
+                    if *iterations > MAX_NSEC3_ITERATIONS {
+                        return Ok(false);
+                    }
+                    let Ok(hash) = nsec3_hash(child, salt, *iterations) else {
+                        continue;
+                    };
+                    let hash = base32hex(&hash);
+                    if hash == owner_hash {
+                        return in_zone(child, types);
+                    }
+                    let next = base32hex(next_hashed_owner);
+                    if covers(owner_hash, &next, &hash) {
+                        return Ok(flags & OPT_OUT == 0);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    Err(format!("no proof that {child} has no DS records"))
+}
+
+/// Whether the owner of NSEC or NSEC3 records with `types` is in the zone that signed them, or is
+/// delegated to an unsigned zone.
+fn in_zone(child: &str, types: &[QueryType]) -> Result<bool, String> {
+    if types.contains(&QueryType::DS) {
+        return Err(format!("the DS records of {child} are missing"));
+    }
+    Ok(!types.contains(&QueryType::NS) || types.contains(&QueryType::SOA))
+}
+
+/// What NSEC or NSEC3 records prove about a name
+enum Proof {
+    Proven,
+    /// Not proven, but an opt-out span may hide an unsigned delegation there
+    OptOut,
+    Unproven,
+}
+
+/// An NSEC record, which lists the types of its owner, and that no names sort between it and
+/// `next`
+struct Nsec<'a> {
+    owner: String,
+    next: &'a str,
+    types: &'a [QueryType],
+}
+
+/// An NSEC3 record, which does the same for hashed names
+struct Nsec3<'a> {
+    hash: String,
+    next: String,
+    flags: u8,
+    iterations: u16,
+    salt: &'a [u8],
+    types: &'a [QueryType],
+}
+
+/**
+ * The NSEC or NSEC3 records of a zone, which prove that names or records don't exist (RFC 4035
+ * section 5.4, RFC 5155 section 8)
+ */
+#[derive(Default)]
+struct Denials<'a> {
+    zone: String,
+    nsec: Vec<Nsec<'a>>,
+    nsec3: Vec<Nsec3<'a>>,
+    /// Whether NSEC3 records were ignored for too many iterations
+    costly: bool,
+}
+
+impl<'a> Denials<'a> {
+    /// The records of `authorities` that `zone` signed with one of `keys`
+    fn new(zone: &str, keys: &[RData], authorities: &'a [ResourceRecord]) -> Self {
+        let mut denials = Denials {
+            zone: zone.to_string(),
+            ..Default::default()
+        };
+        for rrset in rrsets(authorities) {
+            // proofs aren't synthesized from wildcards
+            if matches!(rrset.rtype, QueryType::NSEC | QueryType::NSEC3)
+                && verify_rrset(&rrset, zone, keys) == Ok(label_count(&rrset.name))
+            {
+                for rdata in rrset.records {
+                    denials.add(&rrset.name, rdata);
+                }
+            }
+        }
+        denials
+    }
+
+    fn add(&mut self, owner: &str, rdata: &'a RData) {
+        match rdata {
+            RData::NSEC { next_name, types } => self.nsec.push(Nsec {
+                owner: owner.to_string(),
+                next: next_name,
+                types,
+            }),
+            RData::NSEC3 {
+                hash_algorithm: 1,
+                flags,
+                iterations,
+                salt,
+                next_hashed_owner,
+                types,
+            } => {
+                let (hash, zone) = owner.split_once('.').unwrap_or((owner, ""));
+                if zone != self.zone {
+                    return;
+                }
+                if *iterations > MAX_NSEC3_ITERATIONS {
+                    self.costly = true;
+                    return;
+                }
+                self.nsec3.push(Nsec3 {
+                    hash: hash.to_string(),
+                    next: base32hex(next_hashed_owner),
+                    flags: *flags,
+                    iterations: *iterations,
+                    salt,
+                    types,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// Checks the proof that `name` doesn't exist: no name matches it, and no wildcard at its
+    /// closest encloser does either (RFC 4035 section 3.1.3.2, RFC 5155 section 8.4).
+    fn no_name(&self, name: &str) -> Dnssec {
+        let proof = match self.uses_nsec3() {
+            true => match self.nsec3_encloser(name) {
+                Some((encloser, next_closer)) if self.nsec3_cover(&source(&encloser)) => {
+                    opt_out(next_closer)
+                }
+                _ => Proof::Unproven,
+            },
+            false => match self.nsec_encloser(name) {
+                Some(encloser) if self.nsec_cover(&source(&encloser)).is_some() => Proof::Proven,
+                _ => Proof::Unproven,
+            },
+        };
+        self.status(proof, || format!("no proof that {name} doesn't exist"))
+    }
+
+    /// Checks the proof that `name` has no `qtype` records: it exists without them, or is an empty
+    /// non-terminal, or the wildcard matching it has none (RFC 4035 section 3.1.3.1, RFC 5155
+    /// sections 8.5 to 8.7).
+    fn no_type(&self, name: &str, qtype: QueryType) -> Dnssec {
+        let lacks =
+            |types: &[QueryType]| !types.contains(&qtype) && !types.contains(&QueryType::CNAME);
+        // the records of a name are in the zone below a delegation, except DS records, which are
+        // in the zone above an apex (RFC 6840 section 4.4)
+        let lacks_here = |types: &[QueryType]| {
+            lacks(types)
+                && match qtype {
+                    QueryType::DS => !types.contains(&QueryType::SOA),
+                    _ => !redirects(types),
+                }
+        };
+        let proof = match self.uses_nsec3() {
+            true => match self.nsec3_match(name) {
+                Some(nsec3) => proven(lacks_here(nsec3.types)),
+                None => match self.nsec3_encloser(name) {
+                    // the DS records of an unsigned delegation in an opt-out span
+                    Some((_, next_closer)) if qtype == QueryType::DS => opt_out(next_closer),
+                    Some((encloser, _)) => proven(
+                        self.nsec3_match(&source(&encloser))
+                            .is_some_and(|n| lacks(n.types)),
+                    ),
+                    None => Proof::Unproven,
+                },
+            },
+            false => match self.nsec_match(name) {
+                Some(nsec) => proven(lacks_here(nsec.types)),
+                None => {
+                    let empty_non_terminal = self
+                        .nsec_cover(name)
+                        .is_some_and(|nsec| subdomain_labels(nsec.next, name).is_some());
+                    let wildcard = self
+                        .nsec_encloser(name)
+                        .and_then(|encloser| self.nsec_match(&source(&encloser)))
+                        .is_some_and(|nsec| lacks(nsec.types));
+                    proven(empty_non_terminal || wildcard)
+                }
+            },
+        };
+        self.status(proof, || {
+            format!("no proof that {name} has no {qtype} records")
+        })
+    }
+
+    /// Checks the proof that no name closer to `name` than the wildcard it matched exists, the
+    /// wildcard below its last `labels` labels (RFC 4035 section 5.3.4, RFC 5155 section 8.8).
+    fn no_closer_name(&self, name: &str, labels: usize) -> Dnssec {
+        let proof = match self.uses_nsec3() {
+            true => {
+                let names: Vec<&str> = name.split('.').collect();
+                let next_closer = names[names.len() - labels - 1..].join(".");
+                match self.nsec3_covering(&next_closer) {
+                    Some(nsec3) => opt_out(nsec3),
+                    None => Proof::Unproven,
+                }
+            }
+            false => proven(self.nsec_cover(name).is_some()),
+        };
+        self.status(proof, || {
+            let wildcard = wildcard(name, labels);
+            format!("no proof that there is no closer match for {name} than {wildcard}")
+        })
+    }
+
+    fn uses_nsec3(&self) -> bool {
+        !self.nsec3.is_empty() || self.costly
+    }
+
+    fn status(&self, proof: Proof, reason: impl FnOnce() -> String) -> Dnssec {
+        match proof {
+            Proof::Proven => Dnssec::Secure,
+            Proof::OptOut => Dnssec::Insecure,
+            Proof::Unproven if self.costly => Dnssec::Insecure,
+            Proof::Unproven => Dnssec::Bogus(reason()),
+        }
+    }
+
+    fn nsec_match(&self, name: &str) -> Option<&Nsec<'a>> {
+        self.nsec.iter().find(|nsec| nsec.owner == name)
+    }
+
+    /// The NSEC record proving that `name` doesn't exist, unless it's in a zone delegated from
+    /// its owner (RFC 6840 section 4.1)
+    fn nsec_cover(&self, name: &str) -> Option<&Nsec<'a>> {
+        let key = canonical_key;
+        self.nsec.iter().find(|nsec| {
+            covers(key(&nsec.owner), key(nsec.next), key(name))
+                && !(redirects(nsec.types) && subdomain_labels(name, &nsec.owner).is_some())
+        })
+    }
+
+    /// The closest encloser of `name`, which doesn't exist: the deepest of its ancestors that the
+    /// names of the NSEC record covering it are in
+    fn nsec_encloser(&self, name: &str) -> Option<String> {
+        let nsec = self.nsec_cover(name)?;
+        let (a, b) = (
+            common_ancestor(name, &nsec.owner),
+            common_ancestor(name, nsec.next),
+        );
+        let encloser = match label_count(&a) >= label_count(&b) {
+            true => a,
+            false => b,
+        };
+        subdomain_labels(&encloser, &self.zone).map(|_| encloser)
+    }
+
+    /// The NSEC3 record with the hash of `name`
+    fn nsec3_match(&self, name: &str) -> Option<&Nsec3<'a>> {
+        self.nsec3
+            .iter()
+            .find(|nsec3| hash(nsec3, name).is_some_and(|hash| hash == nsec3.hash))
+    }
+
+    /// Whether an NSEC3 record proves that `name` doesn't exist
+    fn nsec3_cover(&self, name: &str) -> bool {
+        self.nsec3_covering(name).is_some()
+    }
+
+    fn nsec3_covering(&self, name: &str) -> Option<&Nsec3<'a>> {
+        self.nsec3.iter().find(|nsec3| {
+            hash(nsec3, name).is_some_and(|hash| covers(&*nsec3.hash, &*nsec3.next, &*hash))
+        })
+    }
+
+    /// The closest encloser proof of `name`, which doesn't exist (RFC 5155 section 8.3): the
+    /// deepest ancestor with an NSEC3 record, and the NSEC3 record covering the next closer name,
+    /// the one below it
+    fn nsec3_encloser(&self, name: &str) -> Option<(String, &Nsec3<'a>)> {
+        let ancestors = ancestors(name);
+        let i = ancestors
+            .iter()
+            .position(|ancestor| self.nsec3_match(ancestor).is_some())?;
+        let encloser = self.nsec3_match(ancestors[i])?;
+        if i == 0
+            || redirects(encloser.types)
+            || subdomain_labels(ancestors[i], &self.zone).is_none()
+        {
+            return None;
+        }
+        let next_closer = self.nsec3_covering(ancestors[i - 1])?;
+        Some((ancestors[i].to_string(), next_closer))
+    }
+}
+
+/// Whether names below the owner of `types` are elsewhere, delegated or aliased with DNAME
+fn redirects(types: &[QueryType]) -> bool {
+    (types.contains(&QueryType::NS) && !types.contains(&QueryType::SOA)) || types.contains(&DNAME)
+}
+
+fn proven(proven: bool) -> Proof {
+    match proven {
+        true => Proof::Proven,
+        false => Proof::Unproven,
+    }
+}
+
+/// The proof of an NSEC3 record covering a name, which may be an unsigned delegation if it opts
+/// out
+fn opt_out(covering: &Nsec3) -> Proof {
+    match covering.flags & OPT_OUT {
+        0 => Proof::Proven,
+        _ => Proof::OptOut,
+    }
+}
+
+/// The hash of `name` with the parameters of `nsec3`
+fn hash(nsec3: &Nsec3, name: &str) -> Option<String> {
+    let hash = nsec3_hash(name, nsec3.salt, nsec3.iterations).ok()?;
+    Some(base32hex(&hash))
+}
+
+/// `name` and the names above it, up to the root
+fn ancestors(name: &str) -> Vec<&str> {
+    let mut ancestors = vec![name];
+    let mut rest = name;
+    while !rest.is_empty() {
+        rest = rest.split_once('.').map_or("", |(_, parent)| parent);
+        ancestors.push(rest);
+    }
+    ancestors
+}
+
+/// The deepest name that both `a` and `b` are in
+fn common_ancestor(a: &str, b: &str) -> String {
+    let common: Vec<&str> = a
+        .rsplit('.')
+        .zip(b.rsplit('.'))
+        .take_while(|(a, b)| a == b && !a.is_empty())
+        .map(|(a, _)| a)
+        .collect();
+    common.into_iter().rev().collect::<Vec<_>>().join(".")
+}
+
+/// The wildcard that names below `encloser` would be synthesized from
+fn source(encloser: &str) -> String {
+    match encloser {
+        "" => "*".to_string(),
+        _ => format!("*.{encloser}"),
+    }
+}
+
+/// Whether `name` sorts between `owner` and `next`. The last span of a zone wraps around.
+fn covers<K: Ord>(owner: K, next: K, name: K) -> bool {
+    match owner.cmp(&next) {
+        Ordering::Less => owner < name && name < next,
+        _ => owner < name || name < next,
+    }
+}
+
+/// The labels of `name` from the root down, which sort names in the canonical order (RFC 4034
+/// section 6.1).
+fn canonical_key(name: &str) -> Vec<&[u8]> {
+    name.split('.')
+        .rev()
+        .filter(|label| !label.is_empty())
+        .map(str::as_bytes)
+        .collect()
+}
+
+/// The hash of an owner name in NSEC3 records (RFC 5155 section 5)
+fn nsec3_hash(name: &str, salt: &[u8], iterations: u16) -> Result<Vec<u8>, Error> {
+    let mut hash = vec![];
+    write_name(&mut hash, name)?;
+    for _ in 0..=iterations {
+        let mut hasher = Sha1::new();
+        hasher.update(&hash);
+        hasher.update(salt);
+        hash = hasher.finish();
+    }
+    Ok(hash)
+}
+
+/// Whether `key` is the DNSKEY record of `owner` that `ds` has the digest of (RFC 4034 section
+/// 5.1.4).
+fn ds_matches(owner: &str, key: &RData, ds: &RData) -> bool {
+    let (
+        RData::DNSKEY { algorithm, .. },
+        RData::DS {
+            key_tag,
+            algorithm: ds_algorithm,
+            digest_type,
+            digest,
+        },
+    ) = (key, ds)
+    else {
+        return false;
+    };
+    if algorithm != ds_algorithm || key_tag_of(key) != *key_tag {
+        return false;
+    }
+    let mut data = vec![];
+    let (Ok(()), Ok(key)) = (write_name(&mut data, owner), Vec::try_from(key)) else {
+        return false;
+    };
+    data.extend(key);
+    let computed = match digest_type {
+        1 => Sha1::digest(&data),
+        2 => HashAlgorithm::Sha256.digest(&[&data]),
+        4 => HashAlgorithm::Sha384.digest(&[&data]),
+        _ => return false,
+    };
+    computed == *digest
+}
+
+/// The key tag of a DNSKEY record (RFC 4034 appendix B)
+fn key_tag_of(key: &RData) -> u16 {
+    // DNSKEY records have no names, which are all that can fail to encode
+    let sum = Vec::try_from(key)
+        .unwrap_or_default()
+        .iter()
+        .enumerate()
+        .fold(0u32, |sum, (i, &b)| match i % 2 {
+            0 => sum + (u32::from(b) << 8),
+            _ => sum + u32::from(b),
+        });
+    (sum + (sum >> 16)) as u16
+}
+
+fn supports_algorithm(algorithm: u8) -> bool {
+    matches!(algorithm, 8 | 10 | 13 | 14 | 15)
+}
+
+fn supports_digest(digest_type: u8) -> bool {
+    matches!(digest_type, 1 | 2 | 4)
+}
+
+/// Verifies `signature` of `data` with a DNSKEY public key, or returns None if the algorithm
+/// isn't supported (RFC 8624 section 3.1).
+fn verify_signature(
+    algorithm: u8,
+    public_key: &[u8],
+    data: &[u8],
+    signature: &[u8],
+) -> Option<bool> {
+    match algorithm {
+        // RSA/SHA-256 and RSA/SHA-512, with keys in the format of RFC 3110 section 2
+        8 | 10 => {
+            let hash = match algorithm {
+                8 => HashAlgorithm::Sha256,
+                _ => HashAlgorithm::Sha512,
+            };
+            let (exponent_len, rest) = match public_key {
+                [0, high, low, rest @ ..] => (u16::from_be_bytes([*high, *low]).into(), rest),
+                [len, rest @ ..] => (usize::from(*len), rest),
+                [] => return Some(false),
+            };
+            if exponent_len > rest.len() {
+                return Some(false);
+            }
+            let (e, n) = rest.split_at(exponent_len);
+            let key = RsaPublicKey::new(n, e)?;
+            Some(key.verify_pkcs1v15(hash, data, signature))
+        }
+        // ECDSA P-256 with SHA-256 and P-384 with SHA-384 (RFC 6605)
+        13 | 14 => {
+            let (curve, hash) = match algorithm {
+                13 => (Curve::p256(), HashAlgorithm::Sha256),
+                _ => (Curve::p384(), HashAlgorithm::Sha384),
+            };
+            let len = curve.byte_len();
+            if public_key.len() != 2 * len || signature.len() != 2 * len {
+                return Some(false);
+            }
+            let (r, s) = signature.split_at(len);
+            let point = [&[4], public_key].concat();
+            Some(curve.verify(&point, &hash.digest(&[data]), r, s))
+        }
+        // Ed25519 (RFC 8080)
+        15 => Some(ed25519::verify(public_key, data, signature)),
+        _ => None,
+    }
+}
+
+/// How many labels `name` has below `zone`, or None if it isn't in it.
+fn subdomain_labels(name: &str, zone: &str) -> Option<usize> {
+    let count = |n: &str| n.split('.').filter(|label| !label.is_empty()).count();
+    let inside = zone.is_empty() || name == zone || name.ends_with(&format!(".{zone}"));
+    inside.then(|| count(name) - count(zone))
+}
+
+/// The number of labels of `name` in the labels field of RRSIG records, which doesn't count the
+/// root and a wildcard (RFC 4034 section 3.1.3).
+fn label_count(name: &str) -> usize {
+    name.split('.').filter(|label| !label.is_empty()).count() - usize::from(name.starts_with("*"))
+}
+
+/// The wildcard owner of records that were synthesized for `name` from it
+fn wildcard(name: &str, labels: usize) -> String {
+    let names: Vec<&str> = name.split('.').collect();
+    match labels {
+        0 => "*".to_string(),
+        _ => format!("*.{}", names[names.len() - labels..].join(".")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        crypto::tests::hex,
+        dns::{tests::response_to, ResolvConf, Response},
+    };
+    use std::{net::UdpSocket, thread};
+
+    /// RRSIG records of example.com, signed with the Ed25519 key whose private key is 0..=31
+    fn rrsig(type_covered: QueryType, labels: u8, signature: &str) -> RData {
+        RData::RRSIG {
+            type_covered,
+            algorithm: 15,
+            labels,
+            original_ttl: 3600,
+            expiration: 2840140800,
+            inception: 1577836800,
+            key_tag: 34259,
+            signer_name: "example.com".to_string(),
+            signature: hex(signature),
+        }
+    }
+
+    /// A record with its owner
+    type Record<'a> = (&'a str, RData);
+
+    /// The answer and authority sections of the signed zone example.com, with the unsigned zone
+    /// unsigned.example.com delegated from it
+    fn zone(name: &str, qtype: QueryType) -> (Vec<Record<'_>>, Vec<Record<'_>>) {
+        let signed_a = |owner| {
+            vec![
+                (owner, RData::A([192, 0, 2, 1])),
+                (owner, rrsig(QueryType::A, 3, "0ebe6b2324772f2a504bc5293a63924d5cfea504adea20767be80c4e0030c9c9615958c9338becb9b734e8d8833c527ab69a3eb9c59a8ceda3fcea0f47161f07")),
+            ]
+        };
+        let nsec = |owner, next: &str, types: Vec<QueryType>, signature| {
+            let next_name = next.to_string();
+            let rdata = RData::NSEC { next_name, types };
+            vec![
+                (owner, rdata),
+                (owner, rrsig(QueryType::NSEC, 3, signature)),
+            ]
+        };
+        let dnskey = RData::DNSKEY {
+            flags: 257,
+            protocol: 3,
+            algorithm: 15,
+            public_key: hex("03a107bff3ce10be1d70dd18e74bc09967e4d6309ba50d5f1ddc8664125531b8"),
+        };
+        let (answers, authorities) = match (name, qtype) {
+            ("www.example.com", QueryType::A) => (signed_a("www.example.com"), vec![]),
+            // the signature is over the records of www.example.com
+            ("forged.example.com", QueryType::A) => (signed_a("forged.example.com"), vec![]),
+            ("www.example.com", QueryType::TXT) => {
+                let txt = RData::TXT(vec![b"unsigned".to_vec()]);
+                (vec![("www.example.com", txt)], vec![])
+            }
+            // synthesized from *.example.com, without the proof that nowhere.example.com doesn't
+            // exist
+            ("nowhere.example.com", QueryType::A) => (
+                vec![
+                    ("nowhere.example.com", RData::A([192, 0, 2, 1])),
+                    ("nowhere.example.com", rrsig(QueryType::A, 2, "c8bac9771ada70beb4da5ae55212e02821da987890e3960a22b36c53d96486f68d55a724e9f443bff46d475ad80152d54ad565bcaaa305ba0db5bd1113164f06")),
+                ],
+                vec![],
+            ),
+            // an unsigned alias in the signed zone
+            ("www.alias.example.com", QueryType::A) => {
+                let a = RData::A([198, 51, 100, 1]);
+                (vec![("www.alias.example.com", a)], vec![])
+            }
+            ("alias.example.com", QueryType::DS) => {
+                let cname = RData::CNAME("www.example.com".to_string());
+                (vec![("alias.example.com", cname)], vec![])
+            }
+            ("www.unsigned.example.com", QueryType::A) => {
+                let a = RData::A([198, 51, 100, 1]);
+                (vec![("www.unsigned.example.com", a)], vec![])
+            }
+            ("example.com", QueryType::DNSKEY) => (
+                vec![
+                    ("example.com", dnskey),
+                    ("example.com", rrsig(QueryType::DNSKEY, 2, "487f349fadf5e55ef1abafef2b5c55e3c33b1d320552532bdaa197bdbbb64ccc0271c856d22c1be3ee31d9ed3dd5682eee63942a80d4aa963cb6dd77e8de420b")),
+                ],
+                vec![],
+            ),
+            ("www.example.com", QueryType::DS) => (
+                vec![],
+                nsec(
+                    "www.example.com",
+                    "example.com",
+                    vec![QueryType::A, QueryType::RRSIG, QueryType::NSEC],
+                    "a53dbac911334e52e7d1efb09762b2f37fd888fc7942b6350a80a09bafc2d17c35109d6c34243d3bb250567ca6db25884bdd3137a6daf521eb848567fba46c07",
+                ),
+            ),
+            // the names between example.com and unsigned.example.com don't exist
+            ("nx.example.com", QueryType::A) => {
+                let types = vec![
+                    QueryType::NS,
+                    QueryType::SOA,
+                    QueryType::RRSIG,
+                    QueryType::NSEC,
+                    QueryType::DNSKEY,
+                ];
+                let next_name = "unsigned.example.com".to_string();
+                let authorities = vec![
+                    ("example.com", RData::NSEC { next_name, types }),
+                    ("example.com", rrsig(QueryType::NSEC, 2, "9add6978dbe8a23b7b2745aad350db50c1d42d1277e030d5b65b4342ad3b3c06c1b656c3de5360c41e16dc00fbf409a68e664a151db5f3d9c439a814c05cff00")),
+                ];
+                (vec![], authorities)
+            }
+            // the NSEC record of www.example.com is replayed to deny that it exists
+            ("www.example.com", QueryType::MX) | ("www.example.com", QueryType::AAAA) => {
+                zone("www.example.com", QueryType::DS)
+            }
+            // the NSEC record of the delegation is replayed to deny records in the zone below it
+            ("unsigned.example.com", QueryType::A) => zone("unsigned.example.com", QueryType::DS),
+            ("example.com", QueryType::A) | ("nx.unsigned.example.com", QueryType::A) => {
+                (vec![], vec![])
+            }
+            ("unsigned.example.com", QueryType::DS) => (
+                vec![],
+                nsec(
+                    "unsigned.example.com",
+                    "www.example.com",
+                    vec![QueryType::NS, QueryType::RRSIG, QueryType::NSEC],
+                    "286fb389f168d92d7a7a9a83aaf7db8fcd2fda60230f2510cb1c843d7a1589dd718e2ff03654704d07f0757c478636aae055d25b815ec3ff634eee71e4e58f0e",
+                ),
+            ),
+            _ => panic!("unexpected query for {qtype} records of {name}"),
+        };
+        (answers, authorities)
+    }
+
+    /// Whether the answer to a query is NXDOMAIN rather than from `zone`
+    fn nxdomain(name: &str, qtype: QueryType) -> bool {
+        matches!(
+            (name, qtype),
+            ("nx.example.com" | "nx.unsigned.example.com", _) | ("www.example.com", QueryType::MX)
+        )
+    }
+
+    /// Answers `queries` queries on `sock` from `zone`.
+    fn serve_zone(sock: UdpSocket, queries: usize) {
+        for _ in 0..queries {
+            let mut buf = [0u8; 512];
+            let (len, peer) = sock.recv_from(&mut buf).unwrap();
+            let question = &Response::try_from(&buf[..len]).unwrap().questions[0];
+            let (answers, authorities) = zone(&question.qname, question.qtype);
+            let mut response = response_to(&buf[..len]);
+            if nxdomain(&question.qname, question.qtype) {
+                response[3] |= 3;
+            }
+            response[7] = answers.len() as u8;
+            response[9] = authorities.len() as u8;
+            for (owner, rdata) in answers.iter().chain(&authorities) {
+                let rtype = match rdata {
+                    RData::A(_) => QueryType::A,
+                    RData::TXT(_) => QueryType::TXT,
+                    RData::CNAME(_) => QueryType::CNAME,
+                    RData::DNSKEY { .. } => QueryType::DNSKEY,
+                    RData::RRSIG { .. } => QueryType::RRSIG,
+                    _ => QueryType::NSEC,
+                };
+                let rdata = Vec::try_from(rdata).unwrap();
+                write_name(&mut response, owner).unwrap();
+                response.extend_from_slice(&u16::from(rtype).to_be_bytes());
+                response.extend_from_slice(&[0, 1, 0, 0, 0x0e, 0x10]);
+                response.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+                response.extend(rdata);
+            }
+            sock.send_to(&response, peer).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = sock.local_addr().unwrap();
+        let server = thread::spawn(move || serve_zone(sock, 15));
+        let ds = RData::DS {
+            key_tag: 34259,
+            algorithm: 15,
+            digest_type: 2,
+            digest: hex("7841623a2cf575a789b1c1f0e0d6564146757e8b66555a9b5e38e9087adbbeb8"),
+        };
+        let resolver = Resolver::with_config(ResolvConf {
+            nameservers: vec![addr],
+            ..ResolvConf::default()
+        })
+        .with_hosts(None)
+        .with_dnssec(Some(TrustAnchor::new("example.com.", vec![ds])));
+        let lookup = |name, rtype| resolver.lookup(1, name, rtype).unwrap().dnssec.unwrap();
+
+        assert_eq!(lookup("www.example.com", QueryType::A), Dnssec::Secure);
+        let bogus = |reason: &str| Dnssec::Bogus(reason.to_string());
+        assert_eq!(
+            lookup("forged.example.com", QueryType::A),
+            bogus("bad signature over the A records of forged.example.com")
+        );
+        // www.example.com is in the signed zone, according to the NSEC record for it
+        assert_eq!(
+            lookup("www.example.com", QueryType::TXT),
+            bogus("the TXT records of www.example.com aren't signed")
+        );
+        assert_eq!(
+            lookup("www.unsigned.example.com", QueryType::A),
+            Dnssec::Insecure
+        );
+        assert_eq!(
+            lookup("nowhere.example.com", QueryType::A),
+            bogus(
+                "no proof that there is no closer match for nowhere.example.com than *.example.com"
+            )
+        );
+        // the chain of trust down to www.alias.example.com goes through the alias
+        assert_eq!(
+            lookup("www.alias.example.com", QueryType::A),
+            bogus("the CNAME records of alias.example.com aren't signed")
+        );
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_validate_denial() {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = sock.local_addr().unwrap();
+        let server = thread::spawn(move || serve_zone(sock, 13));
+        let ds = RData::DS {
+            key_tag: 34259,
+            algorithm: 15,
+            digest_type: 2,
+            digest: hex("7841623a2cf575a789b1c1f0e0d6564146757e8b66555a9b5e38e9087adbbeb8"),
+        };
+        let resolver = Resolver::with_config(ResolvConf {
+            nameservers: vec![addr],
+            ..ResolvConf::default()
+        })
+        .with_hosts(None)
+        .with_dnssec(Some(TrustAnchor::new("example.com.", vec![ds])));
+        let error = |name, rtype| resolver.lookup(1, name, rtype).unwrap_err().to_string();
+
+        assert_eq!(
+            error("nx.example.com", QueryType::A),
+            "failed to resolve nx.example.com: no such domain"
+        );
+        // the NSEC record proves that www.example.com exists
+        assert_eq!(
+            error("www.example.com", QueryType::MX),
+            "malformed DNS message: DNSSEC validation of www.example.com failed: \
+             Some(Bogus(\"no proof that www.example.com doesn't exist\"))"
+        );
+        assert_eq!(
+            error("www.example.com", QueryType::AAAA),
+            "no records found for www.example.com"
+        );
+        assert_eq!(
+            error("example.com", QueryType::A),
+            "malformed DNS message: DNSSEC validation of example.com failed: \
+             Some(Bogus(\"no proof that example.com has no A records\"))"
+        );
+        assert_eq!(
+            error("unsigned.example.com", QueryType::A),
+            "malformed DNS message: DNSSEC validation of unsigned.example.com failed: \
+             Some(Bogus(\"no proof that unsigned.example.com has no A records\"))"
+        );
+        // in an unsigned zone
+        assert_eq!(
+            error("nx.unsigned.example.com", QueryType::A),
+            "failed to resolve nx.unsigned.example.com: no such domain"
+        );
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_nsec3_denials() {
+        // the zone of RFC 5155 appendix A
+        let nsec3 = |hash: &str, flags, next, types| {
+            let rdata = RData::NSEC3 {
+                hash_algorithm: 1,
+                flags,
+                iterations: 12,
+                salt: hex("aabbccdd"),
+                next_hashed_owner: hex(next),
+                types,
+            };
+            (format!("{hash}.example"), rdata)
+        };
+        let apex = |flags| {
+            nsec3(
+                "0p9mhaveqvm6t7vbl5lop2u3t2rp3tom",
+                flags,
+                "174eb2409fe28bcb4887a1836f957f0a8425e27b",
+                vec![
+                    QueryType::NS,
+                    QueryType::SOA,
+                    QueryType::MX,
+                    QueryType::RRSIG,
+                ],
+            )
+        };
+        let others = [
+            // ns1.example
+            nsec3(
+                "2t7b4g4vsa5smi47k61mv5bv1a22bojr",
+                0,
+                "17f3df17b2b2adaef615257de4d2020b80ac6c7c",
+                vec![QueryType::A, QueryType::RRSIG],
+            ),
+            // a.example, covering *.x.w.example
+            nsec3(
+                "35mthgpgcu1qg68fab165klnsnk3dpvl",
+                0,
+                "593d6419d08c5bc35dca0a4dcb7ed5c131be2525",
+                vec![QueryType::NS, QueryType::DS, QueryType::RRSIG],
+            ),
+            // x.w.example
+            nsec3(
+                "b4um86eghhds6nea196smvmlo4ors995",
+                0,
+                "84dda71446cd56f0c116a57254baef69d09bce12",
+                vec![QueryType::MX, QueryType::RRSIG],
+            ),
+            // w.example, an empty non-terminal
+            nsec3(
+                "k8udemvp1j2f7eg6jebps17vp3n8i58h",
+                0,
+                "a622ad9ecb5a1ac131c85275faa238b92851fa32",
+                vec![],
+            ),
+            // covering z.w.example
+            nsec3(
+                "q04jkcevqvmu85r014c7dkba38o0ji5r",
+                0,
+                "d946bd1d8c17bf6f2dfe2e196b1b2edf13da25d7",
+                vec![QueryType::A, QueryType::RRSIG],
+            ),
+            // *.w.example
+            nsec3(
+                "r53bq7cc2uvmubfu5ocmm6pers9tk9en",
+                0,
+                "e988472f544ae4b65d4839212fecd3c4cc2b563f",
+                vec![QueryType::MX, QueryType::RRSIG],
+            ),
+        ];
+        fn denials(records: &[(String, RData)]) -> Denials<'_> {
+            let mut denials = Denials {
+                zone: "example".to_string(),
+                ..Default::default()
+            };
+            for (owner, rdata) in records {
+                denials.add(owner, rdata);
+            }
+            denials
+        }
+        let zone = [vec![apex(0)], others.to_vec()].concat();
+        let opt_out = [vec![apex(OPT_OUT)], others.to_vec()].concat();
+        let without_wildcard: Vec<_> = zone
+            .iter()
+            .filter(|(owner, _)| !owner.starts_with("35mt"))
+            .cloned()
+            .collect();
+        let bogus = |reason: &str| Dnssec::Bogus(reason.to_string());
+
+        // the closest encloser is x.w.example
+        assert_eq!(denials(&zone).no_name("a.c.x.w.example"), Dnssec::Secure);
+        assert_eq!(
+            denials(&without_wildcard).no_name("a.c.x.w.example"),
+            bogus("no proof that a.c.x.w.example doesn't exist")
+        );
+        assert_eq!(
+            denials(&opt_out).no_name("a.c.x.w.example"),
+            Dnssec::Insecure
+        );
+        assert_eq!(
+            denials(&zone).no_name("x.w.example"),
+            bogus("no proof that x.w.example doesn't exist")
+        );
+        assert_eq!(
+            denials(&zone).no_type("ns1.example", QueryType::MX),
+            Dnssec::Secure
+        );
+        assert_eq!(
+            denials(&zone).no_type("ns1.example", QueryType::A),
+            bogus("no proof that ns1.example has no A records")
+        );
+        // matched by *.w.example
+        assert_eq!(
+            denials(&zone).no_type("a.z.w.example", QueryType::AAAA),
+            Dnssec::Secure
+        );
+        assert_eq!(
+            denials(&zone).no_type("a.z.w.example", QueryType::MX),
+            bogus("no proof that a.z.w.example has no MX records")
+        );
+        assert_eq!(
+            denials(&opt_out).no_type("c.x.w.example", QueryType::DS),
+            Dnssec::Insecure
+        );
+        // answered from *.w.example, the next closer name z.w.example doesn't exist
+        assert_eq!(
+            denials(&zone).no_closer_name("a.z.w.example", 2),
+            Dnssec::Secure
+        );
+        let without_next_closer: Vec<_> = zone
+            .iter()
+            .filter(|(owner, _)| !owner.starts_with("q04j"))
+            .cloned()
+            .collect();
+        assert_eq!(
+            denials(&without_next_closer).no_closer_name("a.z.w.example", 2),
+            bogus("no proof that there is no closer match for a.z.w.example than *.w.example")
+        );
+    }
+
+    #[test]
+    fn test_nsec_denials() {
+        let nsec = |owner: &str, next: &str, types| {
+            let next_name = next.to_string();
+            (owner.to_string(), RData::NSEC { next_name, types })
+        };
+        let zone = [
+            nsec(
+                "example.com",
+                "*.example.com",
+                vec![
+                    QueryType::NS,
+                    QueryType::SOA,
+                    QueryType::RRSIG,
+                    QueryType::NSEC,
+                ],
+            ),
+            nsec(
+                "*.example.com",
+                "a.b.example.com",
+                vec![QueryType::A, QueryType::RRSIG, QueryType::NSEC],
+            ),
+            nsec(
+                "a.b.example.com",
+                "example.com",
+                vec![QueryType::A, QueryType::RRSIG, QueryType::NSEC],
+            ),
+        ];
+        let mut denials = Denials {
+            zone: "example.com".to_string(),
+            ..Default::default()
+        };
+        for (owner, rdata) in &zone {
+            denials.add(owner, rdata);
+        }
+        let bogus = |reason: &str| Dnssec::Bogus(reason.to_string());
+
+        // names that would be answered from the wildcard
+        assert_eq!(
+            denials.no_name("www.example.com"),
+            bogus("no proof that www.example.com doesn't exist")
+        );
+        assert_eq!(denials.no_closer_name("www.example.com", 2), Dnssec::Secure);
+        assert_eq!(
+            denials.no_type("www.example.com", QueryType::AAAA),
+            Dnssec::Secure
+        );
+        assert_eq!(
+            denials.no_closer_name("a.b.example.com", 2),
+            bogus("no proof that there is no closer match for a.b.example.com than *.example.com")
+        );
+        // an empty non-terminal, with a.b.example.com below it
+        assert_eq!(
+            denials.no_type("b.example.com", QueryType::A),
+            Dnssec::Secure
+        );
+        assert_eq!(denials.no_name("c.b.example.com"), Dnssec::Secure);
+        // the DS records of example.com are in the zone above
+        assert_eq!(
+            denials.no_type("example.com", QueryType::DS),
+            bogus("no proof that example.com has no DS records")
+        );
+    }
+
+    #[test]
+    fn test_rsa_and_ecdsa_signatures() {
+        // signatures of the DNSKEY records of example.com with themselves
+        let keys = [
+            (8, "03010001a02aa7f02a0fcc7d326c2f6d439708c8d8eb4b50ee4ea6fb0eba7cfa535e19b9956868930fc55be2e4926ee16478019643d6cc662a0021180ae04254d558fecda0d7c77def757f6b9215a5eee61aaf20743db607c68992edd842538d33345a5a109a4a7fdc4f13f846e6c8174b2051d5699b6ef84b1d0c45bc17410974c1809f", "4e3619e0fcd2f64e430ed43f487269a6c5c928d05a70ec5f65fb52ca711f1186592add545288ff3db247b7061d414a5160b02ccfda0238ebfeb63ac8513029fb78c2dc7674d8f1796882bfdb4cda3dd5ebe8b52064bf890dc24d7df6c065513be5f05294ccd40e313f9806fadc404b7da613e08401c83f650e6271b2c0724c8c"),
+            (13, "c7f176ca955f54817098d34c97da8b4c3b6488332d6623b5ddb97bb4fbc980fb5857ea72b8a92a4707390ddc928c6254aeb70b92cac96396c45740972a807122", "6b97de68076c9df95e0631e454688477e29170c236a1c804096da2dafa4b44fd90cbc1dd5c39e40322da1d08bf44d9e1fa72e525eb83c33776624f26f586bd82"),
+        ];
+        for (algorithm, public_key, signature) in keys {
+            let key = RData::DNSKEY {
+                flags: 256,
+                protocol: 3,
+                algorithm,
+                public_key: hex(public_key),
+            };
+            let rrsig = RData::RRSIG {
+                type_covered: QueryType::DNSKEY,
+                algorithm,
+                labels: 2,
+                original_ttl: 3600,
+                expiration: 2840140800,
+                inception: 1577836800,
+                key_tag: key_tag_of(&key),
+                signer_name: "example.com".to_string(),
+                signature: hex(signature),
+            };
+            let rrset = |signature| RRset {
+                name: "example.com".to_string(),
+                rtype: QueryType::DNSKEY,
+                records: vec![&key],
+                signatures: vec![signature],
+            };
+            let keys = [key.clone()];
+            assert_eq!(verify_rrset(&rrset(&rrsig), "example.com", &keys), Ok(2));
+            let mut forged = rrsig.clone();
+            if let RData::RRSIG { signature, .. } = &mut forged {
+                signature[10] ^= 1;
+            }
+            assert_eq!(
+                verify_rrset(&rrset(&forged), "example.com", &keys),
+                Err("bad signature over the DNSKEY records of example.com".to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn test_nsec3_hash() {
+        // RFC 5155 appendix A
+        let hash = nsec3_hash("example", &hex("aabbccdd"), 12).unwrap();
+        assert_eq!(base32hex(&hash), "0p9mhaveqvm6t7vbl5lop2u3t2rp3tom");
+        let hash = nsec3_hash("a.example", &hex("aabbccdd"), 12).unwrap();
+        assert_eq!(base32hex(&hash), "35mthgpgcu1qg68fab165klnsnk3dpvl");
+        assert!(!covers("0p9m", "2t7b", "35mt"));
+        assert!(!covers("kohar", "0p9m", "35mt"));
+        assert!(covers("kohar", "0p9m", "q04j"));
+    }
+}
@@ -0,0 +1,219 @@
+use std::{sync::Arc, time::Duration};
+
+use super::{base64, Response};
+use crate::{
+    http::{HTTPRequest, Method},
+    redirect::RedirectPolicy,
+    Client, Error, Url,
+};
+
+/// Media type of DNS messages in HTTP (RFC 8484 section 6)
+const DNS_MESSAGE: &str = "application/dns-message";
+
+/**
+ * A DNS-over-HTTPS server (RFC 8484), such as "https://cloudflare-dns.com/dns-query"
+ *
+ * Queries go through a Client of their own, whose host name is resolved with the system's
+ * resolver. http URLs work as well, for servers on the local machine.
+ */
+#[derive(Clone)]
+pub struct Doh {
+    url: Url,
+    method: Method,
+    client: Arc<Client>,
+}
+
+impl std::fmt::Debug for Doh {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Doh")
+            .field("url", &self.url)
+            .field("method", &self.method)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Doh {
+    /// Sends queries to the server at `url` with POST requests.
+    pub fn new(url: &str) -> Result<Self, Error> {
+        let client = Client::builder()
+            .redirect_policy(RedirectPolicy::Limited(0))
+            .build();
+        Self::with_client(url, client)
+    }
+
+    /// Like `new`, but sends the requests with `client`, such as one with its own TLS
+    /// configuration. The client must not use this server as its resolver.
+    pub fn with_client(url: &str, client: Client) -> Result<Self, Error> {
+        let url = Url::parse(url)?;
+        if !matches!(url.scheme(), "https" | "http") {
+            return Err(Error::Url(format!("{url} isn't an http or https URL")));
+        }
+        Ok(Self {
+            url,
+            method: Method::POST,
+            client: Arc::new(client),
+        })
+    }
+
+    /// Sends queries with GET or POST requests. GET requests can be cached by HTTP caches.
+    pub fn method(mut self, method: Method) -> Result<Self, Error> {
+        if !matches!(method, Method::GET | Method::POST) {
+            return Err(Error::Http(format!(
+                "DNS queries can't be sent with {method}"
+            )));
+        }
+        self.method = method;
+        Ok(self)
+    }
+
+    /// Sends `query` in the wire format and returns the response (RFC 8484 section 4.1). Fails
+    /// with `Error::Timeout` if connecting or any read or write takes longer than `timeout`.
+    pub(crate) fn exchange(&self, query: &[u8], timeout: Duration) -> Result<Response, Error> {
+        let mut request = match self.method {
+            Method::GET => {
+                let query = match self.url.query() {
+                    Some(q) => format!("{q}&dns={}", base64(query, true)),
+                    None => format!("dns={}", base64(query, true)),
+                };
+                let url = self.url.join(&format!("{}?{query}", self.url.path()))?;
+                HTTPRequest::new(Method::GET, &url, None)
+            }
+            _ => {
+                let mut request = HTTPRequest::new(Method::POST, &self.url, Some(query.to_vec()));
+                request.insert_header("Content-Type", DNS_MESSAGE);
+                request
+            }
+        };
+        request.insert_header("Accept", DNS_MESSAGE);
+        let response = self.client.execute_timeout(request, timeout)?;
+        if !response.is_success() {
+            return Err(Error::Http(format!(
+                "DNS server {} answered {} {}",
+                self.url,
+                response.status(),
+                response.reason()
+            )));
+        }
+        let content_type = response.headers().get("Content-Type").unwrap_or_default();
+        // the media type may have parameters (RFC 9110 section 8.3.1)
+        let media_type = content_type.split(';').next().unwrap_or_default().trim();
+        if !media_type.eq_ignore_ascii_case(DNS_MESSAGE) {
+            return Err(Error::Dns(format!(
+                "unexpected content type {content_type:?} from {}",
+                self.url
+            )));
+        }
+        let response = Response::try_from(response.bytes())?;
+        if !response.header.qr {
+            return Err(Error::Dns("the answer isn't a response".to_string()));
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dns::{tests::answer, Query, QueryType, ResolvConf, Resolver},
+        http::HTTPHeaders,
+    };
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    /// Answers one request like a DoH server, with `query` for GET requests and a response of
+    /// `content_type`, and returns the request line and headers.
+    fn serve_doh(
+        listener: TcpListener,
+        query: Vec<u8>,
+        content_type: &str,
+    ) -> (String, HTTPHeaders) {
+        let (sock, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(sock.try_clone().unwrap());
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        let headers = HTTPHeaders::new(&mut reader).unwrap();
+        let query = match headers.get("Content-Length") {
+            Some(len) => {
+                let mut body = vec![0; len.parse().unwrap()];
+                reader.read_exact(&mut body).unwrap();
+                body
+            }
+            None => query,
+        };
+        let response = answer(&query);
+        let head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\r\n",
+            response.len()
+        );
+        (&sock)
+            .write_all(&[head.as_bytes(), &response].concat())
+            .unwrap();
+        (request_line.trim_end().to_string(), headers)
+    }
+
+    #[test]
+    fn test_resolve_over_doh() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/dns-query", listener.local_addr().unwrap());
+        let server = thread::spawn(move || serve_doh(listener, vec![], DNS_MESSAGE));
+        let resolver = Resolver::with_config(ResolvConf::default())
+            .with_hosts(None)
+            .with_doh(Some(Doh::new(&url).unwrap()));
+        assert_eq!(
+            resolver.resolve(1, "www.example.com").unwrap(),
+            "192.0.2.1".parse::<std::net::IpAddr>().unwrap()
+        );
+        let (request_line, headers) = server.join().unwrap();
+        assert_eq!(request_line, "POST /dns-query HTTP/1.1");
+        assert_eq!(headers.get("content-type"), Some(DNS_MESSAGE));
+        assert_eq!(headers.get("accept"), Some(DNS_MESSAGE));
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/dns-query?ct", listener.local_addr().unwrap());
+        let query = Vec::try_from(Query::new(0, "www.example.com", QueryType::AAAA)).unwrap();
+        let target = format!("/dns-query?ct&dns={}", base64(&query, true));
+        let server =
+            thread::spawn(move || serve_doh(listener, query, "Application/DNS-Message ; x=1"));
+        let doh = Doh::new(&url).unwrap().method(Method::GET).unwrap();
+        let query = Vec::try_from(Query::new(0, "www.example.com", QueryType::AAAA)).unwrap();
+        let response = doh.exchange(&query, Duration::from_secs(5)).unwrap();
+        assert_eq!(response.answers.len(), 1);
+        assert_eq!(server.join().unwrap().0, format!("GET {target} HTTP/1.1"));
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/dns-query", listener.local_addr().unwrap());
+        let server = thread::spawn(move || serve_doh(listener, vec![], "application/dns-messages"));
+        let doh = Doh::new(&url).unwrap();
+        assert!(matches!(
+            doh.exchange(&query, Duration::from_secs(5)),
+            Err(Error::Dns(_))
+        ));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_unresponsive_server_times_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/dns-query", listener.local_addr().unwrap());
+        // accepts the connection and reads the request, but never answers
+        let server = thread::spawn(move || {
+            let (mut sock, _) = listener.accept().unwrap();
+            let _ = sock.read(&mut [0; 512]);
+            thread::sleep(std::time::Duration::from_millis(500));
+        });
+        let resolver = Resolver::with_config(ResolvConf {
+            timeout: Duration::from_millis(100),
+            attempts: 1,
+            ..ResolvConf::default()
+        })
+        .with_hosts(None)
+        .with_doh(Some(Doh::new(&url).unwrap()));
+        let result = resolver.resolve(1, "www.example.com");
+        assert!(matches!(result, Err(Error::Timeout)), "{result:?}");
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,414 @@
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind, Read, Write},
+    net::{Shutdown, SocketAddr, TcpStream},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use super::{Question, Response};
+use crate::{
+    tls::{ClientConfig, ServerName, TlsStream},
+    Error,
+};
+
+/// Port of DNS over TLS (RFC 7858 section 3.1)
+pub const DOT_PORT: u16 = 853;
+
+/**
+ * A DNS-over-TLS server (RFC 7858)
+ *
+ * Queries share one connection, which is opened with the first query and reopened when the
+ * server closes it. Clones share the connection too.
+ */
+#[derive(Debug, Clone)]
+pub struct Dot {
+    server: SocketAddr,
+    server_name: ServerName,
+    tls_config: ClientConfig,
+    connection: Arc<Mutex<Option<Handle>>>,
+}
+
+impl Dot {
+    /// Sends queries to `server`, such as 1.1.1.1:853, authenticated by its certificate for
+    /// `server_name` issued by one of the CAs of the operating system.
+    pub fn new(server: SocketAddr, server_name: &str) -> Self {
+        Self {
+            server,
+            server_name: ServerName::new(server_name),
+            tls_config: ClientConfig::default(),
+            connection: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Authenticates the server with `tls_config` instead.
+    pub fn with_tls_config(mut self, tls_config: ClientConfig) -> Self {
+        self.tls_config = tls_config;
+        self
+    }
+
+    /// Authenticates the server by the SHA-256 digest of the key of its certificate, or of a
+    /// certificate in its chain that issued it, instead of its name (RFC 7858 section 4.2).
+    pub fn with_spki_pin(mut self, sha256: [u8; 32]) -> Self {
+        self.tls_config.spki_pins.push(sha256);
+        self
+    }
+
+    /// Sends `query` with the transaction ID `id` and waits up to `timeout` for its response.
+    /// Other queries may be sent over the connection meanwhile (RFC 7766 section 6.2.1.1).
+    pub(crate) fn exchange(
+        &self,
+        query: &[u8],
+        id: u16,
+        timeout: Duration,
+    ) -> Result<Response, Error> {
+        let questions = Response::try_from(query)?.questions;
+        let (connection, reused) = self.connection(timeout)?;
+        match connection.exchange(query, (id, questions.clone()), timeout) {
+            // the server may have closed the idle connection just as it was reused, which closes
+            // it here as well
+            Err(Error::Io(_)) if reused => {
+                let (connection, _) = self.connection(timeout)?;
+                connection.exchange(query, (id, questions), timeout)
+            }
+            result => result,
+        }
+    }
+
+    /// Returns the open connection if there is one, otherwise a new one, and whether it was
+    /// reused. Connects without the lock, so that other queries can use the open connection
+    /// meanwhile.
+    fn connection(&self, timeout: Duration) -> Result<(Arc<TlsConnection>, bool), Error> {
+        if let Some(open) = self.open_connection() {
+            return Ok((open, true));
+        }
+        let sock = TcpStream::connect_timeout(&self.server, timeout)?;
+        sock.set_read_timeout(Some(timeout))?;
+        sock.set_write_timeout(Some(timeout))?;
+        let reader = sock.try_clone()?;
+        let stream = TlsStream::connect(&self.tls_config, &self.server_name, sock)?;
+        let open = Connection::open(stream, reader);
+        let mut connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        match connection.as_ref() {
+            // another query connected meanwhile, and may have queries waiting on it
+            Some(Handle(other)) if other.is_open() => {
+                open.close();
+                Ok((other.clone(), true))
+            }
+            _ => {
+                *connection = Some(Handle(open.clone()));
+                Ok((open, false))
+            }
+        }
+    }
+
+    fn open_connection(&self) -> Option<Arc<TlsConnection>> {
+        match self
+            .connection
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+        {
+            Some(Handle(open)) if open.is_open() => Some(open.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Closes the connection when the last clone of its [`Dot`] is dropped, which ends its reader.
+#[derive(Debug)]
+struct Handle(Arc<TlsConnection>);
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        self.0.close();
+    }
+}
+
+type TlsConnection = Connection<TlsStream<TcpStream>>;
+
+/// A transaction ID with the question, which identify the response to a query
+type Key = (u16, Vec<Question>);
+
+/// A stream that DNS messages arrive on
+trait Stream: Read + Write + Send + 'static {
+    /// Returns the data that arrived, or `None` at the end of the stream, without waiting long
+    /// once the socket is readable.
+    fn read_available(&mut self) -> io::Result<Option<Vec<u8>>>;
+}
+
+impl Stream for TlsStream<TcpStream> {
+    fn read_available(&mut self) -> io::Result<Option<Vec<u8>>> {
+        TlsStream::read_available(self)
+    }
+}
+
+/**
+ * A stream carrying length-prefixed DNS messages (RFC 1035 section 4.2.2)
+ *
+ * A reader thread waits for the server on `sock`, a clone of the socket under `stream`, so that
+ * the stream is only locked to read what arrived or to send a query. It hands each response to
+ * the query waiting for it, and drops the ones nobody waits for.
+ */
+#[derive(Debug)]
+struct Connection<S> {
+    stream: Mutex<S>,
+    sock: TcpStream,
+    /// The queries waiting for a response, or `None` once the connection is closed
+    waiting: Mutex<Option<HashMap<Key, mpsc::Sender<Response>>>>,
+}
+
+impl<S: Stream> Connection<S> {
+    /// Starts reading responses from `stream`, whose socket `sock` is a clone of.
+    fn open(stream: S, sock: TcpStream) -> Arc<Self> {
+        let connection = Arc::new(Self {
+            stream: Mutex::new(stream),
+            sock,
+            waiting: Mutex::new(Some(HashMap::new())),
+        });
+        let reader = connection.clone();
+        thread::spawn(move || reader.read_responses());
+        connection
+    }
+
+    fn is_open(&self) -> bool {
+        self.waiting
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_some()
+    }
+
+    /// Fails the queries still waiting, and stops the reader.
+    fn close(&self) {
+        *self.waiting.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        // fails if the server closed the socket already
+        let _ = self.sock.shutdown(Shutdown::Both);
+    }
+
+    /// Sends `query` and waits up to `timeout` for the response identified by `key`.
+    fn exchange(&self, query: &[u8], key: Key, timeout: Duration) -> Result<Response, Error> {
+        let len = u16::try_from(query.len())
+            .map_err(|_| Error::Dns("query is longer than 65535 bytes".to_string()))?;
+        let (sender, receiver) = mpsc::channel();
+        match self
+            .waiting
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_mut()
+        {
+            None => return Err(closed().into()),
+            Some(waiting) if waiting.contains_key(&key) => {
+                return Err(Error::Dns(format!(
+                    "a query with ID {} and the same question is already waiting",
+                    key.0
+                )))
+            }
+            Some(waiting) => {
+                waiting.insert(key.clone(), sender);
+            }
+        }
+        let sent = {
+            let mut stream = self.stream.lock().unwrap_or_else(|e| e.into_inner());
+            // one write, so that the message goes out in one record (RFC 7766 section 8)
+            stream
+                .write_all(&[&len.to_be_bytes(), query].concat())
+                .and_then(|()| stream.flush())
+        };
+        if let Err(e) = sent {
+            // the stream may be in the middle of a message
+            self.close();
+            return Err(e.into());
+        }
+        match receiver.recv_timeout(timeout) {
+            Ok(response) => Ok(response),
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                if let Some(waiting) = self
+                    .waiting
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .as_mut()
+                {
+                    waiting.remove(&key);
+                }
+                Err(Error::Timeout)
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(closed().into()),
+        }
+    }
+
+    /// Hands out responses until the connection is closed or fails.
+    fn read_responses(&self) {
+        let mut pending = vec![];
+        while self.is_open() {
+            // waits without the lock, so that queries can be sent meanwhile
+            match self.sock.peek(&mut [0]) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    continue
+                }
+                Err(_) => break,
+            }
+            let data = self
+                .stream
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .read_available();
+            match data {
+                Ok(Some(data)) => pending.extend(data),
+                // a timeout here leaves the stream in the middle of a message
+                Ok(None) | Err(_) => break,
+            }
+            while let Some(message) = next_message(&mut pending) {
+                self.hand_out(&message);
+            }
+        }
+        self.close();
+    }
+
+    /// Sends `message` to the query waiting for it, if it is a response and there is one.
+    fn hand_out(&self, message: &[u8]) {
+        let Ok(response) = Response::try_from(message) else {
+            return;
+        };
+        if !response.header.qr {
+            return;
+        }
+        let key = (response.header.id, response.questions.clone());
+        let mut waiting = self.waiting.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(sender) = waiting.as_mut().and_then(|waiting| waiting.remove(&key)) {
+            // the query may have given up already
+            let _ = sender.send(response);
+        }
+    }
+}
+
+/// Removes the first message from `pending` if all of it arrived.
+fn next_message(pending: &mut Vec<u8>) -> Option<Vec<u8>> {
+    let len = usize::from(u16::from_be_bytes([*pending.first()?, *pending.get(1)?]));
+    if pending.len() < 2 + len {
+        return None;
+    }
+    let message = pending[2..2 + len].to_vec();
+    pending.drain(..2 + len);
+    Some(message)
+}
+
+fn closed() -> io::Error {
+    io::Error::new(
+        ErrorKind::ConnectionAborted,
+        "the connection to the DNS server was closed",
+    )
+}
+
+#[cfg(test)]
+impl Stream for TcpStream {
+    fn read_available(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut buf = [0u8; 4096];
+        let n = self.read(&mut buf)?;
+        Ok((n > 0).then(|| buf[..n].to_vec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::{tests::answer, Query, QueryType};
+    use std::net::TcpListener;
+
+    fn read_query(sock: &mut TcpStream) -> Vec<u8> {
+        let mut len = [0u8; 2];
+        sock.read_exact(&mut len).unwrap();
+        let mut query = vec![0u8; u16::from_be_bytes(len).into()];
+        sock.read_exact(&mut query).unwrap();
+        query
+    }
+
+    fn write_response(sock: &mut TcpStream, response: &[u8]) {
+        let len = (response.len() as u16).to_be_bytes();
+        sock.write_all(&[&len[..], response].concat()).unwrap();
+    }
+
+    fn connect(addr: SocketAddr) -> Arc<Connection<TcpStream>> {
+        let sock = TcpStream::connect(addr).unwrap();
+        sock.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        Connection::open(sock.try_clone().unwrap(), sock)
+    }
+
+    fn exchange(
+        connection: &Connection<TcpStream>,
+        id: u16,
+        qtype: QueryType,
+        timeout: Duration,
+    ) -> Result<Response, Error> {
+        let query = Vec::try_from(Query::new(id, "example.com", qtype)).unwrap();
+        let key = (id, vec![Question::new("example.com", qtype)]);
+        connection.exchange(&query, key, timeout)
+    }
+
+    fn waiting(connection: &Connection<TcpStream>) -> usize {
+        connection.waiting.lock().unwrap().as_ref().unwrap().len()
+    }
+
+    #[test]
+    fn test_pipelined_responses_out_of_order() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut sock, _) = listener.accept().unwrap();
+            // both queries are sent while the first one waits for its response
+            let queries = [read_query(&mut sock), read_query(&mut sock)];
+            // a response nobody waits for is dropped
+            let mut unsolicited = answer(&queries[0]);
+            unsolicited[..2].copy_from_slice(&7u16.to_be_bytes());
+            write_response(&mut sock, &unsolicited);
+            for query in queries.iter().rev() {
+                write_response(&mut sock, &answer(query));
+            }
+            sock
+        });
+        let connection = connect(addr);
+        let timeout = Duration::from_secs(5);
+        // the same ID with different questions
+        let aaaa = thread::spawn({
+            let connection = connection.clone();
+            move || exchange(&connection, 1, QueryType::AAAA, timeout)
+        });
+        let a = thread::spawn({
+            let connection = connection.clone();
+            move || exchange(&connection, 1, QueryType::A, timeout)
+        });
+        let response = aaaa.join().unwrap().unwrap();
+        assert_eq!(response.answers[0].query_type, QueryType::AAAA);
+        let response = a.join().unwrap().unwrap();
+        assert_eq!(response.answers[0].query_type, QueryType::A);
+        assert_eq!(waiting(&connection), 0);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_late_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (late, answer_late) = mpsc::channel();
+        let server = thread::spawn(move || {
+            let (mut sock, _) = listener.accept().unwrap();
+            let query = read_query(&mut sock);
+            answer_late.recv().unwrap();
+            write_response(&mut sock, &answer(&query));
+            let query = read_query(&mut sock);
+            write_response(&mut sock, &answer(&query));
+            sock
+        });
+        let connection = connect(addr);
+        let result = exchange(&connection, 1, QueryType::A, Duration::from_millis(100));
+        assert!(matches!(result, Err(Error::Timeout)));
+        assert_eq!(waiting(&connection), 0);
+        late.send(()).unwrap();
+        let response = exchange(&connection, 2, QueryType::A, Duration::from_secs(5)).unwrap();
+        assert_eq!(response.header.id, 2);
+        assert_eq!(waiting(&connection), 0);
+        server.join().unwrap();
+    }
+}
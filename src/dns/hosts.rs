@@ -0,0 +1,126 @@
+use std::{
+    collections::HashMap,
+    fs,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+/**
+ * Static host names from a hosts(5) file, reloaded whenever the file changes
+ *
+ * Clones share the entries read from the file.
+ */
+#[derive(Debug, Clone)]
+pub struct Hosts {
+    path: PathBuf,
+    cache: Arc<Mutex<Cache>>,
+}
+
+#[derive(Debug, Default)]
+struct Cache {
+    // modification time and length of the file when it was read, None if it couldn't be read
+    version: Option<(SystemTime, u64)>,
+    entries: HashMap<String, Vec<IpAddr>>,
+}
+
+impl Hosts {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            cache: Arc::new(Mutex::new(Cache::default())),
+        }
+    }
+
+    /// The hosts file of the operating system.
+    pub fn system() -> Self {
+        Self::new("/etc/hosts")
+    }
+
+    /// Returns the addresses of `name` in the order they appear in the file.
+    pub fn lookup(&self, name: &str) -> Option<Vec<IpAddr>> {
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        let version = fs::metadata(&self.path)
+            .and_then(|m| Ok((m.modified()?, m.len())))
+            .ok();
+        if version != cache.version {
+            cache.entries = fs::read_to_string(&self.path)
+                .map(|content| parse(&content))
+                .unwrap_or_default();
+            cache.version = version;
+        }
+        cache.entries.get(&normalize(name)).cloned()
+    }
+}
+
+/// Host names are case-insensitive and may be written with a trailing dot.
+fn normalize(name: &str) -> String {
+    name.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// Parses lines of the form `address canonical_name [aliases...] [# comment]`.
+fn parse(content: &str) -> HashMap<String, Vec<IpAddr>> {
+    let mut entries: HashMap<String, Vec<IpAddr>> = HashMap::new();
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut fields = line.split_whitespace();
+        // a zone index such as fe80::1%eth0 can't be used for a connection by address alone
+        let Some(Ok(addr)) = fields.next().map(str::parse::<IpAddr>) else {
+            continue;
+        };
+        for name in fields {
+            let addrs = entries.entry(normalize(name)).or_default();
+            if !addrs.contains(&addr) {
+                addrs.push(addr);
+            }
+        }
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hosts() {
+        let entries = parse(
+            "# comment\n\
+             127.0.0.1\tlocalhost\n\
+             ::1 localhost ip6-localhost # loopback\n\
+             192.0.2.10 Web.Example.com. web\n\
+             fe80::1%eth0 link-local\n\
+             not-an-address name\n",
+        );
+        assert_eq!(
+            entries["localhost"],
+            [
+                "127.0.0.1".parse::<IpAddr>().unwrap(),
+                "::1".parse().unwrap()
+            ]
+        );
+        assert_eq!(entries["ip6-localhost"], ["::1".parse::<IpAddr>().unwrap()]);
+        assert_eq!(entries["web.example.com"], entries["web"]);
+        assert!(!entries.contains_key("link-local"));
+        assert!(!entries.contains_key("name"));
+    }
+
+    #[test]
+    fn test_hosts_reloads_on_change() {
+        let path = std::env::temp_dir().join(format!("fetch-hosts-{}", std::process::id()));
+        fs::write(&path, "192.0.2.1 example.test\n").unwrap();
+        let hosts = Hosts::new(&path);
+        assert_eq!(
+            hosts.lookup("EXAMPLE.test."),
+            Some(vec!["192.0.2.1".parse().unwrap()])
+        );
+        fs::write(&path, "2001:db8::10 example.test\n").unwrap();
+        assert_eq!(
+            hosts.lookup("example.test"),
+            Some(vec!["2001:db8::10".parse().unwrap()])
+        );
+        fs::remove_file(&path).unwrap();
+        assert_eq!(hosts.lookup("example.test"), None);
+    }
+}
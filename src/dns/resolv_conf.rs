@@ -0,0 +1,185 @@
+use std::{
+    fs,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::Path,
+    time::Duration,
+};
+
+use crate::Error;
+
+/// At most this many nameservers are used, as in glibc (MAXNS)
+const MAX_NAMESERVERS: usize = 3;
+
+/**
+ * Resolver configuration in the format of resolv.conf(5)
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvConf {
+    /// Servers to query, in the order they are tried.
+    pub nameservers: Vec<SocketAddr>,
+    /// Domains appended to names with fewer than `ndots` dots.
+    pub search: Vec<String>,
+    pub ndots: usize,
+    /// How long to wait for an answer from one server, doubled with every round through the servers.
+    pub timeout: Duration,
+    /// How many times each server is tried.
+    pub attempts: usize,
+    /// Spread queries over the servers instead of always starting with the first one.
+    pub rotate: bool,
+    /// Query over TCP only, for networks where UDP is blocked.
+    pub tcp: bool,
+    /// Largest UDP response advertised with EDNS(0), or None to send queries without it.
+    pub udp_payload_size: Option<u16>,
+}
+
+impl Default for ResolvConf {
+    /// The values used when resolv.conf doesn't set them, with a server on the local machine.
+    fn default() -> Self {
+        Self {
+            nameservers: vec![SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 53)],
+            search: vec![],
+            ndots: 1,
+            timeout: Duration::from_secs(5),
+            attempts: 2,
+            rotate: false,
+            tcp: false,
+            // avoids IP fragmentation on common networks (DNS flag day 2020)
+            udp_payload_size: Some(1232),
+        }
+    }
+}
+
+impl ResolvConf {
+    /// Reads the file at `path`, such as /etc/resolv.conf.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    /// Parses the content of a resolv.conf file. Unknown or malformed lines are ignored.
+    pub fn parse(content: &str) -> Self {
+        let mut conf = Self {
+            nameservers: vec![],
+            ..Self::default()
+        };
+        for line in content.lines() {
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("nameserver") => {
+                    // link-local addresses with a zone index such as fe80::1%eth0 are skipped
+                    let addr = fields.next().and_then(|ip| ip.parse::<IpAddr>().ok());
+                    if let Some(ip) = addr.filter(|_| conf.nameservers.len() < MAX_NAMESERVERS) {
+                        conf.nameservers.push(SocketAddr::new(ip, 53));
+                    }
+                }
+                // the last domain or search line wins
+                Some("domain") => conf.search = fields.next().map(normalize).into_iter().collect(),
+                Some("search") => conf.search = fields.map(normalize).collect(),
+                Some("options") => fields.for_each(|option| conf.set_option(option)),
+                _ => {}
+            }
+        }
+        if conf.nameservers.is_empty() {
+            conf.nameservers = Self::default().nameservers;
+        }
+        conf
+    }
+
+    fn set_option(&mut self, option: &str) {
+        let (name, value) = match option.split_once(':') {
+            Some((name, value)) => (name, value.parse::<u64>().ok()),
+            None => (option, None),
+        };
+        // the same limits as glibc
+        match (name, value) {
+            ("ndots", Some(n)) => self.ndots = n.min(15) as usize,
+            ("timeout", Some(n)) => self.timeout = Duration::from_secs(n.clamp(1, 30)),
+            ("attempts", Some(n)) => self.attempts = n.clamp(1, 5) as usize,
+            ("rotate", None) => self.rotate = true,
+            ("use-vc" | "tcp", None) => self.tcp = true,
+            _ => {}
+        }
+    }
+
+    /// Names to query for `name` in order, after search list expansion.
+    ///
+    /// A name ending with a dot is only queried as is. A name with at least `ndots` dots is
+    /// queried as is before the search domains are tried, any other name after them.
+    pub fn candidates(&self, name: &str) -> Vec<String> {
+        if let Some(absolute) = name.strip_suffix('.') {
+            return vec![absolute.to_string()];
+        }
+        let expanded = self.search.iter().map(|domain| format!("{name}.{domain}"));
+        if name.matches('.').count() >= self.ndots {
+            std::iter::once(name.to_string()).chain(expanded).collect()
+        } else {
+            expanded.chain(std::iter::once(name.to_string())).collect()
+        }
+    }
+}
+
+fn normalize(domain: &str) -> String {
+    domain.trim_end_matches('.').to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_resolv_conf() {
+        let conf = ResolvConf::parse(
+            "# generated by NetworkManager\n\
+             ; old comment style\n\
+             domain example.com\n\
+             search Corp.Example.com. example.com\n\
+             nameserver 10.0.0.53\n\
+             nameserver fe80::1%eth0\n\
+             nameserver 2001:db8::53\n\
+             nameserver 10.0.0.54\n\
+             nameserver 10.0.0.55\n\
+             options ndots:2 timeout:60 attempts:3 rotate edns0 use-vc\n",
+        );
+        assert_eq!(
+            conf.nameservers,
+            [
+                "10.0.0.53:53".parse().unwrap(),
+                "[2001:db8::53]:53".parse().unwrap(),
+                "10.0.0.54:53".parse().unwrap()
+            ]
+        );
+        assert_eq!(conf.search, ["corp.example.com", "example.com"]);
+        assert_eq!(conf.ndots, 2);
+        assert_eq!(conf.timeout, Duration::from_secs(30));
+        assert_eq!(conf.attempts, 3);
+        assert!(conf.rotate);
+        assert!(conf.tcp);
+
+        let conf = ResolvConf::parse("");
+        assert_eq!(conf, ResolvConf::default());
+    }
+
+    #[test]
+    fn test_search_list_expansion() {
+        let conf = ResolvConf {
+            search: vec!["corp.example.com".to_string(), "example.com".to_string()],
+            ..ResolvConf::default()
+        };
+        assert_eq!(
+            conf.candidates("intranet"),
+            [
+                "intranet.corp.example.com",
+                "intranet.example.com",
+                "intranet"
+            ]
+        );
+        assert_eq!(
+            conf.candidates("www.rust-lang.org"),
+            [
+                "www.rust-lang.org",
+                "www.rust-lang.org.corp.example.com",
+                "www.rust-lang.org.example.com"
+            ]
+        );
+        assert_eq!(conf.candidates("intranet."), ["intranet"]);
+    }
+}
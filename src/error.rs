@@ -0,0 +1,106 @@
+use std::{fmt::Display, io};
+
+use crate::{dns::Rcode, tls::TlsError, url::Url};
+
+/**
+ * Everything that can go wrong while resolving a name or performing a request
+ */
+#[derive(Debug)]
+pub enum Error {
+    /// Reading from or writing to a socket failed.
+    Io(io::Error),
+    /// The server didn't answer in time.
+    Timeout,
+    Tls(TlsError),
+    /// The URL can't be parsed or uses a scheme other than http and https.
+    Url(String),
+    /// An HTTP message was malformed or used an unsupported feature.
+    Http(String),
+    /// A DNS message was malformed.
+    Dns(String),
+    /// The DNS server answered with an error response code.
+    Rcode {
+        name: String,
+        rcode: Rcode,
+    },
+    /// The name exists but has no records of the requested type.
+    NoRecords(String),
+    /// The redirect policy's limit was exceeded.
+    TooManyRedirects(usize),
+    /// A redirect led back to a URL that was already requested.
+    RedirectLoop(Box<Url>),
+    /// A custom redirect policy failed the request with this message.
+    Redirect(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{e}"),
+            Error::Timeout => write!(f, "timed out"),
+            Error::Tls(e) => write!(f, "TLS error: {e}"),
+            Error::Url(message) => write!(f, "invalid URL: {message}"),
+            Error::Http(message) => write!(f, "malformed HTTP message: {message}"),
+            Error::Dns(message) => write!(f, "malformed DNS message: {message}"),
+            Error::Rcode { name, rcode } => write!(f, "failed to resolve {name}: {rcode}"),
+            Error::NoRecords(name) => write!(f, "no records found for {name}"),
+            Error::TooManyRedirects(max) => write!(f, "too many redirects (more than {max})"),
+            Error::RedirectLoop(url) => write!(f, "redirect loop detected at {url}"),
+            Error::Redirect(message) => write!(f, "redirect refused: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Tls(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    /// TLS failures surface as I/O errors from reads and writes and are unwrapped here.
+    fn from(e: io::Error) -> Self {
+        if matches!(
+            e.kind(),
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+        ) {
+            return Error::Timeout;
+        }
+        match e.downcast::<TlsError>() {
+            Ok(tls) => Error::from(tls),
+            Err(e) => Error::Io(e),
+        }
+    }
+}
+
+impl From<TlsError> for Error {
+    fn from(e: TlsError) -> Self {
+        match e {
+            TlsError::Io(e) => Error::from(e),
+            e => Error::Tls(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+
+    #[test]
+    fn test_error_from_io() {
+        let refused = io::Error::from(io::ErrorKind::ConnectionRefused);
+        assert!(matches!(Error::from(refused), Error::Io(_)));
+        let timeout = io::Error::from(io::ErrorKind::WouldBlock);
+        assert!(matches!(Error::from(timeout), Error::Timeout));
+
+        let tls = io::Error::from(TlsError::AlertReceived(40));
+        let error = Error::from(tls);
+        assert!(matches!(error, Error::Tls(TlsError::AlertReceived(40))));
+        assert!(error.source().is_some());
+    }
+}
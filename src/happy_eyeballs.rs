@@ -0,0 +1,125 @@
+use std::{
+    io,
+    net::{IpAddr, SocketAddr, TcpStream},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+use crate::Error;
+
+/// Time to wait for a connection attempt before starting the next one (RFC 8305 section 8)
+pub const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/**
+ * Orders addresses for connection attempts (RFC 8305 section 4)
+ *
+ * The address families alternate, starting with the family of the first address, so that an
+ * unreachable family only delays the other by one attempt.
+ */
+pub fn interleave(addrs: &[IpAddr]) -> Vec<IpAddr> {
+    let Some(first) = addrs.first() else {
+        return vec![];
+    };
+    let (mut preferred, mut other): (Vec<_>, Vec<_>) = addrs
+        .iter()
+        .partition(|addr| addr.is_ipv6() == first.is_ipv6());
+    let mut ordered = Vec::with_capacity(addrs.len());
+    preferred.reverse();
+    other.reverse();
+    loop {
+        match (preferred.pop(), other.pop()) {
+            (None, None) => return ordered,
+            (a, b) => ordered.extend(a.into_iter().chain(b).copied()),
+        }
+    }
+}
+
+/**
+ * Connects to one of `addrs` on `port` (RFC 8305 section 5)
+ *
+ * Attempts start `delay` apart in the order of `interleave`, or as soon as the previous one
+ * fails, and run concurrently. Each gives up after `timeout` if there is one. The first socket
+ * that connects is returned and the others are closed as their attempts finish.
+ */
+pub fn connect(
+    addrs: &[IpAddr],
+    port: u16,
+    delay: Duration,
+    timeout: Option<Duration>,
+) -> Result<TcpStream, Error> {
+    let (tx, rx) = mpsc::channel();
+    let mut pending = interleave(addrs).into_iter();
+    let mut running = 0;
+    let mut last_error = None;
+    loop {
+        // start the next attempt
+        if let Some(addr) = pending.next() {
+            let tx = tx.clone();
+            let addr = SocketAddr::new(addr, port);
+            thread::spawn(move || {
+                let result = match timeout {
+                    Some(timeout) => TcpStream::connect_timeout(&addr, timeout),
+                    None => TcpStream::connect(addr),
+                };
+                // nobody is waiting anymore if another attempt already succeeded
+                let _ = tx.send((addr, result));
+            });
+            running += 1;
+        } else if running == 0 {
+            let e = last_error.unwrap_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "no addresses to connect to")
+            });
+            return Err(e.into());
+        }
+        // wait for the attempts in flight until it's time to start another one
+        let result = if pending.len() > 0 {
+            match rx.recv_timeout(delay) {
+                Ok(result) => result,
+                Err(_) => continue,
+            }
+        } else {
+            rx.recv()
+                .expect("a sender is kept by every running attempt")
+        };
+        running -= 1;
+        match result {
+            (_, Ok(sock)) => return Ok(sock),
+            (_, Err(e)) => last_error = Some(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_interleave() {
+        let addrs: Vec<IpAddr> = ["::1", "::2", "::3", "10.0.0.1", "10.0.0.2"]
+            .iter()
+            .map(|a| a.parse().unwrap())
+            .collect();
+        let ordered: Vec<String> = interleave(&addrs).iter().map(|a| a.to_string()).collect();
+        assert_eq!(ordered, ["::1", "10.0.0.1", "::2", "10.0.0.2", "::3"]);
+        assert_eq!(interleave(&addrs[3..]), &addrs[3..]);
+    }
+
+    #[test]
+    fn test_connect_falls_back_to_other_addresses() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        // nothing listens on the IPv6 loopback or 127.0.0.2 at this port
+        let addrs: Vec<IpAddr> = ["::1", "127.0.0.2", "127.0.0.1"]
+            .iter()
+            .map(|a| a.parse().unwrap())
+            .collect();
+        let sock = connect(&addrs, port, Duration::from_secs(10), None).unwrap();
+        assert_eq!(sock.peer_addr().unwrap(), listener.local_addr().unwrap());
+
+        drop(listener);
+        assert!(connect(&addrs, port, Duration::from_secs(10), None).is_err());
+        assert!(connect(&[], port, Duration::from_secs(10), None).is_err());
+    }
+}
@@ -1,26 +1,36 @@
 #![allow(dead_code)]
 
+mod body;
+mod charset;
+mod chunked;
+
 use std::{
     collections::HashMap,
     fmt::Display,
-    io::{BufRead, BufReader, Read},
+    io::{self, BufRead, BufReader, Read},
     str::FromStr,
+    time::Duration,
 };
 
-#[derive(Debug, Clone)]
+pub use body::BodyReader;
+pub use chunked::ChunkedReader;
+
+use crate::{url::Url, Error};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Protocol {
     HTTP,
     HTTPS,
 }
 
 impl TryFrom<&str> for Protocol {
-    type Error = String;
+    type Error = Error;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
         match value {
             "http" => Ok(Protocol::HTTP),
             "https" => Ok(Protocol::HTTPS),
-            _ => Err("invalid protocol schema".to_string()),
+            _ => Err(Error::Url(format!("unsupported scheme: {value}"))),
         }
     }
 }
@@ -28,49 +38,110 @@ impl TryFrom<&str> for Protocol {
 #[derive(Debug, Clone)]
 pub struct HTTPRequest {
     request_line: RequestLine,
+    url: Url,
     headers: HTTPHeaders,
-    body: Option<String>,
+    body: Option<Vec<u8>>,
 }
 
 impl HTTPRequest {
-    pub fn new(method: Method, hostname: &str, url: &str, body: Option<String>) -> Self {
-        let request_line = RequestLine::new(method, url);
-        let headers: HTTPHeaders = vec![("Host".to_string(), hostname.to_string())].into();
+    pub fn new(method: Method, url: &Url, body: Option<Vec<u8>>) -> Self {
+        let request_line = RequestLine::new(method, &url.request_target());
+        let mut headers: HTTPHeaders = vec![("Host".to_string(), url.authority())].into();
+        if let Some(body) = &body {
+            headers.insert("Content-Length", &body.len().to_string());
+        }
         Self {
             request_line,
+            url: url.clone(),
             headers,
             body,
         }
     }
+
+    pub fn method(&self) -> &Method {
+        &self.request_line.method
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    pub fn headers(&self) -> &HTTPHeaders {
+        &self.headers
+    }
+
+    pub fn body(&self) -> Option<&[u8]> {
+        self.body.as_deref()
+    }
+
+    /// Adds a header field, appending to an existing field of the same name.
+    pub fn insert_header(&mut self, name: &str, value: &str) {
+        self.headers.insert(name, value);
+    }
+
+    pub fn remove_header(&mut self, name: &str) -> Option<String> {
+        self.headers.remove(name)
+    }
+
+    /// Serializes the request as it is sent on the wire.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = format!("{}{}\r\n", self.request_line, self.headers).into_bytes();
+        if let Some(body) = &self.body {
+            bytes.extend_from_slice(body);
+        }
+        bytes
+    }
 }
 
 impl Display for HTTPRequest {
+    /// Formats the request for logging, replacing a body that isn't UTF-8 with U+FFFD.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match &self.body {
-            Some(body) => write!(f, "{}{}\r\n{}\r\n", self.request_line, self.headers, body),
-            None => write!(f, "{}{}\r\n", self.request_line, self.headers),
-        }
+        write!(f, "{}", String::from_utf8_lossy(&self.to_bytes()))
     }
 }
 
 impl<R: Read> TryFrom<BufReader<R>> for HTTPRequest {
-    type Error = String;
+    type Error = Error;
 
-    fn try_from(reader: BufReader<R>) -> Result<Self, Self::Error> {
-        let mut iterator = reader.lines().map_while(Result::ok).peekable();
-        let request_line = iterator
-            .next()
-            .ok_or("failed to get request line")?
+    fn try_from(mut reader: BufReader<R>) -> Result<Self, Self::Error> {
+        let request_line =
+            read_line(&mut reader)?.ok_or_else(|| malformed("failed to get request line"))?;
+        let request_line: RequestLine = String::from_utf8(request_line)
+            .map_err(|e| malformed(e.to_string()))?
             .parse()?;
-        let headers = HTTPHeaders::new_from_string_iter(&mut iterator)?;
-        let body = if iterator.peek().is_some() {
-            Some(iterator.collect())
+        let headers = HTTPHeaders::new(&mut reader)?;
+        // a request has a body only if it announces one (RFC 9112 section 6.3)
+        let body = if headers
+            .get("Transfer-Encoding")
+            .is_some_and(|encoding| encoding.to_ascii_lowercase().ends_with("chunked"))
+        {
+            let mut body = vec![];
+            ChunkedReader::new(&mut reader)
+                .read_to_end(&mut body)
+                .map_err(body_error)?;
+            Some(body)
+        } else if let Some(length) = headers.get("Content-Length") {
+            let length: u64 = length
+                .trim()
+                .parse()
+                .map_err(|_| malformed(format!("invalid Content-Length header: {length}")))?;
+            let mut body = vec![];
+            reader.take(length).read_to_end(&mut body)?;
+            if (body.len() as u64) < length {
+                return Err(malformed("request body is shorter than its Content-Length"));
+            }
+            Some(body)
         } else {
             None
         };
+        let host = headers
+            .get("Host")
+            .ok_or_else(|| malformed("missing Host header"))?;
+        let url = Url::parse(&format!("http://{host}{}", request_line.request_target))?;
 
         Ok(HTTPRequest {
             request_line,
+            url,
             headers,
             body,
         })
@@ -105,21 +176,21 @@ impl Display for RequestLine {
 }
 
 impl FromStr for RequestLine {
-    type Err = String;
+    type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut iterator = s.split(' ');
         let method: Method = iterator
             .next()
-            .ok_or("failed to get HTTP method")?
+            .ok_or_else(|| malformed("failed to get HTTP method"))?
             .parse()?;
         let request_target = iterator
             .next()
-            .ok_or("failed to get request target")?
+            .ok_or_else(|| malformed("failed to get request target"))?
             .to_string();
         let http_version = iterator
             .next()
-            .ok_or("failed to get HTTP version")?
+            .ok_or_else(|| malformed("failed to get HTTP version"))?
             .to_string();
         Ok(RequestLine {
             method,
@@ -129,8 +200,8 @@ impl FromStr for RequestLine {
     }
 }
 
-#[derive(Debug, Clone)]
-struct HTTPHeaders(HashMap<String, String>);
+#[derive(Debug, Clone, Default)]
+pub struct HTTPHeaders(HashMap<String, String>);
 
 impl From<Vec<(String, String)>> for HTTPHeaders {
     fn from(value: Vec<(String, String)>) -> Self {
@@ -150,23 +221,24 @@ impl Display for HTTPHeaders {
 }
 
 impl HTTPHeaders {
-    pub fn new(iterator: &mut impl Iterator<Item = Vec<u8>>) -> Result<HTTPHeaders, String> {
-        let mut headers = HashMap::new();
-        for line in iterator {
-            if line.len() == 1 && line[0] == b'\r' {
+    /// Reads header lines from `reader` up to and including the empty line that ends the header section.
+    pub fn new(reader: &mut impl BufRead) -> Result<HTTPHeaders, Error> {
+        let mut headers = HTTPHeaders::default();
+        while let Some(line) = read_line(reader)? {
+            if line.is_empty() {
                 break;
             }
-            let line = String::from_utf8(line).map_err(|e| e.to_string())?;
+            let line = String::from_utf8(line).map_err(|e| malformed(e.to_string()))?;
             if let Some((key, value)) = line.split_once(':') {
-                headers.insert(key.to_string(), value.trim().to_string());
+                headers.insert(key.trim(), value.trim());
             }
         }
-        Ok(HTTPHeaders(headers))
+        Ok(headers)
     }
 
     pub fn new_from_string_iter(
         iterator: &mut impl Iterator<Item = String>,
-    ) -> Result<HTTPHeaders, String> {
+    ) -> Result<HTTPHeaders, Error> {
         let mut headers = HashMap::new();
         for line in iterator {
             if line.is_empty() {
@@ -178,9 +250,69 @@ impl HTTPHeaders {
         }
         Ok(HTTPHeaders(headers))
     }
+
+    /// Returns the value of the header field `name`, compared case-insensitively.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let key = self
+            .0
+            .keys()
+            .find(|k| k.eq_ignore_ascii_case(name))?
+            .clone();
+        self.0.remove(&key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Sets the header field `name`, appending to an existing value (case-insensitive) with ", ".
+    pub fn insert(&mut self, name: &str, value: &str) {
+        match self
+            .0
+            .iter_mut()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        {
+            Some((_, v)) => {
+                v.push_str(", ");
+                v.push_str(value);
+            }
+            None => {
+                self.0.insert(name.to_string(), value.to_string());
+            }
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
+/// The longest request, status, header or chunk line accepted, with its line break
+pub(crate) const MAX_LINE: u64 = 64 * 1024;
+
+/// Reads a single line terminated by LF, with the trailing CRLF (or LF) removed. Returns None at EOF.
+fn read_line(reader: &mut impl BufRead) -> Result<Option<Vec<u8>>, Error> {
+    let mut line = vec![];
+    let n = reader.take(MAX_LINE).read_until(b'\n', &mut line)?;
+    if n == 0 {
+        return Ok(None);
+    }
+    if n as u64 == MAX_LINE && line.last() != Some(&b'\n') {
+        return Err(malformed(format!("line is longer than {MAX_LINE} bytes")));
+    }
+    if line.last() == Some(&b'\n') {
+        line.pop();
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+    }
+    Ok(Some(line))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Method {
     GET,
     POST,
@@ -223,8 +355,15 @@ impl Display for Method {
 //     }
 // }
 
+impl Method {
+    /// Whether repeating the request has the same effect as sending it once (RFC 9110 section 9.2.2).
+    pub fn is_idempotent(&self) -> bool {
+        !matches!(self, Method::POST | Method::CONNECT)
+    }
+}
+
 impl FromStr for Method {
-    type Err = String;
+    type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
@@ -236,7 +375,7 @@ impl FromStr for Method {
             "OPTIONS" | "options" => Ok(Method::OPTIONS),
             "CONNECT" | "connect" => Ok(Method::CONNECT),
             "TRACE" | "trace" => Ok(Method::TRACE),
-            _ => Err(format!("invalid HTTP method: {s}")),
+            _ => Err(malformed(format!("invalid HTTP method: {s}"))),
         }
     }
 }
@@ -245,43 +384,208 @@ impl FromStr for Method {
 pub struct HTTPResponse {
     status_line: StatusLine,
     headers: HTTPHeaders,
-    pub body: Option<String>,
+    trailers: HTTPHeaders,
+    framing: BodyFraming,
+    redirects: Vec<Url>,
+    body: Vec<u8>,
 }
 
 impl<R: Read> TryFrom<BufReader<R>> for HTTPResponse {
-    type Error = String;
+    type Error = Error;
 
-    fn try_from(reader: BufReader<R>) -> Result<Self, Self::Error> {
-        let mut iterator = reader.split(b'\n').map_while(Result::ok).peekable();
-        let status_line: StatusLine = iterator
-            .next()
-            .ok_or("failed to get status line")?
-            .try_into()?;
-        let headers = HTTPHeaders::new(&mut iterator)?;
-        // println!("headers: {headers:?}");
-        let mut length = headers
-            .0
-            .get("Content-Length")
-            .ok_or("HTTP header doesn't have Content-Length header in it")?
-            .parse::<usize>()
-            .map_err(|e| e.to_string())?;
-        let mut body = vec![];
-        for mut data in iterator {
-            data.push(b'\n');
-            length -= data.len();
-            body.push(data);
-            if length <= 0 {
-                break;
-            }
+    fn try_from(mut reader: BufReader<R>) -> Result<Self, Self::Error> {
+        HTTPResponse::read(&mut reader, &Method::GET)
+    }
+}
+
+impl HTTPResponse {
+    /// Reads the response to a `method` request. Nothing after the end of the body is consumed,
+    /// so the connection can be used for the next request.
+    pub fn read(reader: &mut impl BufRead, method: &Method) -> Result<Self, Error> {
+        let mut response = HTTPResponse::read_head(reader, method)?;
+        let mut body = BodyReader::new(&mut *reader, response.framing);
+        body.read_to_end(&mut response.body).map_err(body_error)?;
+        if let Some(trailers) = body.trailers() {
+            response.trailers = trailers.clone();
         }
-        let body = body.into_iter().flatten().collect::<Vec<u8>>();
-        let body = String::from_utf8(body).map_err(|e| e.to_string())?;
+        Ok(response)
+    }
+
+    /// Reads the status line and headers of the response to a `method` request, leaving the body
+    /// to be read with a BodyReader.
+    pub(crate) fn read_head(reader: &mut impl BufRead, method: &Method) -> Result<Self, Error> {
+        let status_line: StatusLine = read_line(reader)?
+            .ok_or_else(|| malformed("failed to get status line"))?
+            .try_into()?;
+        let headers = HTTPHeaders::new(reader)?;
+        let framing = match method {
+            // the headers describe the body a GET request would have received
+            Method::HEAD => BodyFraming::Empty,
+            _ => BodyFraming::new(&status_line.status_code, &headers)?,
+        };
         Ok(HTTPResponse {
             status_line,
             headers,
-            body: Some(body),
+            trailers: HTTPHeaders::default(),
+            framing,
+            redirects: vec![],
+            body: vec![],
         })
     }
+
+    /// A reader for the body of a response returned by `read_head`.
+    pub(crate) fn body_reader<R: BufRead>(&self, reader: R) -> BodyReader<R> {
+        BodyReader::new(reader, self.framing)
+    }
+
+    pub(crate) fn set_trailers(&mut self, trailers: HTTPHeaders) {
+        self.trailers = trailers;
+    }
+
+    pub(crate) fn set_body(&mut self, body: Vec<u8>) {
+        self.body = body;
+    }
+
+    /// The status code, such as 200 or 404.
+    pub fn status(&self) -> u16 {
+        self.status_line.status_code.0
+    }
+
+    /// The reason phrase of the status line, which may be empty.
+    pub fn reason(&self) -> &str {
+        &self.status_line.status_text
+    }
+
+    /// Whether the status code is in the 2xx range.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status())
+    }
+
+    /// The HTTP version of the status line, such as "HTTP/1.1".
+    pub fn version(&self) -> &str {
+        &self.status_line.http_version.0
+    }
+
+    pub fn headers(&self) -> &HTTPHeaders {
+        &self.headers
+    }
+
+    /// Header fields sent after a chunked body.
+    pub fn trailers(&self) -> &HTTPHeaders {
+        &self.trailers
+    }
+
+    /// The body with any transfer coding removed. Empty when the response has no body.
+    pub fn bytes(&self) -> &[u8] {
+        &self.body
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.body
+    }
+
+    /// Decodes the body with the charset of the Content-Type header, or UTF-8 when it names none.
+    pub fn text(&self) -> Result<String, Error> {
+        let charset = self
+            .headers
+            .get("Content-Type")
+            .and_then(charset::from_content_type)
+            .unwrap_or("utf-8");
+        charset::decode(&self.body, charset)
+    }
+
+    /// URLs that answered with a redirect before this response, in the order they were requested.
+    pub fn redirects(&self) -> &[Url] {
+        &self.redirects
+    }
+
+    pub(crate) fn set_redirects(&mut self, redirects: Vec<Url>) {
+        self.redirects = redirects;
+    }
+
+    /// The Location header of a 301, 302, 303, 307 or 308 response.
+    pub(crate) fn redirect_location(&self) -> Option<&str> {
+        match self.status() {
+            301 | 302 | 303 | 307 | 308 => self.headers.get("Location"),
+            _ => None,
+        }
+    }
+
+    /// Whether the connection can carry another request after this response (RFC 9112 section 9.3).
+    pub(crate) fn is_keep_alive(&self) -> bool {
+        if self.framing == BodyFraming::UntilClose {
+            return false;
+        }
+        let has_option = |option: &str| {
+            self.headers.get("Connection").is_some_and(|value| {
+                value
+                    .split(',')
+                    .any(|token| token.trim().eq_ignore_ascii_case(option))
+            })
+        };
+        if has_option("close") {
+            return false;
+        }
+        match self.status_line.http_version.0.as_str() {
+            "HTTP/1.0" => has_option("keep-alive"),
+            _ => true,
+        }
+    }
+
+    /// The idle timeout announced by the server in a `Keep-Alive: timeout=n` header.
+    pub(crate) fn keep_alive_timeout(&self) -> Option<Duration> {
+        self.headers
+            .get("Keep-Alive")?
+            .split(',')
+            .filter_map(|param| param.split_once('='))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("timeout"))
+            .and_then(|(_, value)| value.trim().parse().ok())
+            .map(Duration::from_secs)
+    }
+}
+
+/**
+ * How the length of a response body is determined (RFC 9112 section 6.3)
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BodyFraming {
+    Empty,              // 1xx, 204 and 304 responses never have a body
+    Chunked,            // Transfer-Encoding: chunked
+    ContentLength(u64), // Content-Length: n
+    UntilClose,         // neither header: the body ends when the server closes the connection
+}
+
+impl BodyFraming {
+    fn new(status_code: &StatusCode, headers: &HTTPHeaders) -> Result<Self, Error> {
+        if (100..200).contains(&status_code.0) || status_code.0 == 204 || status_code.0 == 304 {
+            return Ok(BodyFraming::Empty);
+        }
+        if let Some(encoding) = headers.get("Transfer-Encoding") {
+            let last = encoding.rsplit(',').next().unwrap_or("").trim();
+            return if last.eq_ignore_ascii_case("chunked") {
+                Ok(BodyFraming::Chunked)
+            } else {
+                Ok(BodyFraming::UntilClose)
+            };
+        }
+        match headers.get("Content-Length") {
+            Some(length) => {
+                // duplicated fields are folded into "n, n" by HTTPHeaders::insert
+                let mut values = length.split(',').map(|v| v.trim().parse::<u64>());
+                let first = values
+                    .next()
+                    .ok_or_else(|| malformed("empty Content-Length header"))?
+                    .map_err(|e| malformed(format!("invalid Content-Length header: {e}")))?;
+                if values.any(|v| v != Ok(first)) {
+                    return Err(malformed(format!(
+                        "conflicting Content-Length header: {length}"
+                    )));
+                }
+                Ok(BodyFraming::ContentLength(first))
+            }
+            None => Ok(BodyFraming::UntilClose),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -291,20 +595,22 @@ pub struct StatusLine {
     status_text: String,
 }
 impl TryFrom<Vec<u8>> for StatusLine {
-    type Error = String;
+    type Error = Error;
 
     fn try_from(v: Vec<u8>) -> Result<Self, Self::Error> {
-        let mut iterator = v.split(|b| *b == b' ');
+        let mut iterator = v.splitn(3, |b| *b == b' ');
         let http_version = iterator
             .next()
-            .ok_or("failed to get HTTP version")?
+            .ok_or_else(|| malformed("failed to get HTTP version"))?
             .try_into()?;
         let status_code = iterator
             .next()
-            .ok_or("no status code to be parsed")?
+            .ok_or_else(|| malformed("no status code to be parsed"))?
             .try_into()?;
-        let status_text = iterator.next().ok_or("failed to get status text")?;
-        let status_text = String::from_utf8(status_text.to_vec()).map_err(|e| e.to_string())?;
+        // the reason phrase may contain spaces or be empty
+        let status_text = iterator.next().unwrap_or_default();
+        let status_text =
+            String::from_utf8(status_text.to_vec()).map_err(|e| malformed(e.to_string()))?;
         Ok(StatusLine {
             http_version,
             status_code,
@@ -314,22 +620,19 @@ impl TryFrom<Vec<u8>> for StatusLine {
 }
 
 impl FromStr for StatusLine {
-    type Err = String;
+    type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut iterator = s.split(' ');
+        let mut iterator = s.splitn(3, ' ');
         let http_version: HTTPVersion = iterator
             .next()
-            .ok_or("failed to get HTTP version")?
+            .ok_or_else(|| malformed("failed to get HTTP version"))?
             .parse()?;
         let status_code: StatusCode = iterator
             .next()
-            .ok_or("no status code to be parsed")?
+            .ok_or_else(|| malformed("no status code to be parsed"))?
             .parse()?;
-        let status_text = iterator
-            .next()
-            .ok_or("failed to get status text")?
-            .to_string();
+        let status_text = iterator.next().unwrap_or_default().to_string();
         Ok(StatusLine {
             http_version,
             status_code,
@@ -342,26 +645,26 @@ impl FromStr for StatusLine {
 struct HTTPVersion(String);
 
 impl TryFrom<&[u8]> for HTTPVersion {
-    type Error = String;
+    type Error = Error;
 
     fn try_from(v: &[u8]) -> Result<Self, Self::Error> {
-        let s = String::from_utf8(v.to_vec()).map_err(|e| e.to_string())?;
+        let s = String::from_utf8(v.to_vec()).map_err(|e| malformed(e.to_string()))?;
         if s.starts_with("HTTP/") {
             Ok(HTTPVersion(s))
         } else {
-            Err(format!("invalid HTTP Version: {}", s))
+            Err(malformed(format!("invalid HTTP Version: {}", s)))
         }
     }
 }
 
 impl FromStr for HTTPVersion {
-    type Err = String;
+    type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if s.starts_with("HTTP/") {
             Ok(HTTPVersion(s.to_string()))
         } else {
-            Err(format!("invalid HTTP Version: {}", s))
+            Err(malformed(format!("invalid HTTP Version: {}", s)))
         }
     }
 }
@@ -370,26 +673,36 @@ impl FromStr for HTTPVersion {
 struct StatusCode(u16);
 
 impl TryFrom<&[u8]> for StatusCode {
-    type Error = String;
+    type Error = Error;
 
     fn try_from(v: &[u8]) -> Result<Self, Self::Error> {
         String::from_utf8(v.to_vec())
-            .map_err(|e| e.to_string())?
-            .parse::<u16>()
-            .map_err(|e| e.to_string())
-            .map(StatusCode)
+            .map_err(|e| malformed(e.to_string()))?
+            .parse()
     }
 }
 
 impl FromStr for StatusCode {
-    type Err = String;
+    type Err = Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         s.parse::<u16>()
-            .or(Err(format!("error parsing status code: {}", s)))
+            .or(Err(malformed(format!("error parsing status code: {}", s))))
             .map(StatusCode)
     }
 }
 
+fn malformed(message: impl Into<String>) -> Error {
+    Error::Http(message.into())
+}
+
+/// The chunked decoder reports malformed chunks as InvalidData, socket errors pass through.
+pub(crate) fn body_error(e: io::Error) -> Error {
+    match Error::from(e) {
+        Error::Io(e) if e.kind() == io::ErrorKind::InvalidData => Error::Http(e.to_string()),
+        e => e,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -415,25 +728,169 @@ mod tests {
 
     #[test]
     fn test_http_request_to_string() {
-        let req = HTTPRequest::new(Method::GET, "example.com", "/hello", None);
+        let url = Url::parse("http://example.com/hello").unwrap();
+        let req = HTTPRequest::new(Method::GET, &url, None);
         assert_eq!(
             req.to_string(),
             "GET /hello HTTP/1.1\r\nHost: example.com\r\n\r\n".to_string()
         );
+        let url = Url::parse("http://[::1]:8080/a b?q#fragment").unwrap();
+        let req = HTTPRequest::new(Method::GET, &url, None);
+        assert_eq!(
+            req.to_string(),
+            "GET /a%20b?q HTTP/1.1\r\nHost: [::1]:8080\r\n\r\n".to_string()
+        );
+    }
+
+    #[test]
+    fn test_http_request_with_body_to_string() {
+        let url = Url::parse("http://example.com").unwrap();
+        let req = HTTPRequest::new(Method::POST, &url, Some(b"hello".to_vec()));
+        let req = req.to_string();
+        assert!(req.starts_with("POST / HTTP/1.1\r\n"));
+        assert!(req.contains("Content-Length: 5\r\n"));
+        assert!(req.ends_with("\r\n\r\nhello"));
+    }
+
+    #[test]
+    fn test_http_request_with_binary_body() {
+        let url = Url::parse("http://example.com/upload").unwrap();
+        let body = b"\x89PNG\r\n\x1a\n\x00\xff".to_vec();
+        let req = HTTPRequest::new(Method::PUT, &url, Some(body.clone()));
+        let bytes = req.to_bytes();
+        assert!(bytes.ends_with(&body));
+
+        let parsed = HTTPRequest::try_from(BufReader::new(&bytes[..])).unwrap();
+        assert_eq!(parsed.method(), &Method::PUT);
+        assert_eq!(parsed.url().to_string(), "http://example.com/upload");
+        assert_eq!(parsed.body(), Some(&body[..]));
     }
 
     #[test]
     fn test_http_version_from_vecu8() {
         let v: &[u8] = b"HTTP/1.1";
         assert_eq!(
-            Ok(HTTPVersion(String::from("HTTP/1.1"))),
-            HTTPVersion::try_from(v)
+            HTTPVersion(String::from("HTTP/1.1")),
+            HTTPVersion::try_from(v).unwrap()
         );
     }
 
     #[test]
     fn test_status_code_from_slice_u8() {
         let slc: &[u8] = b"200";
-        assert_eq!(StatusCode::try_from(slc), Ok(StatusCode(200)));
+        assert_eq!(StatusCode::try_from(slc).unwrap(), StatusCode(200));
+    }
+
+    #[test]
+    fn test_status_line_from_vecu8() {
+        let status_line = StatusLine::try_from(b"HTTP/1.1 404 Not Found".to_vec()).unwrap();
+        assert_eq!(
+            status_line.http_version,
+            HTTPVersion("HTTP/1.1".to_string())
+        );
+        assert_eq!(status_line.status_code, StatusCode(404));
+        assert_eq!(status_line.status_text, "Not Found");
+    }
+
+    fn parse_response(payload: &[u8]) -> Result<HTTPResponse, Error> {
+        HTTPResponse::try_from(BufReader::new(payload))
+    }
+
+    #[test]
+    fn test_http_response_with_content_length() {
+        let response =
+            parse_response(b"HTTP/1.1 200 OK\r\nContent-Length: 12\r\n\r\nhello\nworld\nextra")
+                .unwrap();
+        assert_eq!(response.bytes(), b"hello\nworld\n");
+        assert!(parse_response(b"HTTP/1.1 200 OK\r\nContent-Length: 12\r\n\r\nhello").is_err());
+    }
+
+    #[test]
+    fn test_http_response_with_chunked_body() {
+        let response = parse_response(
+            b"HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\nTrailer: Expires\r\n\r\n\
+              5\r\nhello\r\n7;ext=1\r\n, world\r\n0\r\nExpires: never\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(response.bytes(), b"hello, world");
+        assert_eq!(response.trailers().get("Expires"), Some("never"));
+    }
+
+    #[test]
+    fn test_http_response_read_until_close() {
+        let response =
+            parse_response(b"HTTP/1.0 200 OK\r\nServer: test\r\n\r\nhello\nworld").unwrap();
+        assert_eq!(response.bytes(), b"hello\nworld");
+    }
+
+    #[test]
+    fn test_http_response_with_long_line() {
+        let mut header = b"HTTP/1.1 200 OK\r\nX-Long: ".to_vec();
+        header.resize(MAX_LINE as usize * 2, b'a');
+        assert!(parse_response(&header).is_err());
+    }
+
+    #[test]
+    fn test_http_response_without_body() {
+        let response = parse_response(b"HTTP/1.1 204 No Content\r\n\r\n").unwrap();
+        assert!(response.bytes().is_empty());
+    }
+
+    #[test]
+    fn test_http_response_to_head_request() {
+        let mut reader: &[u8] =
+            b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nHTTP/1.1 204 No Content\r\n\r\n";
+        let response = HTTPResponse::read(&mut reader, &Method::HEAD).unwrap();
+        assert!(response.bytes().is_empty());
+        assert!(reader.starts_with(b"HTTP/1.1 204"));
+    }
+
+    #[test]
+    fn test_http_response_keep_alive() {
+        let response = parse_response(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nKeep-Alive: timeout=5, max=100\r\n\r\n",
+        )
+        .unwrap();
+        assert!(response.is_keep_alive());
+        assert_eq!(response.keep_alive_timeout(), Some(Duration::from_secs(5)));
+        let response =
+            parse_response(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: Close\r\n\r\n")
+                .unwrap();
+        assert!(!response.is_keep_alive());
+        let response = parse_response(b"HTTP/1.0 200 OK\r\nContent-Length: 0\r\n\r\n").unwrap();
+        assert!(!response.is_keep_alive());
+        let response = parse_response(
+            b"HTTP/1.0 200 OK\r\nContent-Length: 0\r\nConnection: keep-alive\r\n\r\n",
+        )
+        .unwrap();
+        assert!(response.is_keep_alive());
+        let response = parse_response(b"HTTP/1.1 200 OK\r\n\r\nuntil close").unwrap();
+        assert!(!response.is_keep_alive());
+    }
+
+    #[test]
+    fn test_http_response_with_binary_body() {
+        let body = b"\x89PNG\r\n\x1a\n\x00\xff\n\n";
+        let mut payload = b"HTTP/1.1 200 OK\r\nContent-Length: 12\r\n\r\n".to_vec();
+        payload.extend_from_slice(body);
+        let response = parse_response(&payload).unwrap();
+        assert_eq!(response.bytes(), body);
+        assert!(response.text().is_err());
+    }
+
+    #[test]
+    fn test_http_response_accessors() {
+        let response = parse_response(
+            b"HTTP/1.1 404 Not Found\r\nContent-Type: text/plain; charset=ISO-8859-1\r\n\
+              Content-Length: 4\r\n\r\ncaf\xe9",
+        )
+        .unwrap();
+        assert_eq!(response.status(), 404);
+        assert_eq!(response.reason(), "Not Found");
+        assert!(!response.is_success());
+        assert_eq!(response.version(), "HTTP/1.1");
+        assert_eq!(response.headers().get("content-length"), Some("4"));
+        assert_eq!(response.bytes(), b"caf\xe9");
+        assert_eq!(response.text().unwrap(), "caf\u{e9}");
     }
 }
@@ -0,0 +1,114 @@
+use std::io::{self, BufRead, Read};
+
+use super::{BodyFraming, ChunkedReader, HTTPHeaders};
+
+/**
+ * Reader for a message body that stops at the end of the body, so the connection underneath
+ * can carry the next message
+ */
+#[derive(Debug)]
+pub struct BodyReader<R> {
+    inner: Inner<R>,
+}
+
+#[derive(Debug)]
+enum Inner<R> {
+    Length { reader: R, remaining: u64 },
+    Chunked(ChunkedReader<R>),
+    UntilClose { reader: R, done: bool },
+}
+
+impl<R: BufRead> BodyReader<R> {
+    pub(super) fn new(reader: R, framing: BodyFraming) -> Self {
+        let inner = match framing {
+            BodyFraming::Empty => Inner::Length {
+                reader,
+                remaining: 0,
+            },
+            BodyFraming::ContentLength(remaining) => Inner::Length { reader, remaining },
+            BodyFraming::Chunked => Inner::Chunked(ChunkedReader::new(reader)),
+            BodyFraming::UntilClose => Inner::UntilClose {
+                reader,
+                done: false,
+            },
+        };
+        Self { inner }
+    }
+
+    /// Whether the whole body has been read.
+    pub fn is_done(&self) -> bool {
+        match &self.inner {
+            Inner::Length { remaining, .. } => *remaining == 0,
+            Inner::Chunked(decoder) => decoder.is_done(),
+            Inner::UntilClose { done, .. } => *done,
+        }
+    }
+
+    /// Trailer fields of a chunked body. Only complete once the body has been read to the end.
+    pub fn trailers(&self) -> Option<&HTTPHeaders> {
+        match &self.inner {
+            Inner::Chunked(decoder) => Some(decoder.trailers()),
+            _ => None,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        match self.inner {
+            Inner::Length { reader, .. } | Inner::UntilClose { reader, .. } => reader,
+            Inner::Chunked(decoder) => decoder.into_inner(),
+        }
+    }
+}
+
+impl<R: BufRead> Read for BodyReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.inner {
+            Inner::Length { reader, remaining } => {
+                if *remaining == 0 || buf.is_empty() {
+                    return Ok(0);
+                }
+                let max = buf.len().min((*remaining).try_into().unwrap_or(usize::MAX));
+                let n = reader.read(&mut buf[..max])?;
+                if n == 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        format!("connection closed with {remaining} bytes of body left"),
+                    ));
+                }
+                *remaining -= n as u64;
+                Ok(n)
+            }
+            Inner::Chunked(decoder) => decoder.read(buf),
+            Inner::UntilClose { reader, done } => {
+                let n = reader.read(buf)?;
+                *done |= n == 0 && !buf.is_empty();
+                Ok(n)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_body_reader_stops_at_end_of_body() {
+        let mut payload: &[u8] = b"hello, worldHTTP/1.1 200 OK";
+        let mut body = BodyReader::new(&mut payload, BodyFraming::ContentLength(12));
+        assert!(!body.is_done());
+        let mut buf = [0u8; 5];
+        body.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello");
+        let mut rest = vec![];
+        body.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b", world");
+        assert!(body.is_done());
+        assert_eq!(payload, b"HTTP/1.1 200 OK");
+
+        let mut short: &[u8] = b"hello";
+        let mut body = BodyReader::new(&mut short, BodyFraming::ContentLength(12));
+        let err = body.read_to_end(&mut vec![]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
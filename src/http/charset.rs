@@ -0,0 +1,89 @@
+use crate::Error;
+
+/// Returns the charset parameter of a Content-Type value such as `text/html; charset="utf-8"`.
+pub fn from_content_type(content_type: &str) -> Option<&str> {
+    content_type
+        .split(';')
+        .skip(1)
+        .filter_map(|param| param.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+        .map(|(_, value)| value.trim().trim_matches('"'))
+}
+
+/**
+ * Decodes text in one of the charsets commonly used on the web
+ *
+ * UTF-8, US-ASCII, ISO-8859-1 and UTF-16 are supported. ISO-8859-1 is decoded as windows-1252,
+ * as browsers do (WHATWG Encoding Standard).
+ */
+pub fn decode(bytes: &[u8], charset: &str) -> Result<String, Error> {
+    match charset.to_ascii_lowercase().as_str() {
+        "utf-8" | "utf8" => {
+            String::from_utf8(bytes.to_vec()).map_err(|e| Error::Http(e.to_string()))
+        }
+        "us-ascii" | "ascii" if bytes.is_ascii() => Ok(bytes.iter().map(|b| *b as char).collect()),
+        "us-ascii" | "ascii" => Err(Error::Http("invalid US-ASCII text".to_string())),
+        "iso-8859-1" | "latin1" | "windows-1252" | "cp1252" => {
+            Ok(bytes.iter().map(|b| windows_1252(*b)).collect())
+        }
+        "utf-16le" => utf16(bytes, u16::from_le_bytes),
+        "utf-16be" => utf16(bytes, u16::from_be_bytes),
+        // without a byte order mark UTF-16 is big endian (RFC 2781 section 4.3)
+        "utf-16" => match bytes {
+            [0xff, 0xfe, rest @ ..] => utf16(rest, u16::from_le_bytes),
+            [0xfe, 0xff, rest @ ..] => utf16(rest, u16::from_be_bytes),
+            _ => utf16(bytes, u16::from_be_bytes),
+        },
+        _ => Err(Error::Http(format!("unsupported charset: {charset}"))),
+    }
+}
+
+fn utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> Result<String, Error> {
+    if !bytes.len().is_multiple_of(2) {
+        return Err(Error::Http("UTF-16 text has an odd length".to_string()));
+    }
+    let units = bytes.chunks_exact(2).map(|c| from_bytes([c[0], c[1]]));
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .map_err(|e| Error::Http(e.to_string()))
+}
+
+/// Maps a windows-1252 byte to its character. 0x80 to 0x9f are mostly typographic characters.
+fn windows_1252(b: u8) -> char {
+    const HIGH: [char; 32] = [
+        '\u{20ac}', '\u{81}', '\u{201a}', '\u{192}', '\u{201e}', '\u{2026}', '\u{2020}',
+        '\u{2021}', '\u{2c6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8d}', '\u{17d}',
+        '\u{8f}', '\u{90}', '\u{2018}', '\u{2019}', '\u{201c}', '\u{201d}', '\u{2022}', '\u{2013}',
+        '\u{2014}', '\u{2dc}', '\u{2122}', '\u{161}', '\u{203a}', '\u{153}', '\u{9d}', '\u{17e}',
+        '\u{178}',
+    ];
+    match b {
+        0x80..=0x9f => HIGH[(b - 0x80) as usize],
+        _ => b as char,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_charset_from_content_type() {
+        assert_eq!(
+            from_content_type("text/html; Charset=\"UTF-8\""),
+            Some("UTF-8")
+        );
+        assert_eq!(from_content_type("application/json"), None);
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode("héllo".as_bytes(), "UTF-8").unwrap(), "héllo");
+        assert!(decode(b"\xff", "utf-8").is_err());
+        assert!(decode(b"\xe9", "us-ascii").is_err());
+        assert_eq!(decode(b"\xe9\x80", "iso-8859-1").unwrap(), "é€");
+        assert_eq!(decode(b"\xff\xfeh\x00i\x00", "utf-16").unwrap(), "hi");
+        assert_eq!(decode(b"\x00h\x00i", "UTF-16BE").unwrap(), "hi");
+        assert!(decode(b"hi", "shift_jis").is_err());
+    }
+}
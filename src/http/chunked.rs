@@ -0,0 +1,214 @@
+use std::io::{self, BufRead, Read};
+
+use super::{HTTPHeaders, MAX_LINE};
+
+/**
+ * Decoder for the chunked transfer coding (RFC 9112 section 7.1)
+ *
+ *   chunked-body   = *chunk
+ *                    last-chunk
+ *                    trailer-section
+ *                    CRLF
+ *   chunk          = chunk-size [ chunk-ext ] CRLF
+ *                    chunk-data CRLF
+ *   last-chunk     = 1*("0") [ chunk-ext ] CRLF
+ */
+#[derive(Debug)]
+pub struct ChunkedReader<R> {
+    inner: R,
+    state: ChunkState,
+    trailers: HTTPHeaders,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChunkState {
+    Size,      // expecting a chunk-size line
+    Data(u64), // remaining bytes in the current chunk
+    DataEnd,   // expecting the CRLF that terminates chunk-data
+    Trailers,  // reading the trailer section after the last chunk
+    Done,
+}
+
+impl<R: BufRead> ChunkedReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            state: ChunkState::Size,
+            trailers: HTTPHeaders::default(),
+        }
+    }
+
+    /// Trailer fields sent after the last chunk. Only complete once the body has been read to the end.
+    pub fn trailers(&self) -> &HTTPHeaders {
+        &self.trailers
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.state == ChunkState::Done
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn read_line(&mut self) -> io::Result<Vec<u8>> {
+        let mut line = vec![];
+        let n = (&mut self.inner)
+            .take(MAX_LINE)
+            .read_until(b'\n', &mut line)?;
+        if n as u64 == MAX_LINE && line.last() != Some(&b'\n') {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("chunk line is longer than {MAX_LINE} bytes"),
+            ));
+        }
+        if n == 0 || line.last() != Some(&b'\n') {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "connection closed in the middle of chunked body",
+            ));
+        }
+        line.pop();
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        Ok(line)
+    }
+
+    fn read_chunk_size(&mut self) -> io::Result<u64> {
+        let line = self.read_line()?;
+        parse_chunk_size(&line)
+    }
+
+    fn read_trailers(&mut self) -> io::Result<()> {
+        loop {
+            let line = self.read_line()?;
+            if line.is_empty() {
+                return Ok(());
+            }
+            let line = String::from_utf8(line).map_err(invalid_data)?;
+            if let Some((key, value)) = line.split_once(':') {
+                self.trailers.insert(key.trim(), value.trim());
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Read for ChunkedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.state {
+                ChunkState::Size => {
+                    self.state = match self.read_chunk_size()? {
+                        0 => ChunkState::Trailers,
+                        n => ChunkState::Data(n),
+                    };
+                }
+                ChunkState::Data(remaining) => {
+                    if buf.is_empty() {
+                        return Ok(0);
+                    }
+                    let max = buf.len().min(remaining.try_into().unwrap_or(usize::MAX));
+                    let n = self.inner.read(&mut buf[..max])?;
+                    if n == 0 {
+                        return Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "connection closed in the middle of a chunk",
+                        ));
+                    }
+                    let remaining = remaining - n as u64;
+                    self.state = if remaining == 0 {
+                        ChunkState::DataEnd
+                    } else {
+                        ChunkState::Data(remaining)
+                    };
+                    return Ok(n);
+                }
+                ChunkState::DataEnd => {
+                    if !self.read_line()?.is_empty() {
+                        return Err(invalid_data("chunk data is not followed by CRLF"));
+                    }
+                    self.state = ChunkState::Size;
+                }
+                ChunkState::Trailers => {
+                    self.read_trailers()?;
+                    self.state = ChunkState::Done;
+                }
+                ChunkState::Done => return Ok(0),
+            }
+        }
+    }
+}
+
+/// Parses a chunk-size line, ignoring any chunk extensions after ';'.
+fn parse_chunk_size(line: &[u8]) -> io::Result<u64> {
+    let line = std::str::from_utf8(line).map_err(invalid_data)?;
+    let size = match line.split_once(';') {
+        Some((size, _extensions)) => size,
+        None => line,
+    }
+    .trim_matches(|c| c == ' ' || c == '\t');
+    if size.is_empty() || !size.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(invalid_data(format!("invalid chunk size: {line}")));
+    }
+    u64::from_str_radix(size, 16).map_err(invalid_data)
+}
+
+fn invalid_data<E>(e: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(payload: &[u8]) -> io::Result<(Vec<u8>, HTTPHeaders)> {
+        let mut reader = ChunkedReader::new(payload);
+        let mut body = vec![];
+        reader.read_to_end(&mut body)?;
+        Ok((body, reader.trailers().clone()))
+    }
+
+    #[test]
+    fn test_decode_chunks() {
+        let (body, trailers) =
+            decode(b"4\r\nWiki\r\n6\r\npedia \r\nE\r\nin \r\n\r\nchunks.\r\n0\r\n\r\n").unwrap();
+        assert_eq!(body, b"Wikipedia in \r\n\r\nchunks.");
+        assert!(trailers.0.is_empty());
+    }
+
+    #[test]
+    fn test_decode_chunk_extensions_and_trailers() {
+        let payload = b"5;name=value;foo=\"a;b\"\r\nhello\r\n000;last\r\nExpires: never\r\nX-Checksum: abc\r\n\r\n";
+        let (body, trailers) = decode(payload).unwrap();
+        assert_eq!(body, b"hello");
+        assert_eq!(trailers.get("expires"), Some("never"));
+        assert_eq!(trailers.get("X-Checksum"), Some("abc"));
+    }
+
+    #[test]
+    fn test_decode_stops_after_last_chunk() {
+        let payload: &[u8] = b"3\r\nabc\r\n0\r\n\r\nHTTP/1.1 200 OK\r\n";
+        let mut reader = ChunkedReader::new(payload);
+        let mut body = vec![];
+        reader.read_to_end(&mut body).unwrap();
+        assert_eq!(body, b"abc");
+        assert!(reader.is_done());
+        assert_eq!(reader.into_inner(), b"HTTP/1.1 200 OK\r\n");
+    }
+
+    #[test]
+    fn test_decode_invalid_chunks() {
+        assert!(decode(b"zz\r\nhello\r\n0\r\n\r\n").is_err());
+        assert!(decode(b"5\r\nhelloX\r\n0\r\n\r\n").is_err());
+        assert!(decode(b"5\r\nhel").is_err());
+        assert!(decode(b"5\r\nhello\r\n").is_err());
+        let mut payload = b"5;ext=".to_vec();
+        payload.resize(MAX_LINE as usize * 2, b'a');
+        let error = decode(&payload).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
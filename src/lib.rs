@@ -1,75 +1,506 @@
+mod crypto;
 pub mod dns;
+mod error;
+mod happy_eyeballs;
 pub mod http;
+mod pool;
+pub mod redirect;
+mod streaming;
+pub mod tls;
+mod transport;
+pub mod url;
 
 use std::{
     fs::File,
-    io::{BufReader, Read, Write},
+    io::{self, BufRead, BufReader, Read, Write},
     net::TcpStream,
+    sync::Arc,
+    time::Duration,
 };
 
-use crate::http::{HTTPRequest, HTTPResponse, Method, Protocol};
+use crate::{
+    happy_eyeballs::CONNECTION_ATTEMPT_DELAY,
+    http::{HTTPRequest, HTTPResponse, Method, Protocol},
+    pool::{Connection, Pool, PoolKey},
+    redirect::RedirectPolicy,
+    tls::ServerName,
+    transport::Transport,
+};
+
+pub use crate::{
+    error::Error,
+    streaming::StreamingResponse,
+    url::{Host, Url},
+};
 
 pub struct Client {
     dns_client: dns::Resolver,
+    tls_config: tls::ClientConfig,
+    pool: Arc<Pool>,
+    redirect_policy: RedirectPolicy,
+    srv_lookup: bool,
 }
 
 impl Client {
+    /// Creates a client trusting the CA certificates of the operating system for HTTPS.
+    pub fn new() -> Self {
+        ClientBuilder::new().build()
+    }
+
+    /// Creates a client authenticating HTTPS servers with the given TLS configuration.
+    pub fn with_tls_config(tls_config: tls::ClientConfig) -> Self {
+        ClientBuilder::new().tls_config(tls_config).build()
+    }
+
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/**
+ * Builder for a Client with non-default settings
+ */
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    tls_config: Option<tls::ClientConfig>,
+    pool_idle_timeout: Duration,
+    pool_max_idle_per_host: usize,
+    redirect_policy: RedirectPolicy,
+    resolver: Option<dns::Resolver>,
+    srv_lookup: bool,
+}
+
+impl ClientBuilder {
     pub fn new() -> Self {
         Self {
-            dns_client: dns::Resolver::new(None, None),
+            tls_config: None,
+            resolver: None,
+            pool_idle_timeout: Duration::from_secs(90),
+            pool_max_idle_per_host: 8,
+            redirect_policy: RedirectPolicy::default(),
+            srv_lookup: false,
+        }
+    }
+
+    /// Sets the TLS configuration used for HTTPS. Defaults to the CA certificates of the operating system.
+    pub fn tls_config(mut self, tls_config: tls::ClientConfig) -> Self {
+        self.tls_config = Some(tls_config);
+        self
+    }
+
+    /// How long an idle connection is kept for reuse. A shorter `Keep-Alive: timeout=` from the server wins.
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.pool_idle_timeout = timeout;
+        self
+    }
+
+    /// Maximum number of idle connections kept per scheme, host and port. 0 disables keep-alive.
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.pool_max_idle_per_host = max;
+        self
+    }
+
+    /// Sets how redirects are followed. Defaults to following up to 10 redirects.
+    pub fn redirect_policy(mut self, policy: RedirectPolicy) -> Self {
+        self.redirect_policy = policy;
+        self
+    }
+
+    /// Sets the resolver for host names. Defaults to the configuration in /etc/resolv.conf.
+    pub fn resolver(mut self, resolver: dns::Resolver) -> Self {
+        self.resolver = Some(resolver);
+        self
+    }
+
+    /// Looks up the servers of URLs without a port in SRV records such as
+    /// "_http._tcp.example.com", and tries them in turn. Hosts without SRV records are connected
+    /// to directly. Disabled by default.
+    pub fn srv_lookup(mut self, enabled: bool) -> Self {
+        self.srv_lookup = enabled;
+        self
+    }
+
+    pub fn build(self) -> Client {
+        Client {
+            dns_client: self.resolver.unwrap_or_else(dns::Resolver::from_system),
+            tls_config: self.tls_config.unwrap_or_default(),
+            pool: Arc::new(Pool::new(
+                self.pool_idle_timeout,
+                self.pool_max_idle_per_host,
+            )),
+            redirect_policy: self.redirect_policy,
+            srv_lookup: self.srv_lookup,
         }
     }
 }
 
-impl Default for Client {
+impl Default for ClientBuilder {
     fn default() -> Self {
         Self::new()
     }
 }
 
 impl Client {
+    /// Sends a request and returns the response whatever its status, following redirects.
     pub fn perform(
         &self,
         method: Method,
         url: String,
-        body: Option<String>,
-    ) -> Result<String, String> {
-        let (protocol, url) = url.split_once("://").unwrap_or(("http", &url));
-        let (hostname, url) = match url.split_once('/') {
-            Some((hostname, url)) => (hostname, format!("/{url}")),
-            None => (url, "/".to_string()),
+        body: Option<Vec<u8>>,
+    ) -> Result<HTTPResponse, Error> {
+        self.execute(HTTPRequest::new(method, &parse_url(&url)?, body))
+    }
+
+    /// Like `perform`, but returns as soon as the headers have arrived and reads the body on demand.
+    pub fn perform_streaming(
+        &self,
+        method: Method,
+        url: String,
+        body: Option<Vec<u8>>,
+    ) -> Result<StreamingResponse, Error> {
+        self.execute_streaming(HTTPRequest::new(method, &parse_url(&url)?, body))
+    }
+
+    /// Sends `request`, following redirects according to the redirect policy.
+    pub fn execute(&self, request: HTTPRequest) -> Result<HTTPResponse, Error> {
+        self.execute_streaming(request)?.into_response()
+    }
+
+    /// Sends `request`, following redirects according to the redirect policy, and returns once the
+    /// headers of the final response have arrived.
+    pub fn execute_streaming(&self, request: HTTPRequest) -> Result<StreamingResponse, Error> {
+        self.follow(request, None)
+    }
+
+    /// Like `execute`, but fails with `Error::Timeout` when connecting, or any read or write of a
+    /// connection, takes longer than `timeout`.
+    pub(crate) fn execute_timeout(
+        &self,
+        request: HTTPRequest,
+        timeout: Duration,
+    ) -> Result<HTTPResponse, Error> {
+        self.follow(request, Some(timeout))?.into_response()
+    }
+
+    fn follow(
+        &self,
+        request: HTTPRequest,
+        timeout: Option<Duration>,
+    ) -> Result<StreamingResponse, Error> {
+        let mut request = request;
+        let mut previous: Vec<Url> = vec![];
+        loop {
+            let mut response = self.send(&request, timeout)?;
+            let Some(location) = response.redirect_location() else {
+                response.set_redirects(previous);
+                return Ok(response);
+            };
+            let url = redirect::location_url(request.url(), location)?;
+            previous.push(request.url().clone());
+            let next = redirect::next_request(
+                &request,
+                response.status(),
+                &url,
+                &previous,
+                &self.redirect_policy,
+            )?;
+            let Some(next) = next else {
+                previous.pop();
+                response.set_redirects(previous);
+                return Ok(response);
+            };
+            // requesting the same URL with the same method again would never end
+            if previous.contains(next.url()) && next.method() == request.method() {
+                return Err(Error::RedirectLoop(Box::new(next.url().clone())));
+            }
+            // read the body of the redirect so the connection can be reused
+            response.into_response()?;
+            request = next;
+        }
+    }
+
+    /// Sends a single request over a pooled or new connection.
+    fn send(
+        &self,
+        request: &HTTPRequest,
+        timeout: Option<Duration>,
+    ) -> Result<StreamingResponse, Error> {
+        let url = request.url();
+        let method = request.method();
+        let protocol: Protocol = url.scheme().try_into()?;
+        let host = url
+            .host()
+            .ok_or_else(|| Error::Url(format!("missing host in {url}")))?;
+        let hostname = match host {
+            Host::Domain(domain) => domain.clone(),
+            Host::Ipv4(ip) => ip.to_string(),
+            Host::Ipv6(ip) => ip.to_string(),
+        };
+        let key = PoolKey {
+            protocol,
+            host: hostname.clone(),
+            port: url
+                .port_or_known_default()
+                .ok_or_else(|| Error::Url(format!("missing port in {url}")))?,
+        };
+        if let Some(mut stream) = self.pool.take(&key) {
+            // the connection may have been used with another timeout
+            let result = stream
+                .get_ref()
+                .set_timeout(timeout)
+                .map_err(Error::from)
+                .and_then(|()| send_request(&mut stream, request, method));
+            match result {
+                Ok(head) => {
+                    return Ok(StreamingResponse::new(
+                        head,
+                        stream,
+                        Arc::clone(&self.pool),
+                        key,
+                    ))
+                }
+                // the server may have closed the connection just as it was reused
+                Err(_) if method.is_idempotent() => {}
+                Err(e) => return Err(e),
+            }
+        }
+        let sock = match host {
+            Host::Domain(domain) if self.srv_lookup && url.port().is_none() => {
+                let service = format!("_{}._tcp.{domain}", url.scheme());
+                match self.dns_client.lookup_srv(get_random_u16()?, &service) {
+                    Ok(srvs) => connect_srv(&self.dns_client, &service, &srvs, timeout)?,
+                    // the host serves itself
+                    Err(Error::NoRecords(_))
+                    | Err(Error::Rcode {
+                        rcode: dns::Rcode::NXDomain,
+                        ..
+                    }) => self.connect(host, key.port, timeout)?,
+                    Err(e) => return Err(e),
+                }
+            }
+            _ => self.connect(host, key.port, timeout)?,
+        };
+        // the TLS handshake is bound by the timeout too
+        sock.set_read_timeout(timeout)?;
+        sock.set_write_timeout(timeout)?;
+        let stream = match protocol {
+            Protocol::HTTP => Transport::Tcp(sock),
+            Protocol::HTTPS => Transport::tls(&self.tls_config, &ServerName::new(&hostname), sock)?,
         };
-        // resove IP address
-        let id = get_random_u16();
-        let addr = self.dns_client.resolve(id, hostname)?;
-        let protocol: Protocol = protocol.try_into()?;
-        println!("protocol: {:?}, IP address: {:?}", protocol, addr);
-        // connet to a server
-        let mut stream = match protocol {
-            Protocol::HTTP => TcpStream::connect((addr, 80)).map_err(|e| e.to_string())?,
-            Protocol::HTTPS => unimplemented!(),
+        let mut stream = BufReader::new(stream);
+        let head = send_request(&mut stream, request, method)?;
+        Ok(StreamingResponse::new(
+            head,
+            stream,
+            Arc::clone(&self.pool),
+            key,
+        ))
+    }
+
+    /// Connects to `host`, resolving its IP addresses unless it is one already.
+    fn connect(
+        &self,
+        host: &Host,
+        port: u16,
+        timeout: Option<Duration>,
+    ) -> Result<TcpStream, Error> {
+        let addrs = match host {
+            Host::Domain(domain) => self.dns_client.resolve_all(get_random_u16()?, domain)?,
+            Host::Ipv4(ip) => vec![(*ip).into()],
+            Host::Ipv6(ip) => vec![(*ip).into()],
         };
-        println!("connection established: {:?}", stream);
-        // send HTTP request
-        let request = HTTPRequest::new(method, hostname, &url, body);
-        let n = stream
-            .write(request.to_string().as_bytes())
-            .map_err(|e| e.to_string())?;
-        println!("sent {n} bytes");
-        // receive HTTP request
-        let reader = BufReader::new(stream);
-        let response = HTTPResponse::try_from(reader)?;
-        // let response = reader.
-        // let payload: HTTPResponse = reader.lines().map_while(Result::ok).collect();
-        println!("{:?}", response);
-        Ok(response.body.unwrap_or("".to_string()))
+        happy_eyeballs::connect(&addrs, port, CONNECTION_ATTEMPT_DELAY, timeout)
     }
 }
 
-fn get_random_u16() -> u16 {
+/// Connects to the first of the servers of `service` that accepts, in the order of `srvs`.
+fn connect_srv(
+    resolver: &dns::Resolver,
+    service: &str,
+    srvs: &[dns::Srv],
+    timeout: Option<Duration>,
+) -> Result<TcpStream, Error> {
+    let mut last_error = None;
+    for srv in srvs {
+        // targets are absolute names, the search list doesn't apply
+        let result = resolver
+            .resolve_all(get_random_u16()?, &format!("{}.", srv.target))
+            .and_then(|addrs| {
+                happy_eyeballs::connect(&addrs, srv.port, CONNECTION_ATTEMPT_DELAY, timeout)
+            });
+        match result {
+            Ok(sock) => return Ok(sock),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| Error::NoRecords(service.to_string())))
+}
+
+/// Parses a URL given by the user. URLs without a scheme such as "example.com:8080/path" are
+/// taken as http.
+fn parse_url(url: &str) -> Result<Url, Error> {
+    if url.contains("://") {
+        url.parse()
+    } else {
+        format!("http://{url}").parse()
+    }
+}
+
+/// Sends the request and reads the status line and headers of the response.
+fn send_request(
+    stream: &mut Connection,
+    request: &HTTPRequest,
+    method: &Method,
+) -> Result<HTTPResponse, Error> {
+    // send HTTP request
+    stream.get_mut().write_all(&request.to_bytes())?;
+    // receive HTTP response
+    if stream.fill_buf()?.is_empty() {
+        return Err(Error::Io(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "connection closed before receiving a response",
+        )));
+    }
+    HTTPResponse::read_head(stream, method)
+}
+
+fn get_random_u16() -> Result<u16, Error> {
     // TODO: this should not work on Windows
-    let mut file = File::open("/dev/urandom").unwrap();
+    let mut file = File::open("/dev/urandom")?;
     let mut buffer = [0u8; 2];
-    file.read_exact(&mut buffer).unwrap();
-    ((buffer[0] as u16) << 8) + (buffer[1] as u16)
+    file.read_exact(&mut buffer)?;
+    Ok(((buffer[0] as u16) << 8) + (buffer[1] as u16))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::BufRead,
+        net::{TcpListener, TcpStream},
+        thread,
+    };
+
+    /// Reads a request without a body and returns its request line.
+    fn read_request(reader: &mut BufReader<TcpStream>) -> String {
+        let mut lines = reader.lines().map(Result::unwrap);
+        let request_line = lines.next().unwrap();
+        lines.find(String::is_empty);
+        request_line
+    }
+
+    #[test]
+    fn test_client_follows_redirects_over_one_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            // both requests must arrive on the first connection
+            let (sock, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(sock.try_clone().unwrap());
+            let mut sock = sock;
+            assert_eq!(read_request(&mut reader), "GET /old HTTP/1.1");
+            sock.write_all(b"HTTP/1.1 301 Moved Permanently\r\nLocation: /new\r\nContent-Length: 5\r\n\r\nmoved")
+                .unwrap();
+            assert_eq!(read_request(&mut reader), "GET /new HTTP/1.1");
+            sock.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 4\r\n\r\ngone")
+                .unwrap();
+        });
+
+        let client = Client::new();
+        let url = format!("http://127.0.0.1:{port}/old");
+        let response = client.perform(Method::GET, url.clone(), None).unwrap();
+        assert_eq!(response.status(), 404);
+        assert_eq!(response.bytes(), b"gone");
+        assert_eq!(response.redirects(), [Url::parse(&url).unwrap()]);
+        server.join().unwrap();
+
+        let client = Client::builder()
+            .redirect_policy(RedirectPolicy::Limited(0))
+            .build();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (sock, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(sock.try_clone().unwrap());
+            let mut sock = sock;
+            read_request(&mut reader);
+            sock.write_all(b"HTTP/1.1 302 Found\r\nLocation: /\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+        });
+        let url = format!("http://127.0.0.1:{port}/");
+        let result = client.perform(Method::GET, url, None);
+        assert!(matches!(result, Err(Error::TooManyRedirects(0))));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_client_fails_over_srv_targets() {
+        let dns_sock = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let path = std::env::temp_dir().join(format!("fetch-srv-hosts-{}", std::process::id()));
+        std::fs::write(&path, "127.0.0.1 down.test up.test\n").unwrap();
+        let resolver = dns::Resolver::with_config(dns::ResolvConf {
+            nameservers: vec![dns_sock.local_addr().unwrap()],
+            udp_payload_size: None,
+            ..dns::ResolvConf::default()
+        })
+        .with_hosts(Some(dns::Hosts::new(&path)))
+        .with_cache(None);
+        // nothing listens on the port of the preferred server anymore
+        let down_port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let up_port = listener.local_addr().unwrap().port();
+
+        let dns_server = thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            let (len, peer) = dns_sock.recv_from(&mut buf).unwrap();
+            let mut response = buf[..len].to_vec();
+            assert!(response.ends_with(b"\x05_http\x04_tcp\x07service\x04test\0\0\x21\0\x01"));
+            response[2] |= 0x80;
+            response[7] = 2;
+            for (priority, port, target) in [
+                (10u16, down_port, &b"\x04down"[..]),
+                (20, up_port, b"\x02up"),
+            ] {
+                let target = [target, b"\x04test\0"].concat();
+                // the owner is a pointer to the question
+                response.extend([0xc0, 12, 0, 33, 0, 1, 0, 0, 0, 60]);
+                response.extend(((target.len() + 6) as u16).to_be_bytes());
+                response.extend(priority.to_be_bytes());
+                response.extend(0u16.to_be_bytes());
+                response.extend(port.to_be_bytes());
+                response.extend(target);
+            }
+            dns_sock.send_to(&response, peer).unwrap();
+        });
+        let server = thread::spawn(move || {
+            let (sock, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(sock.try_clone().unwrap());
+            let mut sock = sock;
+            assert_eq!(read_request(&mut reader), "GET / HTTP/1.1");
+            sock.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nup")
+                .unwrap();
+        });
+
+        let client = Client::builder()
+            .resolver(resolver)
+            .srv_lookup(true)
+            .build();
+        let response = client
+            .perform(Method::GET, "http://service.test/".to_string(), None)
+            .unwrap();
+        assert_eq!(response.bytes(), b"up");
+        dns_server.join().unwrap();
+        server.join().unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
 }
@@ -1,5 +1,5 @@
 use fetch::http::Method;
-use fetch::Client;
+use fetch::{Client, Error};
 use std::process::exit;
 
 fn main() {
@@ -9,12 +9,23 @@ fn main() {
         display_usage(&program);
         exit(1);
     }
-    let method: Method = args.next().unwrap().parse().unwrap();
+    let method: Method = match args.next().unwrap().parse() {
+        Ok(method) => method,
+        Err(e) => fail(e),
+    };
     let url = args.next().unwrap();
-    let body = args.next();
+    let body = args.next().map(String::into_bytes);
     let client = Client::new();
-    let response = client.perform(method, url, body).unwrap();
-    println!("{response}");
+    let mut response = match client.perform_streaming(method, url, body) {
+        Ok(response) => response,
+        Err(e) => fail(e),
+    };
+    if !response.head().is_success() {
+        eprintln!("{} {}", response.status(), response.head().reason());
+    }
+    if let Err(e) = std::io::copy(&mut response, &mut std::io::stdout()) {
+        fail(e.into());
+    }
 }
 
 fn display_usage(program_name: &str) {
@@ -24,3 +35,8 @@ fn display_usage(program_name: &str) {
     "
     );
 }
+
+fn fail(error: Error) -> ! {
+    eprintln!("error: {error}");
+    exit(1);
+}
@@ -0,0 +1,140 @@
+use std::{
+    collections::HashMap,
+    io::BufReader,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{http::Protocol, transport::Transport};
+
+/// A connection together with whatever the response parser buffered from it
+pub type Connection = BufReader<Transport>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PoolKey {
+    pub protocol: Protocol,
+    pub host: String,
+    pub port: u16,
+}
+
+#[derive(Debug)]
+struct IdleConnection {
+    connection: Connection,
+    expires_at: Instant,
+}
+
+/**
+ * Idle HTTP/1.1 connections kept open for reuse, keyed by (scheme, host, port)
+ */
+#[derive(Debug)]
+pub struct Pool {
+    idle: Mutex<HashMap<PoolKey, Vec<IdleConnection>>>,
+    idle_timeout: Duration,
+    max_idle_per_host: usize,
+}
+
+impl Pool {
+    pub fn new(idle_timeout: Duration, max_idle_per_host: usize) -> Self {
+        Self {
+            idle: Mutex::new(HashMap::new()),
+            idle_timeout,
+            max_idle_per_host,
+        }
+    }
+
+    /// Takes the most recently used connection to `key` that is still usable.
+    pub fn take(&self, key: &PoolKey) -> Option<Connection> {
+        let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let connections = idle.get_mut(key)?;
+        let mut found = None;
+        while let Some(entry) = connections.pop() {
+            if entry.expires_at > now && is_reusable(&entry.connection) {
+                found = Some(entry.connection);
+                break;
+            }
+        }
+        if connections.is_empty() {
+            idle.remove(key);
+        }
+        found
+    }
+
+    /// Returns a connection to the pool. `keep_alive_timeout` is the idle timeout announced by the server.
+    pub fn put(&self, key: PoolKey, connection: Connection, keep_alive_timeout: Option<Duration>) {
+        if self.max_idle_per_host == 0 || !connection.buffer().is_empty() {
+            return;
+        }
+        let timeout = keep_alive_timeout.map_or(self.idle_timeout, |t| t.min(self.idle_timeout));
+        if timeout.is_zero() {
+            return;
+        }
+        let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        idle.retain(|_, connections| {
+            connections.retain(|entry| entry.expires_at > now);
+            !connections.is_empty()
+        });
+        let connections = idle.entry(key).or_default();
+        if connections.len() >= self.max_idle_per_host {
+            // drop the connection that has been idle the longest
+            connections.remove(0);
+        }
+        connections.push(IdleConnection {
+            connection,
+            expires_at: now + timeout,
+        });
+    }
+}
+
+/// A pooled connection must have nothing buffered and a socket the server hasn't closed.
+fn is_reusable(connection: &Connection) -> bool {
+    connection.buffer().is_empty() && !connection.get_ref().is_stale()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{SocketAddr, TcpListener, TcpStream};
+
+    fn key() -> PoolKey {
+        PoolKey {
+            protocol: Protocol::HTTP,
+            host: "localhost".to_string(),
+            port: 80,
+        }
+    }
+
+    fn connect(listener: &TcpListener) -> Connection {
+        let addr: SocketAddr = listener.local_addr().unwrap();
+        BufReader::new(Transport::Tcp(TcpStream::connect(addr).unwrap()))
+    }
+
+    #[test]
+    fn test_pool_reuses_open_connections() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let pool = Pool::new(Duration::from_secs(60), 1);
+        let connection = connect(&listener);
+        let (server, _) = listener.accept().unwrap();
+        pool.put(key(), connection, None);
+        let connection = pool.take(&key()).unwrap();
+        assert!(pool.take(&key()).is_none());
+
+        // the server closed the idle connection
+        pool.put(key(), connection, None);
+        drop(server);
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(pool.take(&key()).is_none());
+    }
+
+    #[test]
+    fn test_pool_honors_timeouts() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let pool = Pool::new(Duration::from_secs(60), 2);
+        pool.put(key(), connect(&listener), Some(Duration::ZERO));
+        assert!(pool.take(&key()).is_none());
+        pool.put(key(), connect(&listener), Some(Duration::from_millis(10)));
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(pool.take(&key()).is_none());
+    }
+}
@@ -0,0 +1,273 @@
+use std::{fmt::Debug, sync::Arc};
+
+use crate::{
+    http::{HTTPRequest, Method},
+    url::Url,
+    Error,
+};
+
+/// Number of redirects followed by the default policy
+const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+/// Header fields that must not leak to another origin
+const SENSITIVE_HEADERS: [&str; 3] = ["Authorization", "Proxy-Authorization", "Cookie"];
+
+/// Header fields describing a request body, dropped together with the body
+const CONTENT_HEADERS: [&str; 4] = [
+    "Content-Type",
+    "Content-Encoding",
+    "Content-Language",
+    "Content-Location",
+];
+
+/// Decision of a RedirectPolicy about one redirect
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RedirectAction {
+    /// Send the next request, including any change the policy made to it.
+    Follow,
+    /// Return the redirect response to the caller.
+    Stop,
+    /// Fail the request with Error::Redirect and this message.
+    Error(String),
+}
+
+/**
+ * A redirect about to be followed, handed to a RedirectPolicy
+ */
+#[derive(Debug)]
+pub struct Attempt<'a> {
+    status: u16,
+    previous: &'a [Url],
+    request: &'a mut HTTPRequest,
+}
+
+impl Attempt<'_> {
+    /// Status code of the redirect response.
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// The URL the server redirects to.
+    pub fn url(&self) -> &Url {
+        self.request.url()
+    }
+
+    /// URLs requested so far, starting with the original one. The last one sent the redirect.
+    pub fn previous(&self) -> &[Url] {
+        self.previous
+    }
+
+    /// The request that is sent when the redirect is followed, with the method and headers
+    /// already rewritten for this hop.
+    pub fn request(&self) -> &HTTPRequest {
+        self.request
+    }
+
+    pub fn request_mut(&mut self) -> &mut HTTPRequest {
+        self.request
+    }
+}
+
+/**
+ * How a Client handles 301, 302, 303, 307 and 308 responses
+ */
+#[derive(Clone)]
+pub enum RedirectPolicy {
+    /// Never follow redirects.
+    None,
+    /// Follow at most this many redirects, failing the request after that.
+    Limited(usize),
+    /// Ask a function for every redirect.
+    Custom(Arc<dyn Fn(&mut Attempt) -> RedirectAction + Send + Sync>),
+}
+
+impl RedirectPolicy {
+    pub fn custom(policy: impl Fn(&mut Attempt) -> RedirectAction + Send + Sync + 'static) -> Self {
+        RedirectPolicy::Custom(Arc::new(policy))
+    }
+
+    /// Whether the redirect should be followed.
+    pub(crate) fn redirect(&self, attempt: &mut Attempt) -> Result<bool, Error> {
+        match self {
+            RedirectPolicy::None => Ok(false),
+            RedirectPolicy::Limited(max) if attempt.previous.len() > *max => {
+                Err(Error::TooManyRedirects(*max))
+            }
+            RedirectPolicy::Limited(_) => Ok(true),
+            RedirectPolicy::Custom(policy) => match policy(attempt) {
+                RedirectAction::Follow => Ok(true),
+                RedirectAction::Stop => Ok(false),
+                RedirectAction::Error(message) => Err(Error::Redirect(message)),
+            },
+        }
+    }
+}
+
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        RedirectPolicy::Limited(DEFAULT_MAX_REDIRECTS)
+    }
+}
+
+impl Debug for RedirectPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RedirectPolicy::None => write!(f, "None"),
+            RedirectPolicy::Limited(max) => write!(f, "Limited({max})"),
+            RedirectPolicy::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+/// Resolves the Location of a redirect. A Location without a fragment inherits the fragment of
+/// the request URL (RFC 9110 section 10.2.2).
+pub(crate) fn location_url(from: &Url, location: &str) -> Result<Url, Error> {
+    let url = from.join(location)?;
+    match (url.fragment(), from.fragment()) {
+        (None, Some(fragment)) => url.join(&format!("#{fragment}")),
+        _ => Ok(url),
+    }
+}
+
+/**
+ * Builds the request that follows a redirect (RFC 9110 section 15.4)
+ *
+ * 303 turns any method except HEAD into GET, and so do 301 and 302 for POST, as browsers do.
+ * The body is dropped whenever the method changes. Credentials are not sent to another origin.
+ */
+pub(crate) fn next_request(
+    request: &HTTPRequest,
+    status: u16,
+    url: &Url,
+    previous: &[Url],
+    policy: &RedirectPolicy,
+) -> Result<Option<HTTPRequest>, Error> {
+    let method = match (status, request.method()) {
+        (303, Method::HEAD) => Method::HEAD,
+        (303, _) | (301 | 302, Method::POST) => Method::GET,
+        (_, method) => method.clone(),
+    };
+    let keep_body = &method == request.method();
+    let body = request.body().filter(|_| keep_body).map(<[u8]>::to_vec);
+    let mut next = HTTPRequest::new(method, url, body);
+    let cross_origin = !same_origin(request.url(), url);
+    for (name, value) in request.headers().iter() {
+        let skip = name.eq_ignore_ascii_case("Host")
+            || name.eq_ignore_ascii_case("Content-Length")
+            || (!keep_body && CONTENT_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(name)))
+            || (cross_origin
+                && SENSITIVE_HEADERS
+                    .iter()
+                    .any(|h| h.eq_ignore_ascii_case(name)));
+        if !skip {
+            next.insert_header(name, value);
+        }
+    }
+    let mut attempt = Attempt {
+        status,
+        previous,
+        request: &mut next,
+    };
+    let follow = policy.redirect(&mut attempt)?;
+    Ok(Some(next).filter(|_| follow))
+}
+
+fn same_origin(a: &Url, b: &Url) -> bool {
+    a.scheme() == b.scheme()
+        && a.host() == b.host()
+        && a.port_or_known_default() == b.port_or_known_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(url: &str) -> HTTPRequest {
+        let mut request = HTTPRequest::new(
+            Method::POST,
+            &Url::parse(url).unwrap(),
+            Some(b"a=1".to_vec()),
+        );
+        request.insert_header("Content-Type", "application/x-www-form-urlencoded");
+        request.insert_header("Authorization", "Bearer secret");
+        request
+    }
+
+    fn follow(request: &HTTPRequest, status: u16, location: &str) -> HTTPRequest {
+        let url = location_url(request.url(), location).unwrap();
+        let previous = [request.url().clone()];
+        next_request(request, status, &url, &previous, &RedirectPolicy::default())
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_redirect_rewrites_method() {
+        let request = post("http://example.com/form");
+        let next = follow(&request, 303, "/done");
+        assert_eq!(next.method(), &Method::GET);
+        assert_eq!(next.url().to_string(), "http://example.com/done");
+        assert_eq!(next.body(), None);
+        assert_eq!(next.headers().get("Content-Type"), None);
+        assert_eq!(next.headers().get("Content-Length"), None);
+        assert_eq!(next.headers().get("Authorization"), Some("Bearer secret"));
+        assert_eq!(follow(&request, 302, "/done").method(), &Method::GET);
+
+        let next = follow(&request, 307, "/retry");
+        assert_eq!(next.method(), &Method::POST);
+        assert_eq!(next.body(), Some(&b"a=1"[..]));
+        assert_eq!(next.headers().get("Content-Length"), Some("3"));
+        assert_eq!(next.headers().get("Host"), Some("example.com"));
+    }
+
+    #[test]
+    fn test_redirect_strips_credentials_across_origins() {
+        let request = post("http://example.com/form");
+        let next = follow(&request, 308, "https://example.com/form");
+        assert_eq!(next.headers().get("Authorization"), None);
+        assert_eq!(
+            next.headers().get("Content-Type"),
+            Some("application/x-www-form-urlencoded")
+        );
+        let next = follow(&request, 308, "//example.com:8080/form");
+        assert_eq!(next.headers().get("Authorization"), None);
+        assert_eq!(next.headers().get("Host"), Some("example.com:8080"));
+    }
+
+    #[test]
+    fn test_location_url_keeps_fragment() {
+        let from = Url::parse("http://example.com/a#section").unwrap();
+        assert_eq!(
+            location_url(&from, "/b").unwrap().to_string(),
+            "http://example.com/b#section"
+        );
+        assert_eq!(
+            location_url(&from, "/b#other").unwrap().to_string(),
+            "http://example.com/b#other"
+        );
+    }
+
+    #[test]
+    fn test_redirect_policy() {
+        let request = post("http://example.com/");
+        let url = Url::parse("http://example.com/next").unwrap();
+        let previous = vec![request.url().clone(); 2];
+        let next = |policy| next_request(&request, 301, &url, &previous, &policy);
+        assert!(next(RedirectPolicy::None).unwrap().is_none());
+        assert!(next(RedirectPolicy::Limited(2)).unwrap().is_some());
+        assert!(matches!(
+            next(RedirectPolicy::Limited(1)),
+            Err(Error::TooManyRedirects(1))
+        ));
+        let policy = RedirectPolicy::custom(|attempt| {
+            if attempt.url().path() == "/next" {
+                attempt.request_mut().insert_header("X-Hop", "1");
+                RedirectAction::Follow
+            } else {
+                RedirectAction::Stop
+            }
+        });
+        let followed = next(policy).unwrap().unwrap();
+        assert_eq!(followed.headers().get("X-Hop"), Some("1"));
+    }
+}
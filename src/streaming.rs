@@ -0,0 +1,155 @@
+use std::{
+    io::{self, Read},
+    sync::Arc,
+};
+
+use crate::{
+    http::{body_error, BodyReader, HTTPHeaders, HTTPResponse},
+    pool::{Connection, Pool, PoolKey},
+    url::Url,
+    Error,
+};
+
+/**
+ * A response whose body is read from the connection on demand
+ *
+ * The connection goes back to the client's pool once the body has been read to the end. Dropping
+ * the response earlier closes it.
+ */
+#[derive(Debug)]
+pub struct StreamingResponse {
+    head: HTTPResponse,
+    body: Option<BodyReader<Connection>>,
+    pool: Arc<Pool>,
+    key: PoolKey,
+}
+
+impl StreamingResponse {
+    pub(crate) fn new(
+        head: HTTPResponse,
+        connection: Connection,
+        pool: Arc<Pool>,
+        key: PoolKey,
+    ) -> Self {
+        let body = head.body_reader(connection);
+        let mut response = Self {
+            head,
+            body: Some(body),
+            pool,
+            key,
+        };
+        response.release_if_done();
+        response
+    }
+
+    /// The status line and headers. Its body is always empty.
+    pub fn head(&self) -> &HTTPResponse {
+        &self.head
+    }
+
+    pub fn status(&self) -> u16 {
+        self.head.status()
+    }
+
+    pub fn headers(&self) -> &HTTPHeaders {
+        self.head.headers()
+    }
+
+    /// Header fields sent after a chunked body, available once the body has been read.
+    pub fn trailers(&self) -> &HTTPHeaders {
+        self.head.trailers()
+    }
+
+    /// Reads the rest of the body into memory.
+    pub fn into_response(mut self) -> Result<HTTPResponse, Error> {
+        let mut body = vec![];
+        if let Some(reader) = self.body.as_mut() {
+            reader.read_to_end(&mut body).map_err(body_error)?;
+        }
+        self.release_if_done();
+        self.head.set_body(body);
+        Ok(self.head)
+    }
+
+    pub(crate) fn redirect_location(&self) -> Option<&str> {
+        self.head.redirect_location()
+    }
+
+    pub(crate) fn set_redirects(&mut self, redirects: Vec<Url>) {
+        self.head.set_redirects(redirects);
+    }
+
+    /// Returns the connection to the pool once nothing of the body is left on it.
+    fn release_if_done(&mut self) {
+        if !self.body.as_ref().is_some_and(BodyReader::is_done) {
+            return;
+        }
+        let body = self.body.take().unwrap();
+        if let Some(trailers) = body.trailers() {
+            self.head.set_trailers(trailers.clone());
+        }
+        if self.head.is_keep_alive() {
+            self.pool.put(
+                self.key.clone(),
+                body.into_inner(),
+                self.head.keep_alive_timeout(),
+            );
+        }
+    }
+}
+
+impl Read for StreamingResponse {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(body) = self.body.as_mut() else {
+            return Ok(0);
+        };
+        let n = body.read(buf)?;
+        self.release_if_done();
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http::{Method, Protocol},
+        transport::Transport,
+    };
+    use std::{
+        io::{BufReader, Write},
+        net::{TcpListener, TcpStream},
+        time::Duration,
+    };
+
+    #[test]
+    fn test_streaming_response_returns_connection_when_drained() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut connection = BufReader::new(Transport::Tcp(
+            TcpStream::connect(listener.local_addr().unwrap()).unwrap(),
+        ));
+        let (mut server, _) = listener.accept().unwrap();
+        server
+            .write_all(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n")
+            .unwrap();
+        let head = HTTPResponse::read_head(&mut connection, &Method::GET).unwrap();
+
+        let pool = Arc::new(Pool::new(Duration::from_secs(60), 1));
+        let key = PoolKey {
+            protocol: Protocol::HTTP,
+            host: "127.0.0.1".to_string(),
+            port: 80,
+        };
+        let mut response = StreamingResponse::new(head, connection, pool.clone(), key.clone());
+        assert_eq!(response.status(), 200);
+        let mut buf = [0u8; 5];
+        response.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello");
+        assert!(pool.take(&key).is_none());
+
+        server.write_all(b"0\r\nX-Done: yes\r\n\r\n").unwrap();
+        assert_eq!(response.read(&mut buf).unwrap(), 0);
+        assert_eq!(response.trailers().get("X-Done"), Some("yes"));
+        assert!(pool.take(&key).is_some());
+    }
+}
@@ -0,0 +1,869 @@
+use std::{
+    io::{self, Read, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{
+    codec::{put_vec_u24, put_vec_u8, Reader},
+    handshake::{self, CipherSuite, ClientHello, NamedGroup, ServerHello},
+    key_schedule::{self, KeySchedule},
+    record::{self, RecordCipher},
+    x509::{self, Certificate, PublicKey},
+    ClientConfig, ServerName, TlsError,
+};
+use crate::crypto::{constant_time_eq, ec::Curve, fill_random, x25519};
+
+// alert descriptions
+const CLOSE_NOTIFY: u8 = 0;
+const BAD_RECORD_MAC: u8 = 20;
+const HANDSHAKE_FAILURE: u8 = 40;
+const BAD_CERTIFICATE: u8 = 42;
+const DECODE_ERROR: u8 = 50;
+
+/// Last 8 bytes of ServerHello.random of a TLS 1.3 capable server negotiating TLS 1.2
+const DOWNGRADE_TLS12: &[u8] = b"DOWNGRD\x01";
+
+/// A message received from the record layer
+enum Message {
+    /// A complete handshake message including its 4 byte header
+    Handshake(Vec<u8>),
+    ChangeCipherSpec,
+    ApplicationData(Vec<u8>),
+    /// close_notify or end of stream
+    Eof,
+}
+
+/**
+ * Ephemeral keys for every group offered in the ClientHello
+ */
+struct KeyShares {
+    x25519: ([u8; 32], [u8; 32]),
+    secp256r1: (Vec<u8>, Vec<u8>),
+}
+
+impl KeyShares {
+    fn generate() -> io::Result<Self> {
+        Ok(Self {
+            x25519: x25519::generate_keypair()?,
+            secp256r1: Curve::p256().generate_keypair()?,
+        })
+    }
+
+    fn public(&self, group: NamedGroup) -> Vec<u8> {
+        match group {
+            NamedGroup::X25519 => self.x25519.1.to_vec(),
+            NamedGroup::Secp256r1 => self.secp256r1.1.clone(),
+        }
+    }
+
+    fn shared_secret(&self, group: NamedGroup, peer: &[u8]) -> Result<Vec<u8>, TlsError> {
+        let shared = match group {
+            NamedGroup::X25519 => peer
+                .try_into()
+                .ok()
+                .map(|peer| x25519::x25519(&self.x25519.0, peer))
+                .filter(|shared| shared.iter().any(|b| *b != 0))
+                .map(|shared| shared.to_vec()),
+            NamedGroup::Secp256r1 => Curve::p256().shared_secret(&self.secp256r1.0, peer),
+        };
+        shared.ok_or_else(|| TlsError::HandshakeFailure("invalid key share".to_string()))
+    }
+}
+
+/**
+ * A TLS client connection over a byte stream such as a TcpStream
+ *
+ * The handshake is completed by `connect`. Reading returns 0 once the server sent
+ * close_notify or closed the underlying stream.
+ */
+#[derive(Debug)]
+pub struct TlsStream<S> {
+    sock: S,
+    suite: CipherSuite,
+    read_cipher: Option<RecordCipher>,
+    write_cipher: Option<RecordCipher>,
+    // TLS 1.3 application traffic secrets, needed for KeyUpdate
+    read_secret: Vec<u8>,
+    write_secret: Vec<u8>,
+    // handshake messages can be fragmented over several records
+    handshake_buf: Vec<u8>,
+    plaintext: Vec<u8>,
+    plaintext_pos: usize,
+    eof: bool,
+}
+
+impl<S: Read + Write> TlsStream<S> {
+    /// Performs a handshake over `sock` and authenticates the server as `server_name`.
+    pub fn connect(
+        config: &ClientConfig,
+        server_name: &ServerName,
+        sock: S,
+    ) -> Result<Self, TlsError> {
+        let mut stream = Self {
+            sock,
+            suite: handshake::CIPHER_SUITES[0],
+            read_cipher: None,
+            write_cipher: None,
+            read_secret: vec![],
+            write_secret: vec![],
+            handshake_buf: vec![],
+            plaintext: vec![],
+            plaintext_pos: 0,
+            eof: false,
+        };
+        match stream.handshake(config, server_name) {
+            Ok(()) => Ok(stream),
+            Err(e) => {
+                let description = match &e {
+                    TlsError::Decode(_) => Some(DECODE_ERROR),
+                    TlsError::HandshakeFailure(_) => Some(HANDSHAKE_FAILURE),
+                    TlsError::BadCertificate(_) => Some(BAD_CERTIFICATE),
+                    TlsError::DecryptError => Some(BAD_RECORD_MAC),
+                    TlsError::Io(_) | TlsError::AlertReceived(_) => None,
+                };
+                if let Some(description) = description {
+                    // best effort, the original error is more useful than a failure to send the alert
+                    let _ = stream.send(record::ALERT, &[2, description]);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.sock
+    }
+
+    /// Returns the data that arrived, reading the next message if none is buffered, or `None` at
+    /// the end of the stream. Unlike `read`, it doesn't wait for another message when that one
+    /// carries no data, such as a session ticket, so it returns soon once the socket is readable.
+    pub(crate) fn read_available(&mut self) -> io::Result<Option<Vec<u8>>> {
+        if self.plaintext_pos >= self.plaintext.len() && !self.eof {
+            self.read_record()?;
+        }
+        if self.plaintext_pos >= self.plaintext.len() && self.eof {
+            return Ok(None);
+        }
+        let data = self.plaintext[self.plaintext_pos..].to_vec();
+        self.plaintext_pos = self.plaintext.len();
+        Ok(Some(data))
+    }
+
+    /// Whether TLS 1.3 was negotiated.
+    pub fn is_tls13(&self) -> bool {
+        self.suite.tls13
+    }
+
+    fn handshake(
+        &mut self,
+        config: &ClientConfig,
+        server_name: &ServerName,
+    ) -> Result<(), TlsError> {
+        let keys = KeyShares::generate()?;
+        let mut client_random = [0u8; 32];
+        let mut session_id = [0u8; 32];
+        fill_random(&mut client_random)?;
+        fill_random(&mut session_id)?;
+        let server_name_indication = match server_name {
+            ServerName::Dns(name) => Some(name.as_str()),
+            ServerName::Ip(_) => None,
+        };
+        let mut client_hello = ClientHello {
+            random: client_random,
+            session_id,
+            server_name: server_name_indication,
+            key_shares: [NamedGroup::X25519, NamedGroup::Secp256r1]
+                .into_iter()
+                .map(|group| (group, keys.public(group)))
+                .collect(),
+            cookie: None,
+        };
+        let message = client_hello.encode();
+        self.send(record::HANDSHAKE, &message)?;
+        let mut transcript = message;
+
+        let mut message = self.expect_handshake(handshake::SERVER_HELLO)?;
+        let mut server_hello = ServerHello::parse(&message[4..])?;
+        let mut retry_suite = None;
+        if server_hello.is_retry_request() {
+            let suite = select_suite(&server_hello)?;
+            if server_hello.selected_version != Some(handshake::TLS13) || !suite.tls13 {
+                return Err(TlsError::HandshakeFailure(
+                    "HelloRetryRequest for a version other than TLS 1.3".to_string(),
+                ));
+            }
+            // every supported group already has a key share, so only a cookie can justify a retry
+            if server_hello.key_share.is_some() || server_hello.cookie.is_none() {
+                return Err(TlsError::HandshakeFailure(
+                    "invalid HelloRetryRequest".to_string(),
+                ));
+            }
+            client_hello.cookie = server_hello.cookie.clone();
+            // the first ClientHello is replaced by its hash (RFC 8446 section 4.4.1)
+            let hash = suite.hash.digest(&[&transcript]);
+            transcript = handshake::message(handshake::MESSAGE_HASH, &hash);
+            transcript.extend_from_slice(&message);
+            self.send(record::CHANGE_CIPHER_SPEC, &[1])?;
+            let second_hello = client_hello.encode();
+            self.send(record::HANDSHAKE, &second_hello)?;
+            transcript.extend_from_slice(&second_hello);
+            message = self.expect_handshake(handshake::SERVER_HELLO)?;
+            server_hello = ServerHello::parse(&message[4..])?;
+            if server_hello.is_retry_request() {
+                return Err(TlsError::HandshakeFailure(
+                    "received a second HelloRetryRequest".to_string(),
+                ));
+            }
+            retry_suite = Some(suite);
+        }
+        transcript.extend_from_slice(&message);
+
+        self.suite = select_suite(&server_hello)?;
+        if retry_suite.is_some_and(|suite| suite != self.suite) {
+            return Err(TlsError::HandshakeFailure(
+                "server changed the cipher suite after HelloRetryRequest".to_string(),
+            ));
+        }
+        match server_hello.selected_version {
+            Some(handshake::TLS13) if self.suite.tls13 => self.handshake_tls13(
+                config,
+                server_name,
+                &keys,
+                &client_hello,
+                &server_hello,
+                transcript,
+            ),
+            None if server_hello.legacy_version == handshake::TLS12
+                && !self.suite.tls13
+                && retry_suite.is_none() =>
+            {
+                if server_hello.random.ends_with(DOWNGRADE_TLS12) {
+                    return Err(TlsError::HandshakeFailure(
+                        "detected a downgrade from TLS 1.3".to_string(),
+                    ));
+                }
+                self.handshake_tls12(
+                    config,
+                    server_name,
+                    &keys,
+                    &client_random,
+                    &server_hello,
+                    transcript,
+                )
+            }
+            _ => Err(TlsError::HandshakeFailure(
+                "server selected an unsupported protocol version".to_string(),
+            )),
+        }
+    }
+
+    fn handshake_tls13(
+        &mut self,
+        config: &ClientConfig,
+        server_name: &ServerName,
+        keys: &KeyShares,
+        client_hello: &ClientHello,
+        server_hello: &ServerHello,
+        mut transcript: Vec<u8>,
+    ) -> Result<(), TlsError> {
+        if server_hello.session_id != client_hello.session_id {
+            return Err(TlsError::HandshakeFailure(
+                "server did not echo the session id".to_string(),
+            ));
+        }
+        let hash = self.suite.hash;
+        let (group, peer) = server_hello
+            .key_share
+            .as_ref()
+            .and_then(|(id, peer)| Some((NamedGroup::from_id(*id)?, peer)))
+            .filter(|(group, _)| client_hello.key_shares.iter().any(|(g, _)| g == group))
+            .ok_or_else(|| {
+                TlsError::HandshakeFailure("server selected an unoffered key share".to_string())
+            })?;
+        let schedule = KeySchedule::new(hash, &keys.shared_secret(group, peer)?);
+        let (client_secret, server_secret) =
+            schedule.handshake_traffic_secrets(&hash.digest(&[&transcript]));
+        self.set_read_secret(&server_secret)?;
+
+        let message = self.expect_handshake(handshake::ENCRYPTED_EXTENSIONS)?;
+        transcript.extend_from_slice(&message);
+
+        let mut message = self.read_handshake()?;
+        let mut certificate_request = None;
+        if message[0] == handshake::CERTIFICATE_REQUEST {
+            // answered with an empty Certificate, client authentication is not supported
+            let mut reader = Reader::new(&message[4..]);
+            certificate_request = Some(reader.vec_u8()?.to_vec());
+            transcript.extend_from_slice(&message);
+            message = self.read_handshake()?;
+        }
+        let public_key = self.verify_certificate(config, server_name, &message, true)?;
+        transcript.extend_from_slice(&message);
+
+        let message = self.expect_handshake(handshake::CERTIFICATE_VERIFY)?;
+        let (scheme, signature) = handshake::parse_signature(&mut Reader::new(&message[4..]))?;
+        let mut signed = vec![0x20; 64];
+        signed.extend_from_slice(b"TLS 1.3, server CertificateVerify\0");
+        signed.extend(hash.digest(&[&transcript]));
+        if !public_key.verify(
+            handshake::signature_algorithm(scheme, true)?,
+            &signed,
+            signature,
+        ) {
+            return Err(TlsError::HandshakeFailure(
+                "invalid CertificateVerify signature".to_string(),
+            ));
+        }
+        transcript.extend_from_slice(&message);
+
+        let message = self.expect_handshake(handshake::FINISHED)?;
+        let expected =
+            key_schedule::finished_verify_data(hash, &server_secret, &hash.digest(&[&transcript]));
+        if !constant_time_eq(&expected, &message[4..]) {
+            return Err(TlsError::DecryptError);
+        }
+        transcript.extend_from_slice(&message);
+        let schedule = schedule.into_master();
+        let (client_app_secret, server_app_secret) =
+            schedule.application_traffic_secrets(&hash.digest(&[&transcript]));
+
+        // middlebox compatibility mode (RFC 8446 appendix D.4), unless it was already sent
+        // before the second ClientHello
+        if client_hello.cookie.is_none() {
+            self.send(record::CHANGE_CIPHER_SPEC, &[1])?;
+        }
+        self.set_write_secret(&client_secret);
+        if let Some(context) = certificate_request {
+            let mut body = vec![];
+            put_vec_u8(&mut body, &context);
+            put_vec_u24(&mut body, &[]);
+            let message = handshake::message(handshake::CERTIFICATE, &body);
+            self.send(record::HANDSHAKE, &message)?;
+            transcript.extend_from_slice(&message);
+        }
+        let verify_data =
+            key_schedule::finished_verify_data(hash, &client_secret, &hash.digest(&[&transcript]));
+        self.send(
+            record::HANDSHAKE,
+            &handshake::message(handshake::FINISHED, &verify_data),
+        )?;
+        self.set_write_secret(&client_app_secret);
+        self.set_read_secret(&server_app_secret)?;
+        Ok(())
+    }
+
+    fn handshake_tls12(
+        &mut self,
+        config: &ClientConfig,
+        server_name: &ServerName,
+        keys: &KeyShares,
+        client_random: &[u8],
+        server_hello: &ServerHello,
+        mut transcript: Vec<u8>,
+    ) -> Result<(), TlsError> {
+        if server_hello
+            .renegotiation_info
+            .as_ref()
+            .is_some_and(|info| !info.is_empty())
+        {
+            return Err(TlsError::HandshakeFailure(
+                "unexpected renegotiation_info".to_string(),
+            ));
+        }
+        let hash = self.suite.hash;
+        let message = self.expect_handshake(handshake::CERTIFICATE)?;
+        let public_key = self.verify_certificate(config, server_name, &message, false)?;
+        transcript.extend_from_slice(&message);
+
+        let message = self.expect_handshake(handshake::SERVER_KEY_EXCHANGE)?;
+        let mut reader = Reader::new(&message[4..]);
+        // ECParameters with curve_type named_curve (3)
+        if reader.u8()? != 3 {
+            return Err(TlsError::HandshakeFailure(
+                "unsupported ECDHE parameters".to_string(),
+            ));
+        }
+        let id = reader.u16()?;
+        let group = NamedGroup::from_id(id).ok_or_else(|| {
+            TlsError::HandshakeFailure(format!("server selected unsupported group {id}"))
+        })?;
+        let peer = reader.vec_u8()?;
+        let params = &message[4..4 + 3 + 1 + peer.len()];
+        let (scheme, signature) = handshake::parse_signature(&mut reader)?;
+        let signed = [client_random, &server_hello.random, params].concat();
+        if !public_key.verify(
+            handshake::signature_algorithm(scheme, false)?,
+            &signed,
+            signature,
+        ) {
+            return Err(TlsError::HandshakeFailure(
+                "invalid ServerKeyExchange signature".to_string(),
+            ));
+        }
+        let pre_master_secret = keys.shared_secret(group, peer)?;
+        transcript.extend_from_slice(&message);
+
+        let mut message = self.read_handshake()?;
+        let certificate_requested = message[0] == handshake::CERTIFICATE_REQUEST;
+        if certificate_requested {
+            transcript.extend_from_slice(&message);
+            message = self.read_handshake()?;
+        }
+        if message[0] != handshake::SERVER_HELLO_DONE {
+            return Err(unexpected_message(message[0]));
+        }
+        transcript.extend_from_slice(&message);
+
+        if certificate_requested {
+            let mut body = vec![];
+            put_vec_u24(&mut body, &[]);
+            let message = handshake::message(handshake::CERTIFICATE, &body);
+            self.send(record::HANDSHAKE, &message)?;
+            transcript.extend_from_slice(&message);
+        }
+        let mut body = vec![];
+        put_vec_u8(&mut body, &keys.public(group));
+        let message = handshake::message(handshake::CLIENT_KEY_EXCHANGE, &body);
+        self.send(record::HANDSHAKE, &message)?;
+        transcript.extend_from_slice(&message);
+
+        let master_secret = if server_hello.extended_master_secret {
+            let session_hash = hash.digest(&[&transcript]);
+            key_schedule::prf(
+                hash,
+                &pre_master_secret,
+                "extended master secret",
+                &session_hash,
+                48,
+            )
+        } else {
+            let seed = [client_random, &server_hello.random].concat();
+            key_schedule::prf(hash, &pre_master_secret, "master secret", &seed, 48)
+        };
+        let key_len = self.suite.key_len;
+        let seed = [&server_hello.random, client_random].concat();
+        let key_block = key_schedule::prf(
+            hash,
+            &master_secret,
+            "key expansion",
+            &seed,
+            2 * key_len + 8,
+        );
+        let (client_key, rest) = key_block.split_at(key_len);
+        let (server_key, rest) = rest.split_at(key_len);
+        let (client_salt, server_salt) = rest.split_at(4);
+
+        self.send(record::CHANGE_CIPHER_SPEC, &[1])?;
+        self.write_cipher = Some(RecordCipher::tls12(client_key, client_salt));
+        let verify_data = key_schedule::prf(
+            hash,
+            &master_secret,
+            "client finished",
+            &hash.digest(&[&transcript]),
+            12,
+        );
+        let message = handshake::message(handshake::FINISHED, &verify_data);
+        self.send(record::HANDSHAKE, &message)?;
+        transcript.extend_from_slice(&message);
+
+        match self.read_message()? {
+            Message::ChangeCipherSpec if self.handshake_buf.is_empty() => {
+                self.read_cipher = Some(RecordCipher::tls12(server_key, server_salt));
+            }
+            _ => return Err(unexpected_message(record::CHANGE_CIPHER_SPEC)),
+        }
+        let message = self.expect_handshake(handshake::FINISHED)?;
+        let expected = key_schedule::prf(
+            hash,
+            &master_secret,
+            "server finished",
+            &hash.digest(&[&transcript]),
+            12,
+        );
+        if !constant_time_eq(&expected, &message[4..]) {
+            return Err(TlsError::DecryptError);
+        }
+        Ok(())
+    }
+
+    /// Parses a Certificate message and validates the chain. Returns the server's public key.
+    fn verify_certificate(
+        &self,
+        config: &ClientConfig,
+        server_name: &ServerName,
+        message: &[u8],
+        tls13: bool,
+    ) -> Result<PublicKey, TlsError> {
+        if message[0] != handshake::CERTIFICATE {
+            return Err(unexpected_message(message[0]));
+        }
+        let chain = handshake::parse_certificate(&message[4..], tls13)?
+            .iter()
+            .map(|der| Certificate::from_der(der))
+            .collect::<Result<Vec<_>, _>>()?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() as i64);
+        if config.spki_pins.is_empty() {
+            config.roots.verify_server(&chain, server_name, now)?;
+        } else {
+            x509::verify_pinned(&chain, &config.spki_pins, now)?;
+        }
+        Ok(chain[0].public_key().clone())
+    }
+
+    fn set_read_secret(&mut self, secret: &[u8]) -> Result<(), TlsError> {
+        // a key change must happen on a record boundary
+        if !self.handshake_buf.is_empty() {
+            return Err(unexpected_message(handshake::FINISHED));
+        }
+        let (key, iv) = key_schedule::traffic_keys(self.suite.hash, secret, self.suite.key_len);
+        self.read_cipher = Some(RecordCipher::tls13(&key, &iv));
+        self.read_secret = secret.to_vec();
+        Ok(())
+    }
+
+    fn set_write_secret(&mut self, secret: &[u8]) {
+        let (key, iv) = key_schedule::traffic_keys(self.suite.hash, secret, self.suite.key_len);
+        self.write_cipher = Some(RecordCipher::tls13(&key, &iv));
+        self.write_secret = secret.to_vec();
+    }
+
+    /// Sends `data` in as many records as needed.
+    fn send(&mut self, content_type: u8, data: &[u8]) -> Result<(), TlsError> {
+        let mut records = vec![];
+        for fragment in data.chunks(record::MAX_FRAGMENT_LEN) {
+            match self.write_cipher.as_mut() {
+                Some(cipher) => records.extend(cipher.seal(content_type, fragment)),
+                None => records.extend(record::plaintext_record(content_type, fragment)),
+            }
+        }
+        self.sock.write_all(&records)?;
+        Ok(())
+    }
+
+    fn read_message(&mut self) -> Result<Message, TlsError> {
+        loop {
+            if let Some(message) = self.take_handshake_message() {
+                return Ok(message);
+            }
+            let Some((content_type, payload)) = record::read_record(&mut self.sock)? else {
+                if !self.handshake_buf.is_empty() {
+                    return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+                }
+                return Ok(Message::Eof);
+            };
+            // ChangeCipherSpec is never protected
+            let (content_type, payload) = match self.read_cipher.as_mut() {
+                Some(cipher) if content_type != record::CHANGE_CIPHER_SPEC => {
+                    cipher.open(content_type, &payload)?
+                }
+                _ => (content_type, payload),
+            };
+            if payload.len() > record::MAX_FRAGMENT_LEN {
+                return Err(TlsError::Decode("record overflow".to_string()));
+            }
+            if !self.handshake_buf.is_empty() && content_type != record::HANDSHAKE {
+                return Err(unexpected_message(content_type));
+            }
+            match content_type {
+                record::HANDSHAKE if payload.is_empty() => {
+                    return Err(TlsError::Decode("empty handshake record".to_string()))
+                }
+                record::HANDSHAKE => self.handshake_buf.extend(payload),
+                record::CHANGE_CIPHER_SPEC if payload == [1] => {
+                    // TLS 1.3 peers send it for middlebox compatibility only
+                    if !self.suite.tls13 || self.read_cipher.is_none() {
+                        return Ok(Message::ChangeCipherSpec);
+                    }
+                }
+                record::ALERT => match payload[..] {
+                    [_, CLOSE_NOTIFY] => return Ok(Message::Eof),
+                    // warnings are only used by TLS 1.2
+                    [1, _] if !self.suite.tls13 => {}
+                    [_, description] => return Err(TlsError::AlertReceived(description)),
+                    _ => return Err(TlsError::Decode("malformed alert".to_string())),
+                },
+                record::APPLICATION_DATA => return Ok(Message::ApplicationData(payload)),
+                _ => return Err(unexpected_message(content_type)),
+            }
+        }
+    }
+
+    fn take_handshake_message(&mut self) -> Option<Message> {
+        if self.handshake_buf.len() < 4 {
+            return None;
+        }
+        let length = Reader::new(&self.handshake_buf[1..4]).u24().ok()?;
+        if self.handshake_buf.len() < 4 + length {
+            return None;
+        }
+        let rest = self.handshake_buf.split_off(4 + length);
+        let message = std::mem::replace(&mut self.handshake_buf, rest);
+        Some(Message::Handshake(message))
+    }
+
+    fn read_handshake(&mut self) -> Result<Vec<u8>, TlsError> {
+        match self.read_message()? {
+            Message::Handshake(message) => Ok(message),
+            Message::Eof => Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+            Message::ChangeCipherSpec => Err(unexpected_message(record::CHANGE_CIPHER_SPEC)),
+            Message::ApplicationData(_) => Err(unexpected_message(record::APPLICATION_DATA)),
+        }
+    }
+
+    fn expect_handshake(&mut self, msg_type: u8) -> Result<Vec<u8>, TlsError> {
+        let message = self.read_handshake()?;
+        if message[0] != msg_type {
+            return Err(unexpected_message(message[0]));
+        }
+        Ok(message)
+    }
+
+    /// Reads the next message after the handshake: application data becomes readable, and
+    /// handshake messages are handled.
+    fn read_record(&mut self) -> Result<(), TlsError> {
+        match self.read_message()? {
+            Message::ApplicationData(data) => {
+                self.plaintext = data;
+                self.plaintext_pos = 0;
+            }
+            Message::Handshake(message) => self.post_handshake(&message)?,
+            Message::ChangeCipherSpec => {
+                return Err(unexpected_message(record::CHANGE_CIPHER_SPEC))
+            }
+            Message::Eof => self.eof = true,
+        }
+        Ok(())
+    }
+
+    /// Handles a handshake message received after the handshake completed.
+    fn post_handshake(&mut self, message: &[u8]) -> Result<(), TlsError> {
+        match message[0] {
+            handshake::NEW_SESSION_TICKET if self.suite.tls13 => Ok(()),
+            handshake::KEY_UPDATE if self.suite.tls13 => {
+                let request_update = match message[4..] {
+                    [0] => false,
+                    [1] => true,
+                    _ => return Err(TlsError::Decode("malformed KeyUpdate".to_string())),
+                };
+                let hash = self.suite.hash;
+                let secret = key_schedule::next_traffic_secret(hash, &self.read_secret);
+                self.set_read_secret(&secret)?;
+                if request_update {
+                    self.send(
+                        record::HANDSHAKE,
+                        &handshake::message(handshake::KEY_UPDATE, &[0]),
+                    )?;
+                    let secret = key_schedule::next_traffic_secret(hash, &self.write_secret);
+                    self.set_write_secret(&secret);
+                }
+                Ok(())
+            }
+            // HelloRequest, renegotiation is not supported so it is ignored
+            0 if !self.suite.tls13 => Ok(()),
+            msg_type => Err(unexpected_message(msg_type)),
+        }
+    }
+}
+
+fn select_suite(hello: &ServerHello) -> Result<CipherSuite, TlsError> {
+    CipherSuite::from_id(hello.cipher_suite).ok_or_else(|| {
+        TlsError::HandshakeFailure(format!(
+            "server selected unsupported cipher suite {:#06x}",
+            hello.cipher_suite
+        ))
+    })
+}
+
+fn unexpected_message(msg_type: u8) -> TlsError {
+    TlsError::HandshakeFailure(format!("unexpected message type {msg_type}"))
+}
+
+impl<S: Read + Write> Read for TlsStream<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.plaintext_pos >= self.plaintext.len() {
+            if self.eof {
+                return Ok(0);
+            }
+            self.read_record()?;
+        }
+        let n = buf.len().min(self.plaintext.len() - self.plaintext_pos);
+        buf[..n].copy_from_slice(&self.plaintext[self.plaintext_pos..self.plaintext_pos + n]);
+        self.plaintext_pos += n;
+        Ok(n)
+    }
+}
+
+impl<S: Read + Write> Write for TlsStream<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.send(record::APPLICATION_DATA, buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.sock.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tls::{x509::pem_certificates, RootCertStore};
+    use std::{
+        io::{BufRead, BufReader},
+        net::{TcpListener, TcpStream},
+        path::PathBuf,
+        process::{Child, Command, Stdio},
+    };
+
+    fn testdata(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/tls/testdata")
+            .join(name)
+    }
+
+    /**
+     * openssl s_server answering one connection with its status page
+     */
+    struct Server {
+        child: Child,
+        port: u16,
+    }
+
+    impl Server {
+        /// Returns None when openssl is not installed.
+        fn start(key: &str, args: &[&str]) -> Option<Self> {
+            let port = TcpListener::bind("127.0.0.1:0")
+                .and_then(|listener| listener.local_addr())
+                .ok()?
+                .port();
+            let mut child = Command::new("openssl")
+                .args(["s_server", "-www", "-naccept", "1", "-accept"])
+                .arg(port.to_string())
+                .arg("-cert")
+                .arg(testdata(&format!("{key}.pem")))
+                .arg("-key")
+                .arg(testdata(&format!("{key}.key")))
+                .arg("-cert_chain")
+                .arg(testdata("ca.pem"))
+                .args(args)
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .ok()?;
+            // s_server prints ACCEPT once it listens
+            let stdout = BufReader::new(child.stdout.take()?);
+            for line in stdout.lines() {
+                if line.ok()?.starts_with("ACCEPT") {
+                    return Some(Self { child, port });
+                }
+            }
+            None
+        }
+
+        fn get(&self, config: &ClientConfig, name: &str) -> Result<String, TlsError> {
+            let sock = TcpStream::connect(("127.0.0.1", self.port))?;
+            let mut stream = TlsStream::connect(config, &ServerName::new(name), sock)?;
+            stream.write_all(b"GET / HTTP/1.0\r\n\r\n")?;
+            let mut response = String::new();
+            stream.read_to_string(&mut response)?;
+            Ok(response)
+        }
+    }
+
+    impl Drop for Server {
+        fn drop(&mut self) {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+
+    fn config() -> ClientConfig {
+        let mut roots = RootCertStore::empty();
+        assert_eq!(roots.add_pem_file(testdata("ca.pem")).unwrap(), 1);
+        ClientConfig::new(roots)
+    }
+
+    #[test]
+    fn test_tls13() {
+        for (key, args) in [
+            ("ec", &["-tls1_3"][..]),
+            (
+                "rsa",
+                &["-tls1_3", "-ciphersuites", "TLS_AES_256_GCM_SHA384"],
+            ),
+            // answers the first ClientHello with a HelloRetryRequest carrying a cookie
+            ("ec", &["-tls1_3", "-stateless"]),
+        ] {
+            let Some(server) = Server::start(key, args) else {
+                return;
+            };
+            let response = server.get(&config(), "localhost").unwrap();
+            assert!(response.starts_with("HTTP/1.0 200 ok"), "{response}");
+            assert!(response.contains("TLSv1.3"), "{response}");
+        }
+    }
+
+    #[test]
+    fn test_tls12() {
+        for (key, cipher) in [
+            ("ec", "ECDHE-ECDSA-AES128-GCM-SHA256"),
+            ("rsa", "ECDHE-RSA-AES256-GCM-SHA384"),
+        ] {
+            let Some(server) = Server::start(key, &["-tls1_2", "-cipher", cipher]) else {
+                return;
+            };
+            let response = server.get(&config(), "127.0.0.1").unwrap();
+            assert!(response.contains(cipher), "{response}");
+        }
+    }
+
+    #[test]
+    fn test_rejects_invalid_certificate() {
+        let Some(server) = Server::start("ec", &[]) else {
+            return;
+        };
+        let result = server.get(&config(), "example.com");
+        assert!(matches!(result, Err(TlsError::BadCertificate(_))));
+        let Some(server) = Server::start("ec", &[]) else {
+            return;
+        };
+        let result = server.get(&ClientConfig::new(RootCertStore::empty()), "localhost");
+        assert!(matches!(result, Err(TlsError::BadCertificate(_))));
+    }
+
+    #[test]
+    fn test_spki_pins() {
+        let key = |name: &str| {
+            let pem = std::fs::read_to_string(testdata(&format!("{name}.pem"))).unwrap();
+            Certificate::from_der(&pem_certificates(&pem).unwrap()[0])
+                .unwrap()
+                .spki_sha256()
+        };
+        let mut config = ClientConfig::new(RootCertStore::empty());
+        // the pin replaces the checks of the name and the roots
+        for pin in [key("ec"), key("ca")] {
+            config.spki_pins = vec![[0; 32], pin];
+            let Some(server) = Server::start("ec", &[]) else {
+                return;
+            };
+            let response = server.get(&config, "example.com").unwrap();
+            assert!(response.starts_with("HTTP/1.0 200 ok"), "{response}");
+        }
+
+        // a self-signed certificate followed by the pinned CA certificate it isn't issued by
+        let Some(server) = Server::start("forged", &[]) else {
+            return;
+        };
+        let result = server.get(&config, "example.com");
+        assert!(matches!(result, Err(TlsError::BadCertificate(_))));
+
+        config.spki_pins.remove(1);
+        let Some(server) = Server::start("ec", &[]) else {
+            return;
+        };
+        let result = server.get(&config, "localhost");
+        assert!(matches!(result, Err(TlsError::BadCertificate(_))));
+    }
+}
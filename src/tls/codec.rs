@@ -0,0 +1,132 @@
+use super::TlsError;
+
+/**
+ * Cursor over a TLS structure with the length-prefixed vectors of RFC 8446 section 3.4
+ */
+#[derive(Debug, Clone)]
+pub struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offset >= self.data.len()
+    }
+
+    pub fn bytes(&mut self, n: usize) -> Result<&'a [u8], TlsError> {
+        let end = self
+            .offset
+            .checked_add(n)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| TlsError::Decode("message is too short".to_string()))?;
+        let bytes = &self.data[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    pub fn rest(&mut self) -> &'a [u8] {
+        let bytes = &self.data[self.offset.min(self.data.len())..];
+        self.offset = self.data.len();
+        bytes
+    }
+
+    pub fn u8(&mut self) -> Result<u8, TlsError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, TlsError> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    pub fn u24(&mut self) -> Result<usize, TlsError> {
+        let b = self.bytes(3)?;
+        Ok(((b[0] as usize) << 16) | ((b[1] as usize) << 8) | b[2] as usize)
+    }
+
+    /// opaque data<0..2^8-1>
+    pub fn vec_u8(&mut self) -> Result<&'a [u8], TlsError> {
+        let n = self.u8()? as usize;
+        self.bytes(n)
+    }
+
+    /// opaque data<0..2^16-1>
+    pub fn vec_u16(&mut self) -> Result<&'a [u8], TlsError> {
+        let n = self.u16()? as usize;
+        self.bytes(n)
+    }
+
+    /// opaque data<0..2^24-1>
+    pub fn vec_u24(&mut self) -> Result<&'a [u8], TlsError> {
+        let n = self.u24()?;
+        self.bytes(n)
+    }
+
+    /// Fails if any bytes are left over.
+    pub fn finish(&self) -> Result<(), TlsError> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(TlsError::Decode("trailing data in message".to_string()))
+        }
+    }
+}
+
+pub fn put_u16(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_be_bytes());
+}
+
+pub fn put_u24(buf: &mut Vec<u8>, value: usize) {
+    buf.extend_from_slice(&(value as u32).to_be_bytes()[1..]);
+}
+
+pub fn put_vec_u8(buf: &mut Vec<u8>, data: &[u8]) {
+    buf.push(data.len() as u8);
+    buf.extend_from_slice(data);
+}
+
+pub fn put_vec_u16(buf: &mut Vec<u8>, data: &[u8]) {
+    put_u16(buf, data.len() as u16);
+    buf.extend_from_slice(data);
+}
+
+pub fn put_vec_u24(buf: &mut Vec<u8>, data: &[u8]) {
+    put_u24(buf, data.len());
+    buf.extend_from_slice(data);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reader() {
+        let data = [0x01, 0x00, 0x02, 0x00, 0x00, 0x01, 0xff, 0x02, 0xaa, 0xbb];
+        let mut reader = Reader::new(&data);
+        assert_eq!(reader.u8().unwrap(), 1);
+        assert_eq!(reader.u16().unwrap(), 2);
+        assert_eq!(reader.vec_u24().unwrap(), &[0xff]);
+        assert!(reader.finish().is_err());
+        assert_eq!(reader.vec_u8().unwrap(), &[0xaa, 0xbb]);
+        assert!(reader.finish().is_ok());
+        assert!(reader.u8().is_err());
+        assert!(Reader::new(&[0x00, 0x05, 0x01]).vec_u16().is_err());
+    }
+
+    #[test]
+    fn test_writer() {
+        let mut buf = vec![];
+        put_u16(&mut buf, 0x0303);
+        put_vec_u8(&mut buf, &[1, 2]);
+        put_vec_u24(&mut buf, &[3]);
+        assert_eq!(
+            buf,
+            vec![0x03, 0x03, 0x02, 0x01, 0x02, 0x00, 0x00, 0x01, 0x03]
+        );
+    }
+}
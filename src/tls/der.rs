@@ -0,0 +1,210 @@
+use super::TlsError;
+
+pub const BOOLEAN: u8 = 0x01;
+pub const INTEGER: u8 = 0x02;
+pub const BIT_STRING: u8 = 0x03;
+pub const OCTET_STRING: u8 = 0x04;
+pub const OID: u8 = 0x06;
+pub const UTC_TIME: u8 = 0x17;
+pub const GENERALIZED_TIME: u8 = 0x18;
+pub const SEQUENCE: u8 = 0x30;
+
+/// Context-specific constructed tag [n], as used for EXPLICIT tagging.
+pub const fn explicit(n: u8) -> u8 {
+    0xa0 | n
+}
+
+/// Context-specific primitive tag [n], as used for IMPLICIT tagging of primitive types.
+pub const fn implicit(n: u8) -> u8 {
+    0x80 | n
+}
+
+/**
+ * Reader over a sequence of DER encoded TLVs (ITU-T X.690), restricted to single byte tags
+ */
+#[derive(Debug, Clone)]
+pub struct Der<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Der<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn peek_tag(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    /// Reads the next TLV and returns its tag, its value and the whole encoding.
+    pub fn read(&mut self) -> Result<(u8, &'a [u8], &'a [u8]), TlsError> {
+        let error = || TlsError::Decode("malformed DER".to_string());
+        let tag = *self.data.first().ok_or_else(error)?;
+        if tag & 0x1f == 0x1f {
+            return Err(error());
+        }
+        let first = *self.data.get(1).ok_or_else(error)? as usize;
+        let (length, header) = if first < 0x80 {
+            (first, 2)
+        } else {
+            let n = first & 0x7f;
+            if n == 0 || n > 4 {
+                // indefinite or absurdly long lengths
+                return Err(error());
+            }
+            let bytes = self.data.get(2..2 + n).ok_or_else(error)?;
+            if bytes[0] == 0 {
+                return Err(error());
+            }
+            let length = bytes.iter().fold(0usize, |acc, b| (acc << 8) | *b as usize);
+            if length < 0x80 {
+                return Err(error());
+            }
+            (length, 2 + n)
+        };
+        let end = header.checked_add(length).ok_or_else(error)?;
+        if end > self.data.len() {
+            return Err(error());
+        }
+        let (tlv, rest) = self.data.split_at(end);
+        self.data = rest;
+        Ok((tag, &tlv[header..], tlv))
+    }
+
+    /// Reads the next TLV, failing unless it has the expected tag. Returns its value.
+    pub fn expect(&mut self, tag: u8) -> Result<&'a [u8], TlsError> {
+        match self.read()? {
+            (t, value, _) if t == tag => Ok(value),
+            (t, _, _) => Err(TlsError::Decode(format!(
+                "unexpected DER tag {t:#04x}, expected {tag:#04x}"
+            ))),
+        }
+    }
+
+    /// Reads the next TLV only if it has the given tag.
+    pub fn optional(&mut self, tag: u8) -> Result<Option<&'a [u8]>, TlsError> {
+        if self.peek_tag() == Some(tag) {
+            self.expect(tag).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn sequence(&mut self) -> Result<Der<'a>, TlsError> {
+        self.expect(SEQUENCE).map(Der::new)
+    }
+
+    /// Reads a non-negative INTEGER and returns its big-endian magnitude without leading zeros.
+    pub fn unsigned_integer(&mut self) -> Result<&'a [u8], TlsError> {
+        let value = self.expect(INTEGER)?;
+        if value.is_empty() || value[0] & 0x80 != 0 {
+            return Err(TlsError::Decode("expected a positive INTEGER".to_string()));
+        }
+        let start = value.iter().position(|b| *b != 0).unwrap_or(value.len());
+        Ok(&value[start..])
+    }
+
+    /// Reads a BIT STRING without unused bits.
+    pub fn bit_string(&mut self) -> Result<&'a [u8], TlsError> {
+        match self.expect(BIT_STRING)?.split_first() {
+            Some((0, bits)) => Ok(bits),
+            _ => Err(TlsError::Decode("unsupported BIT STRING".to_string())),
+        }
+    }
+
+    pub fn finish(&self) -> Result<(), TlsError> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(TlsError::Decode("trailing data in DER".to_string()))
+        }
+    }
+}
+
+/// Parses UTCTime or GeneralizedTime in the "Z" form required by RFC 5280 into seconds since the Unix epoch.
+pub fn parse_time(tag: u8, value: &[u8]) -> Result<i64, TlsError> {
+    let error = || TlsError::Decode("invalid time".to_string());
+    let s = std::str::from_utf8(value).map_err(|_| error())?;
+    let s = s.strip_suffix('Z').ok_or_else(error)?;
+    if !s.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(error());
+    }
+    let (year, rest) = match (tag, s.len()) {
+        (UTC_TIME, 12) => {
+            let yy: i64 = s[..2].parse().map_err(|_| error())?;
+            (if yy >= 50 { 1900 + yy } else { 2000 + yy }, &s[2..])
+        }
+        (GENERALIZED_TIME, 14) => (s[..4].parse().map_err(|_| error())?, &s[4..]),
+        _ => return Err(error()),
+    };
+    let field = |i: usize| -> i64 { rest[i..i + 2].parse().unwrap_or(-1) };
+    let (month, day, hour, minute, second) = (field(0), field(2), field(4), field(6), field(8));
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        return Err(error());
+    }
+    Ok(days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+/// Number of days since 1970-01-01 of a proleptic Gregorian date.
+pub fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_der_read() {
+        let data = [0x30, 0x06, 0x02, 0x01, 0x05, 0x04, 0x01, 0xaa, 0x05, 0x00];
+        let mut der = Der::new(&data);
+        let mut seq = der.sequence().unwrap();
+        assert_eq!(seq.unsigned_integer().unwrap(), &[0x05]);
+        assert_eq!(seq.optional(INTEGER).unwrap(), None);
+        assert_eq!(seq.optional(OCTET_STRING).unwrap(), Some(&[0xaa][..]));
+        assert!(seq.finish().is_ok());
+        assert!(der.expect(INTEGER).is_err());
+
+        // long form length, and truncated input
+        let mut long = vec![0x04, 0x81, 0x80];
+        long.extend([0; 128]);
+        assert_eq!(Der::new(&long).expect(OCTET_STRING).unwrap().len(), 128);
+        assert!(Der::new(&long[..100]).read().is_err());
+        // non-minimal length encoding is not DER
+        assert!(Der::new(&[0x04, 0x81, 0x01, 0x00]).read().is_err());
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time(UTC_TIME, b"700101000000Z").ok(), Some(0));
+        assert_eq!(
+            parse_time(UTC_TIME, b"491231235959Z").ok(),
+            Some(2524607999)
+        );
+        assert_eq!(
+            parse_time(GENERALIZED_TIME, b"20500101000000Z").ok(),
+            Some(2524608000)
+        );
+        assert_eq!(
+            parse_time(UTC_TIME, b"240229120000Z").ok(),
+            Some(1709208000)
+        );
+        assert!(parse_time(UTC_TIME, b"20500101000000Z").is_err());
+        assert!(parse_time(GENERALIZED_TIME, b"20501301000000Z").is_err());
+        assert!(parse_time(UTC_TIME, b"700101000000+0100").is_err());
+    }
+}
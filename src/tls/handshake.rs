@@ -0,0 +1,358 @@
+use super::{
+    codec::{put_u16, put_vec_u16, put_vec_u24, put_vec_u8, Reader},
+    x509::SignatureAlgorithm,
+    TlsError,
+};
+use crate::crypto::{sha2::Sha256, HashAlgorithm};
+
+// handshake message types
+pub const CLIENT_HELLO: u8 = 1;
+pub const SERVER_HELLO: u8 = 2;
+pub const NEW_SESSION_TICKET: u8 = 4;
+pub const ENCRYPTED_EXTENSIONS: u8 = 8;
+pub const CERTIFICATE: u8 = 11;
+pub const SERVER_KEY_EXCHANGE: u8 = 12;
+pub const CERTIFICATE_REQUEST: u8 = 13;
+pub const SERVER_HELLO_DONE: u8 = 14;
+pub const CERTIFICATE_VERIFY: u8 = 15;
+pub const CLIENT_KEY_EXCHANGE: u8 = 16;
+pub const FINISHED: u8 = 20;
+pub const KEY_UPDATE: u8 = 24;
+pub const MESSAGE_HASH: u8 = 254;
+
+// extension types
+const SERVER_NAME: u16 = 0;
+const SUPPORTED_GROUPS: u16 = 10;
+const EC_POINT_FORMATS: u16 = 11;
+const SIGNATURE_ALGORITHMS: u16 = 13;
+const EXTENDED_MASTER_SECRET: u16 = 23;
+const SUPPORTED_VERSIONS: u16 = 43;
+const COOKIE: u16 = 44;
+const KEY_SHARE: u16 = 51;
+const RENEGOTIATION_INFO: u16 = 0xff01;
+
+pub const TLS12: u16 = 0x0303;
+pub const TLS13: u16 = 0x0304;
+
+/// Named groups for (EC)DHE
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NamedGroup {
+    Secp256r1,
+    X25519,
+}
+
+impl NamedGroup {
+    pub fn id(&self) -> u16 {
+        match self {
+            NamedGroup::Secp256r1 => 23,
+            NamedGroup::X25519 => 29,
+        }
+    }
+
+    pub fn from_id(id: u16) -> Option<Self> {
+        match id {
+            23 => Some(NamedGroup::Secp256r1),
+            29 => Some(NamedGroup::X25519),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * Cipher suites offered by the client. All of them use AES-GCM.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CipherSuite {
+    pub id: u16,
+    pub hash: HashAlgorithm,
+    pub key_len: usize,
+    pub tls13: bool,
+}
+
+pub const CIPHER_SUITES: [CipherSuite; 6] = [
+    // TLS_AES_128_GCM_SHA256
+    CipherSuite::new(0x1301, HashAlgorithm::Sha256, 16, true),
+    // TLS_AES_256_GCM_SHA384
+    CipherSuite::new(0x1302, HashAlgorithm::Sha384, 32, true),
+    // TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256
+    CipherSuite::new(0xc02b, HashAlgorithm::Sha256, 16, false),
+    // TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256
+    CipherSuite::new(0xc02f, HashAlgorithm::Sha256, 16, false),
+    // TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384
+    CipherSuite::new(0xc02c, HashAlgorithm::Sha384, 32, false),
+    // TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384
+    CipherSuite::new(0xc030, HashAlgorithm::Sha384, 32, false),
+];
+
+impl CipherSuite {
+    const fn new(id: u16, hash: HashAlgorithm, key_len: usize, tls13: bool) -> Self {
+        Self {
+            id,
+            hash,
+            key_len,
+            tls13,
+        }
+    }
+
+    pub fn from_id(id: u16) -> Option<Self> {
+        CIPHER_SUITES.iter().find(|suite| suite.id == id).copied()
+    }
+}
+
+/// Signature schemes offered in the signature_algorithms extension
+const SIGNATURE_SCHEMES: [u16; 8] = [
+    0x0403, // ecdsa_secp256r1_sha256
+    0x0503, // ecdsa_secp384r1_sha384
+    0x0804, // rsa_pss_rsae_sha256
+    0x0805, // rsa_pss_rsae_sha384
+    0x0806, // rsa_pss_rsae_sha512
+    0x0401, // rsa_pkcs1_sha256
+    0x0501, // rsa_pkcs1_sha384
+    0x0601, // rsa_pkcs1_sha512
+];
+
+/// Maps a TLS SignatureScheme to a signature algorithm. PKCS#1 v1.5 is not allowed in TLS 1.3 handshakes.
+pub fn signature_algorithm(scheme: u16, tls13: bool) -> Result<SignatureAlgorithm, TlsError> {
+    let algorithm = match scheme {
+        0x0403 => SignatureAlgorithm::Ecdsa(HashAlgorithm::Sha256),
+        0x0503 => SignatureAlgorithm::Ecdsa(HashAlgorithm::Sha384),
+        0x0804 => SignatureAlgorithm::RsaPss(HashAlgorithm::Sha256),
+        0x0805 => SignatureAlgorithm::RsaPss(HashAlgorithm::Sha384),
+        0x0806 => SignatureAlgorithm::RsaPss(HashAlgorithm::Sha512),
+        0x0401 if !tls13 => SignatureAlgorithm::RsaPkcs1(HashAlgorithm::Sha256),
+        0x0501 if !tls13 => SignatureAlgorithm::RsaPkcs1(HashAlgorithm::Sha384),
+        0x0601 if !tls13 => SignatureAlgorithm::RsaPkcs1(HashAlgorithm::Sha512),
+        _ => {
+            return Err(TlsError::HandshakeFailure(format!(
+                "server used unsupported signature scheme {scheme:#06x}"
+            )))
+        }
+    };
+    Ok(algorithm)
+}
+
+/// Prepends the handshake message header (type and 24-bit length).
+pub fn message(msg_type: u8, body: &[u8]) -> Vec<u8> {
+    let mut msg = vec![msg_type];
+    put_vec_u24(&mut msg, body);
+    msg
+}
+
+/**
+ * ClientHello offering TLS 1.2 and 1.3 with the key shares in `key_shares`
+ */
+#[derive(Debug, Clone)]
+pub struct ClientHello<'a> {
+    pub random: [u8; 32],
+    pub session_id: [u8; 32],
+    pub server_name: Option<&'a str>,
+    pub key_shares: Vec<(NamedGroup, Vec<u8>)>,
+    pub cookie: Option<Vec<u8>>,
+}
+
+impl ClientHello<'_> {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut body = vec![];
+        put_u16(&mut body, TLS12); // legacy_version
+        body.extend_from_slice(&self.random);
+        put_vec_u8(&mut body, &self.session_id);
+        let suites: Vec<u8> = CIPHER_SUITES
+            .iter()
+            .flat_map(|s| s.id.to_be_bytes())
+            .collect();
+        put_vec_u16(&mut body, &suites);
+        put_vec_u8(&mut body, &[0]); // null compression
+
+        let mut extensions = vec![];
+        if let Some(name) = self.server_name {
+            let mut entry = vec![0]; // host_name
+            put_vec_u16(&mut entry, name.as_bytes());
+            let mut list = vec![];
+            put_vec_u16(&mut list, &entry);
+            extension(&mut extensions, SERVER_NAME, &list);
+        }
+        let groups: Vec<u8> = [NamedGroup::X25519, NamedGroup::Secp256r1]
+            .iter()
+            .flat_map(|g| g.id().to_be_bytes())
+            .collect();
+        extension(&mut extensions, SUPPORTED_GROUPS, &vec_u16(&groups));
+        extension(&mut extensions, EC_POINT_FORMATS, &[1, 0]); // uncompressed
+        let schemes: Vec<u8> = SIGNATURE_SCHEMES
+            .iter()
+            .flat_map(|s| s.to_be_bytes())
+            .collect();
+        extension(&mut extensions, SIGNATURE_ALGORITHMS, &vec_u16(&schemes));
+        extension(&mut extensions, EXTENDED_MASTER_SECRET, &[]);
+        extension(&mut extensions, RENEGOTIATION_INFO, &[0]);
+        let mut versions = vec![];
+        put_u16(&mut versions, TLS13);
+        put_u16(&mut versions, TLS12);
+        let mut data = vec![];
+        put_vec_u8(&mut data, &versions);
+        extension(&mut extensions, SUPPORTED_VERSIONS, &data);
+        if let Some(cookie) = &self.cookie {
+            extension(&mut extensions, COOKIE, &vec_u16(cookie));
+        }
+        let mut shares = vec![];
+        for (group, public) in &self.key_shares {
+            put_u16(&mut shares, group.id());
+            put_vec_u16(&mut shares, public);
+        }
+        extension(&mut extensions, KEY_SHARE, &vec_u16(&shares));
+        put_vec_u16(&mut body, &extensions);
+        message(CLIENT_HELLO, &body)
+    }
+}
+
+fn vec_u16(data: &[u8]) -> Vec<u8> {
+    let mut v = vec![];
+    put_vec_u16(&mut v, data);
+    v
+}
+
+fn extension(buf: &mut Vec<u8>, ext_type: u16, data: &[u8]) {
+    put_u16(buf, ext_type);
+    put_vec_u16(buf, data);
+}
+
+/// SHA-256 of "HelloRetryRequest", the special ServerHello.random of a HelloRetryRequest
+pub fn hello_retry_request_random() -> Vec<u8> {
+    Sha256::digest(b"HelloRetryRequest")
+}
+
+/**
+ * ServerHello, which is also used for HelloRetryRequest in TLS 1.3
+ */
+#[derive(Debug, Clone, Default)]
+pub struct ServerHello {
+    pub legacy_version: u16,
+    pub random: Vec<u8>,
+    pub session_id: Vec<u8>,
+    pub cipher_suite: u16,
+    pub selected_version: Option<u16>,
+    pub key_share: Option<(u16, Vec<u8>)>, // in a HelloRetryRequest only the group is present
+    pub cookie: Option<Vec<u8>>,
+    pub extended_master_secret: bool,
+    pub renegotiation_info: Option<Vec<u8>>,
+}
+
+impl ServerHello {
+    pub fn parse(body: &[u8]) -> Result<Self, TlsError> {
+        let mut reader = Reader::new(body);
+        let mut hello = ServerHello {
+            legacy_version: reader.u16()?,
+            random: reader.bytes(32)?.to_vec(),
+            session_id: reader.vec_u8()?.to_vec(),
+            cipher_suite: reader.u16()?,
+            ..Default::default()
+        };
+        if reader.u8()? != 0 {
+            return Err(TlsError::HandshakeFailure(
+                "server selected compression".to_string(),
+            ));
+        }
+        let is_retry = hello.is_retry_request();
+        if !reader.is_empty() {
+            let mut extensions = Reader::new(reader.vec_u16()?);
+            while !extensions.is_empty() {
+                let ext_type = extensions.u16()?;
+                let mut data = Reader::new(extensions.vec_u16()?);
+                match ext_type {
+                    SUPPORTED_VERSIONS => hello.selected_version = Some(data.u16()?),
+                    KEY_SHARE if is_retry => hello.key_share = Some((data.u16()?, vec![])),
+                    KEY_SHARE => hello.key_share = Some((data.u16()?, data.vec_u16()?.to_vec())),
+                    COOKIE => hello.cookie = Some(data.vec_u16()?.to_vec()),
+                    EXTENDED_MASTER_SECRET => hello.extended_master_secret = true,
+                    RENEGOTIATION_INFO => hello.renegotiation_info = Some(data.vec_u8()?.to_vec()),
+                    _ => {
+                        data.rest();
+                    }
+                }
+                data.finish()?;
+            }
+        }
+        reader.finish()?;
+        Ok(hello)
+    }
+
+    pub fn is_retry_request(&self) -> bool {
+        self.random == hello_retry_request_random()
+    }
+}
+
+/// Parses a Certificate message into the DER encoded certificates, end-entity first.
+pub fn parse_certificate(body: &[u8], tls13: bool) -> Result<Vec<Vec<u8>>, TlsError> {
+    let mut reader = Reader::new(body);
+    if tls13 {
+        reader.vec_u8()?; // certificate_request_context
+    }
+    let mut list = Reader::new(reader.vec_u24()?);
+    reader.finish()?;
+    let mut certificates = vec![];
+    while !list.is_empty() {
+        certificates.push(list.vec_u24()?.to_vec());
+        if tls13 {
+            list.vec_u16()?; // per certificate extensions
+        }
+    }
+    Ok(certificates)
+}
+
+/// Parses CertificateVerify (TLS 1.3) or the signature part of ServerKeyExchange (TLS 1.2).
+pub fn parse_signature<'a>(reader: &mut Reader<'a>) -> Result<(u16, &'a [u8]), TlsError> {
+    let scheme = reader.u16()?;
+    let signature = reader.vec_u16()?;
+    reader.finish()?;
+    Ok((scheme, signature))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_hello_encode() {
+        let hello = ClientHello {
+            random: [1; 32],
+            session_id: [2; 32],
+            server_name: Some("example.com"),
+            key_shares: vec![(NamedGroup::X25519, vec![3; 32])],
+            cookie: None,
+        };
+        let msg = hello.encode();
+        assert_eq!(msg[0], CLIENT_HELLO);
+        let mut reader = Reader::new(&msg[1..]);
+        let body = reader.vec_u24().unwrap();
+        assert!(reader.finish().is_ok());
+        let mut reader = Reader::new(body);
+        assert_eq!(reader.u16().unwrap(), TLS12);
+        assert_eq!(reader.bytes(32).unwrap(), &[1; 32]);
+        assert_eq!(reader.vec_u8().unwrap(), &[2; 32]);
+        assert_eq!(reader.vec_u16().unwrap().len(), 2 * CIPHER_SUITES.len());
+        assert_eq!(reader.vec_u8().unwrap(), &[0]);
+        let mut extensions = Reader::new(reader.vec_u16().unwrap());
+        assert!(reader.finish().is_ok());
+        let mut types = vec![];
+        while !extensions.is_empty() {
+            types.push(extensions.u16().unwrap());
+            extensions.vec_u16().unwrap();
+        }
+        assert!(types.contains(&SERVER_NAME));
+        assert_eq!(types.last(), Some(&KEY_SHARE));
+    }
+
+    #[test]
+    fn test_server_hello_parse() {
+        let mut body = vec![0x03, 0x03];
+        body.extend(hello_retry_request_random());
+        body.extend([0x00, 0x13, 0x01, 0x00]);
+        body.extend([0x00, 0x0c, 0x00, 0x2b, 0x00, 0x02, 0x03, 0x04]);
+        body.extend([0x00, 0x33, 0x00, 0x02, 0x00, 0x17]);
+        let hello = ServerHello::parse(&body).unwrap();
+        assert!(hello.is_retry_request());
+        assert_eq!(hello.cipher_suite, 0x1301);
+        assert_eq!(hello.selected_version, Some(TLS13));
+        assert_eq!(hello.key_share, Some((23, vec![])));
+        assert!(ServerHello::parse(&body[..body.len() - 1]).is_err());
+    }
+}
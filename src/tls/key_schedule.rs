@@ -0,0 +1,171 @@
+use super::codec::{put_u16, put_vec_u8};
+use crate::crypto::{
+    hmac::{hkdf_expand, hkdf_extract, hmac},
+    HashAlgorithm,
+};
+
+/// HKDF-Expand-Label (RFC 8446 section 7.1)
+pub fn expand_label(
+    hash: HashAlgorithm,
+    secret: &[u8],
+    label: &str,
+    context: &[u8],
+    length: usize,
+) -> Vec<u8> {
+    let mut info = vec![];
+    put_u16(&mut info, length as u16);
+    put_vec_u8(&mut info, format!("tls13 {label}").as_bytes());
+    put_vec_u8(&mut info, context);
+    hkdf_expand(hash, secret, &info, length)
+}
+
+/// Derive-Secret (RFC 8446 section 7.1) over an already computed transcript hash
+pub fn derive_secret(
+    hash: HashAlgorithm,
+    secret: &[u8],
+    label: &str,
+    transcript_hash: &[u8],
+) -> Vec<u8> {
+    expand_label(hash, secret, label, transcript_hash, hash.output_len())
+}
+
+/**
+ * TLS 1.3 key schedule without pre-shared keys
+ *
+ * ```text
+ *       0 -> HKDF-Extract = Early Secret
+ * (EC)DHE -> HKDF-Extract = Handshake Secret
+ *       0 -> HKDF-Extract = Master Secret
+ * ```
+ */
+#[derive(Debug, Clone)]
+pub struct KeySchedule {
+    hash: HashAlgorithm,
+    secret: Vec<u8>,
+}
+
+impl KeySchedule {
+    /// Computes the handshake secret from the (EC)DHE shared secret.
+    pub fn new(hash: HashAlgorithm, shared_secret: &[u8]) -> Self {
+        let zeros = vec![0u8; hash.output_len()];
+        let early_secret = hkdf_extract(hash, &zeros, &zeros);
+        let derived = derive_secret(hash, &early_secret, "derived", &hash.digest(&[]));
+        Self {
+            hash,
+            secret: hkdf_extract(hash, &derived, shared_secret),
+        }
+    }
+
+    /// Returns the client and server handshake traffic secrets.
+    pub fn handshake_traffic_secrets(&self, transcript_hash: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        (
+            derive_secret(self.hash, &self.secret, "c hs traffic", transcript_hash),
+            derive_secret(self.hash, &self.secret, "s hs traffic", transcript_hash),
+        )
+    }
+
+    /// Moves from the handshake secret to the master secret.
+    pub fn into_master(self) -> Self {
+        let zeros = vec![0u8; self.hash.output_len()];
+        let derived = derive_secret(self.hash, &self.secret, "derived", &self.hash.digest(&[]));
+        Self {
+            hash: self.hash,
+            secret: hkdf_extract(self.hash, &derived, &zeros),
+        }
+    }
+
+    /// Returns the client and server application traffic secrets. Only valid on the master secret.
+    pub fn application_traffic_secrets(&self, transcript_hash: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        (
+            derive_secret(self.hash, &self.secret, "c ap traffic", transcript_hash),
+            derive_secret(self.hash, &self.secret, "s ap traffic", transcript_hash),
+        )
+    }
+}
+
+/// verify_data of a TLS 1.3 Finished message
+pub fn finished_verify_data(
+    hash: HashAlgorithm,
+    traffic_secret: &[u8],
+    transcript_hash: &[u8],
+) -> Vec<u8> {
+    let finished_key = expand_label(hash, traffic_secret, "finished", &[], hash.output_len());
+    hmac(hash, &finished_key, &[transcript_hash])
+}
+
+/// Key and IV of a TLS 1.3 traffic secret
+pub fn traffic_keys(
+    hash: HashAlgorithm,
+    traffic_secret: &[u8],
+    key_len: usize,
+) -> (Vec<u8>, Vec<u8>) {
+    (
+        expand_label(hash, traffic_secret, "key", &[], key_len),
+        expand_label(hash, traffic_secret, "iv", &[], 12),
+    )
+}
+
+/// The next generation of a TLS 1.3 traffic secret after a KeyUpdate
+pub fn next_traffic_secret(hash: HashAlgorithm, traffic_secret: &[u8]) -> Vec<u8> {
+    expand_label(hash, traffic_secret, "traffic upd", &[], hash.output_len())
+}
+
+/// The TLS 1.2 pseudorandom function P_hash (RFC 5246 section 5)
+pub fn prf(hash: HashAlgorithm, secret: &[u8], label: &str, seed: &[u8], length: usize) -> Vec<u8> {
+    let mut output = Vec::with_capacity(length + hash.output_len());
+    let mut a = hmac(hash, secret, &[label.as_bytes(), seed]);
+    while output.len() < length {
+        output.extend(hmac(hash, secret, &[&a, label.as_bytes(), seed]));
+        a = hmac(hash, secret, &[&a]);
+    }
+    output.truncate(length);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::tests::hex;
+
+    #[test]
+    fn test_tls13_key_schedule() {
+        // values from the simple 1-RTT handshake of RFC 8448 section 3
+        let hash = HashAlgorithm::Sha256;
+        let shared_secret = hex("8bd4054fb55b9d63fdfbacf9f04b9f0d35e6d63f537563efd46272900f89492d");
+        let hello_hash = hex("860c06edc07858ee8e78f0e7428c58edd6b43f2ca3e6e95f02ed063cf0e1cad8");
+        let schedule = KeySchedule::new(hash, &shared_secret);
+        assert_eq!(
+            schedule.secret,
+            hex("1dc826e93606aa6fdc0aadc12f741b01046aa6b99f691ed221a9f0ca043fbeac")
+        );
+        let (client, server) = schedule.handshake_traffic_secrets(&hello_hash);
+        assert_eq!(
+            client,
+            hex("b3eddb126e067f35a780b3abf45e2d8f3b1a950738f52e9600746a0e27a55a21")
+        );
+        assert_eq!(
+            server,
+            hex("b67b7d690cc16c4e75e54213cb2d37b4e9c912bcded9105d42befd59d391ad38")
+        );
+        let (key, iv) = traffic_keys(hash, &server, 16);
+        assert_eq!(key, hex("3fce516009c21727d0f2e4e86ee403bc"));
+        assert_eq!(iv, hex("5d313eb2671276ee13000b30"));
+        assert_eq!(
+            schedule.into_master().secret,
+            hex("18df06843d13a08bf2a449844c5f8a478001bc4d4c627984d5a41da8d0402919")
+        );
+    }
+
+    #[test]
+    fn test_tls12_prf() {
+        let output = prf(
+            HashAlgorithm::Sha256,
+            &hex("9bbe436ba940f017b17652849a71db35"),
+            "test label",
+            &hex("a0ba9f936cda311827a6f796ffd5198c"),
+            100,
+        );
+        assert_eq!(output.len(), 100);
+        assert_eq!(&output[..16], &hex("e3f229ba727be17b8d122620557cd453"));
+    }
+}
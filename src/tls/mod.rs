@@ -0,0 +1,129 @@
+//!
+//! TLS 1.2 and 1.3 client (RFC 5246, RFC 8446) used for HTTPS.
+//!
+//! Only the parts needed by this crate are implemented: ECDHE key exchange over x25519 or
+//! secp256r1, AES-GCM cipher suites, and server authentication with RSA or ECDSA certificates.
+//! Client certificates, session resumption and renegotiation are not supported.
+//!
+//! The key exchange and AES primitives in `crate::crypto` are not constant-time: scalar
+//! multiplication branches on key bits and AES uses lookup tables. Someone who can measure the
+//! timing of this process closely, e.g. from the same host, may be able to recover keys.
+//!
+mod client;
+mod codec;
+mod der;
+mod handshake;
+mod key_schedule;
+mod record;
+mod x509;
+
+use std::{fmt::Display, io, net::IpAddr};
+
+pub use client::TlsStream;
+pub use x509::RootCertStore;
+
+#[derive(Debug)]
+pub enum TlsError {
+    Io(io::Error),
+    /// The peer sent a fatal alert with this description.
+    AlertReceived(u8),
+    Decode(String),
+    HandshakeFailure(String),
+    BadCertificate(String),
+    /// A record failed authentication.
+    DecryptError,
+}
+
+impl Display for TlsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TlsError::Io(e) => write!(f, "{e}"),
+            TlsError::AlertReceived(alert) => write!(f, "received fatal alert {alert}"),
+            TlsError::Decode(message) => write!(f, "malformed TLS message: {message}"),
+            TlsError::HandshakeFailure(message) => write!(f, "handshake failure: {message}"),
+            TlsError::BadCertificate(message) => write!(f, "invalid certificate: {message}"),
+            TlsError::DecryptError => write!(f, "failed to decrypt record"),
+        }
+    }
+}
+
+impl std::error::Error for TlsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TlsError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TlsError {
+    fn from(e: io::Error) -> Self {
+        TlsError::Io(e)
+    }
+}
+
+impl From<TlsError> for io::Error {
+    fn from(e: TlsError) -> Self {
+        match e {
+            TlsError::Io(e) => e,
+            e => io::Error::new(io::ErrorKind::InvalidData, e),
+        }
+    }
+}
+
+/**
+ * Name of the server the certificate is checked against. Server Name Indication is only sent
+ * for DNS names.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerName {
+    Dns(String),
+    Ip(IpAddr),
+}
+
+impl ServerName {
+    pub fn new(host: &str) -> Self {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        match host.parse() {
+            Ok(ip) => ServerName::Ip(ip),
+            Err(_) => ServerName::Dns(host.trim_end_matches('.').to_ascii_lowercase()),
+        }
+    }
+}
+
+impl Display for ServerName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServerName::Dns(name) => write!(f, "{name}"),
+            ServerName::Ip(ip) => write!(f, "{ip}"),
+        }
+    }
+}
+
+/**
+ * Configuration shared by TLS connections
+ */
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    pub roots: RootCertStore,
+    /// SHA-256 digests of SubjectPublicKeyInfo structures. If any are set, a server is
+    /// authenticated by a certificate with one of these keys, or issued through its chain by one,
+    /// instead of by its name and the roots (RFC 7858 section 4.2).
+    pub spki_pins: Vec<[u8; 32]>,
+}
+
+impl ClientConfig {
+    pub fn new(roots: RootCertStore) -> Self {
+        Self {
+            roots,
+            spki_pins: vec![],
+        }
+    }
+}
+
+impl Default for ClientConfig {
+    /// Trusts the CA bundle of the operating system.
+    fn default() -> Self {
+        Self::new(RootCertStore::system())
+    }
+}
@@ -0,0 +1,211 @@
+use std::io::{self, Read};
+
+use super::TlsError;
+use crate::crypto::gcm::{AesGcm, NONCE_LEN, TAG_LEN};
+
+// record content types
+pub const CHANGE_CIPHER_SPEC: u8 = 20;
+pub const ALERT: u8 = 21;
+pub const HANDSHAKE: u8 = 22;
+pub const APPLICATION_DATA: u8 = 23;
+
+/// Maximum length of a plaintext fragment
+pub const MAX_FRAGMENT_LEN: usize = 1 << 14;
+/// Maximum length of a record payload, which is 2^14 + 256 in TLS 1.3 and 2^14 + 2048 in TLS 1.2
+const MAX_PAYLOAD_LEN: usize = MAX_FRAGMENT_LEN + 2048;
+/// Length of the explicit part of the nonce in TLS 1.2 GCM records
+const EXPLICIT_NONCE_LEN: usize = 8;
+
+/**
+ * AEAD protection of records in one direction
+ *
+ * TLS 1.3 (RFC 8446 section 5.2) derives the nonce from the sequence number and hides the real
+ * content type inside the encrypted record. TLS 1.2 GCM (RFC 5288) sends the explicit part of
+ * the nonce in front of each record.
+ */
+#[derive(Debug, Clone)]
+pub struct RecordCipher {
+    aead: AesGcm,
+    iv: Vec<u8>,
+    seq: u64,
+    tls13: bool,
+}
+
+impl RecordCipher {
+    /// `iv` is the 12 byte write IV.
+    pub fn tls13(key: &[u8], iv: &[u8]) -> Self {
+        Self::new(key, iv, true)
+    }
+
+    /// `salt` is the 4 byte implicit part of the nonce.
+    pub fn tls12(key: &[u8], salt: &[u8]) -> Self {
+        Self::new(key, salt, false)
+    }
+
+    fn new(key: &[u8], iv: &[u8], tls13: bool) -> Self {
+        Self {
+            aead: AesGcm::new(key).expect("cipher suites only use AES-128 or AES-256"),
+            iv: iv.to_vec(),
+            seq: 0,
+            tls13,
+        }
+    }
+
+    fn nonce(&self, explicit: &[u8; EXPLICIT_NONCE_LEN]) -> [u8; NONCE_LEN] {
+        let mut nonce = [0u8; NONCE_LEN];
+        if self.tls13 {
+            nonce.copy_from_slice(&self.iv);
+            for (n, s) in nonce[NONCE_LEN - 8..].iter_mut().zip(explicit) {
+                *n ^= s;
+            }
+        } else {
+            nonce[..4].copy_from_slice(&self.iv);
+            nonce[4..].copy_from_slice(explicit);
+        }
+        nonce
+    }
+
+    /// Encrypts a fragment and returns the whole record including its header.
+    pub fn seal(&mut self, content_type: u8, fragment: &[u8]) -> Vec<u8> {
+        let seq = self.seq.to_be_bytes();
+        let nonce = self.nonce(&seq);
+        self.seq += 1;
+        if self.tls13 {
+            let mut inner = fragment.to_vec();
+            inner.push(content_type);
+            let header = header(APPLICATION_DATA, inner.len() + TAG_LEN);
+            let mut record = header.to_vec();
+            record.extend(self.aead.seal(&nonce, &header, &inner));
+            record
+        } else {
+            let aad = tls12_aad(&seq, content_type, fragment.len());
+            let mut record =
+                header(content_type, EXPLICIT_NONCE_LEN + fragment.len() + TAG_LEN).to_vec();
+            record.extend_from_slice(&seq);
+            record.extend(self.aead.seal(&nonce, &aad, fragment));
+            record
+        }
+    }
+
+    /// Decrypts the payload of a record and returns the real content type and the plaintext.
+    pub fn open(&mut self, content_type: u8, payload: &[u8]) -> Result<(u8, Vec<u8>), TlsError> {
+        let seq = self.seq.to_be_bytes();
+        self.seq += 1;
+        if self.tls13 {
+            if content_type != APPLICATION_DATA {
+                return Err(TlsError::Decode(format!(
+                    "unexpected record type {content_type} after handshake keys are in use"
+                )));
+            }
+            let nonce = self.nonce(&seq);
+            let header = header(content_type, payload.len());
+            let mut inner = self
+                .aead
+                .open(&nonce, &header, payload)
+                .ok_or(TlsError::DecryptError)?;
+            // strip the padding, the last non-zero byte is the content type
+            let end = inner
+                .iter()
+                .rposition(|b| *b != 0)
+                .ok_or_else(|| TlsError::Decode("record has no content type".to_string()))?;
+            let inner_type = inner[end];
+            inner.truncate(end);
+            Ok((inner_type, inner))
+        } else {
+            if payload.len() < EXPLICIT_NONCE_LEN + TAG_LEN {
+                return Err(TlsError::DecryptError);
+            }
+            let (explicit, ciphertext) = payload.split_at(EXPLICIT_NONCE_LEN);
+            let nonce = self.nonce(explicit.try_into().unwrap());
+            let aad = tls12_aad(&seq, content_type, ciphertext.len() - TAG_LEN);
+            let plaintext = self
+                .aead
+                .open(&nonce, &aad, ciphertext)
+                .ok_or(TlsError::DecryptError)?;
+            Ok((content_type, plaintext))
+        }
+    }
+}
+
+fn header(content_type: u8, length: usize) -> [u8; 5] {
+    let length = (length as u16).to_be_bytes();
+    [content_type, 0x03, 0x03, length[0], length[1]]
+}
+
+fn tls12_aad(seq: &[u8; 8], content_type: u8, length: usize) -> Vec<u8> {
+    let mut aad = seq.to_vec();
+    aad.extend_from_slice(&header(content_type, length));
+    aad
+}
+
+/// Encodes an unprotected record.
+pub fn plaintext_record(content_type: u8, fragment: &[u8]) -> Vec<u8> {
+    let mut record = header(content_type, fragment.len()).to_vec();
+    record.extend_from_slice(fragment);
+    record
+}
+
+/// Reads one record and returns its content type and payload, or None if the peer closed the
+/// connection at a record boundary.
+pub fn read_record(reader: &mut impl Read) -> Result<Option<(u8, Vec<u8>)>, TlsError> {
+    let mut header = [0u8; 5];
+    let mut filled = 0;
+    while filled < header.len() {
+        match reader.read(&mut header[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    let content_type = header[0];
+    if !(CHANGE_CIPHER_SPEC..=APPLICATION_DATA).contains(&content_type) || header[1] != 0x03 {
+        return Err(TlsError::Decode("not a TLS record".to_string()));
+    }
+    let length = u16::from_be_bytes([header[3], header[4]]) as usize;
+    if length > MAX_PAYLOAD_LEN {
+        return Err(TlsError::Decode("record overflow".to_string()));
+    }
+    let mut payload = vec![0u8; length];
+    reader.read_exact(&mut payload)?;
+    Ok(Some((content_type, payload)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_cipher_roundtrip() {
+        for tls13 in [true, false] {
+            let iv_len = if tls13 { 12 } else { 4 };
+            let mut writer = RecordCipher::new(&[7; 16], &vec![9; iv_len], tls13);
+            let mut reader = writer.clone();
+            for message in [&b"hello"[..], b"world"] {
+                let record = writer.seal(HANDSHAKE, message);
+                let (content_type, payload) = read_record(&mut &record[..]).unwrap().unwrap();
+                assert_eq!(
+                    reader.open(content_type, &payload).ok(),
+                    Some((HANDSHAKE, message.to_vec()))
+                );
+            }
+            // a modified record must be rejected
+            let mut record = writer.seal(APPLICATION_DATA, b"data");
+            *record.last_mut().unwrap() ^= 1;
+            assert!(reader.open(record[0], &record[5..]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_read_record() {
+        assert!(read_record(&mut &b""[..]).unwrap().is_none());
+        assert!(read_record(&mut &[0x16, 0x03][..]).is_err());
+        assert!(read_record(&mut &b"HTTP/1.1 400"[..]).is_err());
+        let record = plaintext_record(ALERT, &[2, 40]);
+        assert_eq!(
+            read_record(&mut &record[..]).unwrap(),
+            Some((ALERT, vec![2, 40]))
+        );
+    }
+}
@@ -0,0 +1,651 @@
+use std::{net::IpAddr, path::Path};
+
+use super::{
+    der::{self, Der},
+    ServerName, TlsError,
+};
+use crate::crypto::{ec::Curve, rsa::RsaPublicKey, sha2::Sha256, HashAlgorithm};
+
+/// Maximum number of intermediate certificates between the end-entity certificate and a root.
+const MAX_CHAIN_DEPTH: usize = 8;
+
+// DER encoded object identifiers
+const OID_RSA_ENCRYPTION: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+const OID_SHA256_WITH_RSA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b];
+const OID_SHA384_WITH_RSA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0c];
+const OID_SHA512_WITH_RSA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0d];
+const OID_EC_PUBLIC_KEY: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+const OID_P256: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+const OID_P384: &[u8] = &[0x2b, 0x81, 0x04, 0x00, 0x22];
+const OID_ECDSA_WITH_SHA256: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02];
+const OID_ECDSA_WITH_SHA384: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x03];
+const OID_ECDSA_WITH_SHA512: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x04];
+const OID_BASIC_CONSTRAINTS: &[u8] = &[0x55, 0x1d, 0x13];
+const OID_KEY_USAGE: &[u8] = &[0x55, 0x1d, 0x0f];
+const OID_SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+const OID_EXT_KEY_USAGE: &[u8] = &[0x55, 0x1d, 0x25];
+const OID_ANY_EXT_KEY_USAGE: &[u8] = &[0x55, 0x1d, 0x25, 0x00];
+const OID_KP_SERVER_AUTH: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x01];
+
+/// keyCertSign (bit 5) of the KeyUsage extension
+const KEY_USAGE_KEY_CERT_SIGN: u16 = 1 << 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PublicKey {
+    Rsa(RsaPublicKey),
+    EcP256(Vec<u8>),
+    EcP384(Vec<u8>),
+    Unsupported,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureAlgorithm {
+    RsaPkcs1(HashAlgorithm),
+    RsaPss(HashAlgorithm),
+    Ecdsa(HashAlgorithm),
+}
+
+impl PublicKey {
+    fn from_spki(spki: &[u8]) -> Result<Self, TlsError> {
+        let mut spki = Der::new(spki).sequence()?;
+        let mut algorithm = spki.sequence()?;
+        let key = spki.bit_string()?;
+        match algorithm.expect(der::OID)? {
+            OID_RSA_ENCRYPTION => {
+                let mut key = Der::new(key).sequence()?;
+                let n = key.unsigned_integer()?;
+                let e = key.unsigned_integer()?;
+                Ok(RsaPublicKey::new(n, e)
+                    .map(PublicKey::Rsa)
+                    .unwrap_or(PublicKey::Unsupported))
+            }
+            OID_EC_PUBLIC_KEY => match algorithm.optional(der::OID)? {
+                Some(OID_P256) => Ok(PublicKey::EcP256(key.to_vec())),
+                Some(OID_P384) => Ok(PublicKey::EcP384(key.to_vec())),
+                _ => Ok(PublicKey::Unsupported),
+            },
+            _ => Ok(PublicKey::Unsupported),
+        }
+    }
+
+    /// Verifies `signature` over `message`. ECDSA signatures are DER encoded as in X.509 and TLS.
+    pub fn verify(&self, algorithm: SignatureAlgorithm, message: &[u8], signature: &[u8]) -> bool {
+        match (self, algorithm) {
+            (PublicKey::Rsa(key), SignatureAlgorithm::RsaPkcs1(hash)) => {
+                key.verify_pkcs1v15(hash, message, signature)
+            }
+            (PublicKey::Rsa(key), SignatureAlgorithm::RsaPss(hash)) => {
+                key.verify_pss(hash, message, signature)
+            }
+            (PublicKey::EcP256(point), SignatureAlgorithm::Ecdsa(hash)) => {
+                verify_ecdsa(Curve::p256(), point, hash, message, signature)
+            }
+            (PublicKey::EcP384(point), SignatureAlgorithm::Ecdsa(hash)) => {
+                verify_ecdsa(Curve::p384(), point, hash, message, signature)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn verify_ecdsa(
+    curve: &Curve,
+    point: &[u8],
+    hash: HashAlgorithm,
+    message: &[u8],
+    signature: &[u8],
+) -> bool {
+    let parse = || -> Result<(&[u8], &[u8]), TlsError> {
+        let mut outer = Der::new(signature);
+        let mut seq = outer.sequence()?;
+        let r = seq.unsigned_integer()?;
+        let s = seq.unsigned_integer()?;
+        seq.finish()?;
+        outer.finish()?;
+        Ok((r, s))
+    };
+    match parse() {
+        Ok((r, s)) => curve.verify(point, &hash.digest(&[message]), r, s),
+        Err(_) => false,
+    }
+}
+
+/**
+ * The parts of an X.509 v3 certificate (RFC 5280) needed for server authentication
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Certificate {
+    der: Vec<u8>,
+    tbs: Vec<u8>,
+    signature_algorithm: Option<SignatureAlgorithm>,
+    signature: Vec<u8>,
+    issuer: Vec<u8>,
+    subject: Vec<u8>,
+    not_before: i64,
+    not_after: i64,
+    public_key: PublicKey,
+    spki: Vec<u8>,
+    is_ca: bool,
+    path_len: Option<usize>,
+    key_usage: Option<u16>,
+    /// Whether the ExtendedKeyUsage extension is missing or allows server authentication
+    server_auth: bool,
+    dns_names: Vec<String>,
+    ip_addresses: Vec<IpAddr>,
+}
+
+impl Certificate {
+    pub fn from_der(data: &[u8]) -> Result<Self, TlsError> {
+        let mut outer = Der::new(data);
+        let mut cert = outer.sequence()?;
+        outer.finish()?;
+        let (_, tbs_value, tbs) = cert.read()?;
+        let (_, algorithm_value, algorithm) = cert.read()?;
+        let signature_algorithm = signature_algorithm(Der::new(algorithm_value))?;
+        let signature = cert.bit_string()?.to_vec();
+        cert.finish()?;
+
+        let mut tbs_der = Der::new(tbs_value);
+        if let Some(version) = tbs_der.optional(der::explicit(0))? {
+            if Der::new(version).unsigned_integer()? != [2] {
+                return Err(TlsError::BadCertificate(
+                    "only X.509 v3 certificates are supported".to_string(),
+                ));
+            }
+        }
+        tbs_der.expect(der::INTEGER)?; // serial number
+        let (_, _, inner_algorithm) = tbs_der.read()?;
+        if inner_algorithm != algorithm {
+            return Err(TlsError::BadCertificate(
+                "signature algorithm mismatch".to_string(),
+            ));
+        }
+        let (_, _, issuer) = tbs_der.read()?;
+        let mut validity = tbs_der.sequence()?;
+        let (tag, value, _) = validity.read()?;
+        let not_before = der::parse_time(tag, value)?;
+        let (tag, value, _) = validity.read()?;
+        let not_after = der::parse_time(tag, value)?;
+        let (_, _, subject) = tbs_der.read()?;
+        let (_, _, spki) = tbs_der.read()?;
+        tbs_der.optional(der::implicit(1))?; // issuerUniqueID
+        tbs_der.optional(der::implicit(2))?; // subjectUniqueID
+
+        let mut certificate = Certificate {
+            der: data.to_vec(),
+            tbs: tbs.to_vec(),
+            signature_algorithm,
+            signature,
+            issuer: issuer.to_vec(),
+            subject: subject.to_vec(),
+            not_before,
+            not_after,
+            public_key: PublicKey::from_spki(spki)?,
+            spki: spki.to_vec(),
+            is_ca: false,
+            path_len: None,
+            key_usage: None,
+            server_auth: true,
+            dns_names: vec![],
+            ip_addresses: vec![],
+        };
+        if let Some(extensions) = tbs_der.optional(der::explicit(3))? {
+            certificate.parse_extensions(extensions)?;
+        }
+        tbs_der.finish()?;
+        Ok(certificate)
+    }
+
+    fn parse_extensions(&mut self, extensions: &[u8]) -> Result<(), TlsError> {
+        let mut outer = Der::new(extensions);
+        let mut extensions = outer.sequence()?;
+        outer.finish()?;
+        while !extensions.is_empty() {
+            let mut extension = extensions.sequence()?;
+            let oid = extension.expect(der::OID)?;
+            let critical = match extension.optional(der::BOOLEAN)? {
+                Some(value) => value == [0xff],
+                None => false,
+            };
+            let value = extension.expect(der::OCTET_STRING)?;
+            match oid {
+                OID_BASIC_CONSTRAINTS => {
+                    let mut constraints = Der::new(value).sequence()?;
+                    self.is_ca = constraints.optional(der::BOOLEAN)? == Some(&[0xff]);
+                    if let Some(path_len) = constraints.optional(der::INTEGER)? {
+                        self.path_len = Some(path_len.iter().fold(0usize, |acc, b| {
+                            acc.saturating_mul(256).saturating_add(*b as usize)
+                        }));
+                    }
+                }
+                OID_KEY_USAGE => {
+                    let bits = Der::new(value).expect(der::BIT_STRING)?;
+                    // after the unused bits count, named bit 0 is the MSB of the first byte
+                    let first = bits.get(1).unwrap_or(&0).reverse_bits() as u16;
+                    let second = bits.get(2).unwrap_or(&0).reverse_bits() as u16;
+                    self.key_usage = Some(first | (second << 8));
+                }
+                OID_SUBJECT_ALT_NAME => {
+                    let mut names = Der::new(value).sequence()?;
+                    while !names.is_empty() {
+                        match names.read()? {
+                            (0x82, name, _) => {
+                                let name = String::from_utf8_lossy(name).to_ascii_lowercase();
+                                self.dns_names.push(name);
+                            }
+                            (0x87, ip, _) => {
+                                if let Ok(octets) = <[u8; 4]>::try_from(ip) {
+                                    self.ip_addresses.push(IpAddr::from(octets));
+                                } else if let Ok(octets) = <[u8; 16]>::try_from(ip) {
+                                    self.ip_addresses.push(IpAddr::from(octets));
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                OID_EXT_KEY_USAGE => {
+                    let mut purposes = Der::new(value).sequence()?;
+                    self.server_auth = false;
+                    while !purposes.is_empty() {
+                        let purpose = purposes.expect(der::OID)?;
+                        self.server_auth |=
+                            purpose == OID_KP_SERVER_AUTH || purpose == OID_ANY_EXT_KEY_USAGE;
+                    }
+                }
+                _ if critical => {
+                    return Err(TlsError::BadCertificate(
+                        "unsupported critical extension".to_string(),
+                    ))
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    /// SHA-256 digest of the DER encoded SubjectPublicKeyInfo, as used for key pinning.
+    pub fn spki_sha256(&self) -> [u8; 32] {
+        Sha256::digest(&self.spki).try_into().unwrap()
+    }
+
+    fn is_valid_at(&self, now: i64) -> bool {
+        self.not_before <= now && now <= self.not_after
+    }
+
+    /// Whether this certificate was signed by the key of `issuer`.
+    fn is_issued_by(&self, issuer: &Certificate) -> bool {
+        self.issuer == issuer.subject
+            && match self.signature_algorithm {
+                Some(algorithm) => issuer
+                    .public_key
+                    .verify(algorithm, &self.tbs, &self.signature),
+                None => false,
+            }
+    }
+
+    fn can_sign_certificates(&self) -> bool {
+        self.is_ca
+            && self
+                .key_usage
+                .is_none_or(|usage| usage & KEY_USAGE_KEY_CERT_SIGN != 0)
+    }
+
+    fn matches(&self, server_name: &ServerName) -> bool {
+        match server_name {
+            ServerName::Dns(host) => self
+                .dns_names
+                .iter()
+                .any(|name| dns_name_matches(name, host)),
+            ServerName::Ip(ip) => self.ip_addresses.contains(ip),
+        }
+    }
+}
+
+fn signature_algorithm(mut algorithm: Der) -> Result<Option<SignatureAlgorithm>, TlsError> {
+    let algorithm = match algorithm.expect(der::OID)? {
+        OID_SHA256_WITH_RSA => SignatureAlgorithm::RsaPkcs1(HashAlgorithm::Sha256),
+        OID_SHA384_WITH_RSA => SignatureAlgorithm::RsaPkcs1(HashAlgorithm::Sha384),
+        OID_SHA512_WITH_RSA => SignatureAlgorithm::RsaPkcs1(HashAlgorithm::Sha512),
+        OID_ECDSA_WITH_SHA256 => SignatureAlgorithm::Ecdsa(HashAlgorithm::Sha256),
+        OID_ECDSA_WITH_SHA384 => SignatureAlgorithm::Ecdsa(HashAlgorithm::Sha384),
+        OID_ECDSA_WITH_SHA512 => SignatureAlgorithm::Ecdsa(HashAlgorithm::Sha512),
+        _ => return Ok(None),
+    };
+    Ok(Some(algorithm))
+}
+
+/// Matches a dNSName from a certificate against a host name, allowing a wildcard as the whole left-most label.
+/// A wildcard needs two labels after it, so that it doesn't cover a public suffix like `*.com`, and
+/// doesn't match IP addresses or internationalized labels (RFC 6125 section 6.4.3).
+fn dns_name_matches(pattern: &str, host: &str) -> bool {
+    let host = host.trim_end_matches('.');
+    let pattern = pattern.trim_end_matches('.');
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => match host.split_once('.') {
+            Some((label, rest)) => {
+                !label.is_empty()
+                    && !label.to_ascii_lowercase().starts_with("xn--")
+                    && suffix.split('.').filter(|label| !label.is_empty()).count() >= 2
+                    && host.parse::<IpAddr>().is_err()
+                    && rest.eq_ignore_ascii_case(suffix)
+            }
+            None => false,
+        },
+        None => pattern.eq_ignore_ascii_case(host),
+    }
+}
+
+/**
+ * Set of trust anchors used to authenticate servers
+ */
+#[derive(Debug, Clone, Default)]
+pub struct RootCertStore {
+    roots: Vec<Certificate>,
+}
+
+impl RootCertStore {
+    /// Creates a store that trusts nothing.
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    /// Loads the CA bundle of the operating system. The store is empty when no bundle is found.
+    pub fn system() -> Self {
+        const BUNDLES: [&str; 4] = [
+            "/etc/ssl/certs/ca-certificates.crt",
+            "/etc/pki/tls/certs/ca-bundle.crt",
+            "/etc/ssl/ca-bundle.pem",
+            "/etc/ssl/cert.pem",
+        ];
+        let mut store = Self::empty();
+        for path in BUNDLES {
+            if let Ok(n) = store.add_pem_file(path) {
+                if n > 0 {
+                    break;
+                }
+            }
+        }
+        store
+    }
+
+    pub fn len(&self) -> usize {
+        self.roots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// Adds a DER encoded certificate as a trust anchor.
+    pub fn add_der(&mut self, der: &[u8]) -> Result<(), TlsError> {
+        self.roots.push(Certificate::from_der(der)?);
+        Ok(())
+    }
+
+    /// Adds every "CERTIFICATE" block of a PEM document, skipping ones that can't be parsed.
+    /// Returns the number of certificates added.
+    pub fn add_pem(&mut self, pem: &str) -> Result<usize, TlsError> {
+        let mut added = 0;
+        for der in pem_certificates(pem)? {
+            if self.add_der(&der).is_ok() {
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+
+    pub fn add_pem_file(&mut self, path: impl AsRef<Path>) -> Result<usize, TlsError> {
+        let pem = std::fs::read_to_string(path)?;
+        self.add_pem(&pem)
+    }
+
+    /**
+     * Validates the certificate chain sent by a server (end-entity certificate first)
+     * against this store for `server_name` at `now` (seconds since the Unix epoch)
+     */
+    pub(crate) fn verify_server(
+        &self,
+        chain: &[Certificate],
+        server_name: &ServerName,
+        now: i64,
+    ) -> Result<(), TlsError> {
+        let leaf = chain.first().ok_or_else(|| {
+            TlsError::BadCertificate("server did not send a certificate".to_string())
+        })?;
+        if !leaf.is_valid_at(now) {
+            return Err(TlsError::BadCertificate(
+                "certificate is expired or not yet valid".to_string(),
+            ));
+        }
+        if !leaf.matches(server_name) {
+            return Err(TlsError::BadCertificate(format!(
+                "certificate is not valid for {server_name}"
+            )));
+        }
+        if !leaf.server_auth {
+            return Err(TlsError::BadCertificate(
+                "certificate is not valid for server authentication".to_string(),
+            ));
+        }
+        // a self-signed certificate explicitly added to the store is trusted as is
+        if self.roots.iter().any(|root| root.der == leaf.der) {
+            return Ok(());
+        }
+        if self.build_path(leaf, &chain[1..], now, 0) {
+            Ok(())
+        } else {
+            Err(TlsError::BadCertificate(
+                "certificate is not issued by a trusted authority".to_string(),
+            ))
+        }
+    }
+
+    /// Depth first search for a path from `cert` to a trust anchor through the intermediates.
+    fn build_path(
+        &self,
+        cert: &Certificate,
+        intermediates: &[Certificate],
+        now: i64,
+        depth: usize,
+    ) -> bool {
+        if self.roots.iter().any(|root| cert.is_issued_by(root)) {
+            return true;
+        }
+        if depth >= MAX_CHAIN_DEPTH {
+            return false;
+        }
+        intermediates.iter().any(|issuer| {
+            issuer.der != cert.der
+                && issuer.is_valid_at(now)
+                && issuer.can_sign_certificates()
+                && issuer.server_auth
+                && issuer.path_len.is_none_or(|max| depth <= max)
+                && cert.is_issued_by(issuer)
+                && self.build_path(issuer, intermediates, now, depth + 1)
+        })
+    }
+}
+
+/// Checks that the leaf of `chain` has one of the `pins`, SHA-256 digests of SubjectPublicKeyInfo
+/// structures, or is issued through the chain by a certificate that has one. The name and the
+/// issuers beyond the pinned key aren't checked.
+pub(crate) fn verify_pinned(
+    chain: &[Certificate],
+    pins: &[[u8; 32]],
+    now: i64,
+) -> Result<(), TlsError> {
+    let leaf = chain
+        .first()
+        .ok_or_else(|| TlsError::BadCertificate("server did not send a certificate".to_string()))?;
+    if !leaf.is_valid_at(now) {
+        return Err(TlsError::BadCertificate(
+            "certificate is expired or not yet valid".to_string(),
+        ));
+    }
+    if !leaf.server_auth {
+        return Err(TlsError::BadCertificate(
+            "certificate is not valid for server authentication".to_string(),
+        ));
+    }
+    if pinned_path(leaf, &chain[1..], pins, now, 0) {
+        Ok(())
+    } else {
+        Err(TlsError::BadCertificate(
+            "certificate is not issued by a pinned key".to_string(),
+        ))
+    }
+}
+
+/// Like `RootCertStore::build_path`, but ends at a certificate of the chain with a pinned key.
+fn pinned_path(
+    cert: &Certificate,
+    intermediates: &[Certificate],
+    pins: &[[u8; 32]],
+    now: i64,
+    depth: usize,
+) -> bool {
+    if pins.contains(&cert.spki_sha256()) {
+        return true;
+    }
+    if depth >= MAX_CHAIN_DEPTH {
+        return false;
+    }
+    intermediates.iter().any(|issuer| {
+        issuer.der != cert.der
+            && issuer.is_valid_at(now)
+            && issuer.can_sign_certificates()
+            && issuer.server_auth
+            && issuer.path_len.is_none_or(|max| depth <= max)
+            && cert.is_issued_by(issuer)
+            && pinned_path(issuer, intermediates, pins, now, depth + 1)
+    })
+}
+
+/// Extracts the DER payloads of the "CERTIFICATE" blocks of a PEM document (RFC 7468).
+pub(crate) fn pem_certificates(pem: &str) -> Result<Vec<Vec<u8>>, TlsError> {
+    let mut certificates = vec![];
+    let mut body: Option<String> = None;
+    for line in pem.lines().map(str::trim) {
+        if line == "-----BEGIN CERTIFICATE-----" {
+            body = Some(String::new());
+        } else if line == "-----END CERTIFICATE-----" {
+            if let Some(body) = body.take() {
+                certificates.push(base64_decode(&body)?);
+            }
+        } else if let Some(body) = body.as_mut() {
+            body.push_str(line);
+        }
+    }
+    Ok(certificates)
+}
+
+fn base64_decode(s: &str) -> Result<Vec<u8>, TlsError> {
+    let mut output = Vec::with_capacity(s.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in s.bytes().filter(|c| !c.is_ascii_whitespace()) {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            _ => return Err(TlsError::Decode("invalid base64".to_string())),
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CA: &str = include_str!("testdata/ca.pem");
+    const LEAF: &str = include_str!("testdata/ec.pem");
+    // 2030-01-01T00:00:00Z
+    const NOW: i64 = 1893456000;
+
+    fn certificate(pem: &str) -> Certificate {
+        Certificate::from_der(&pem_certificates(pem).unwrap()[0]).unwrap()
+    }
+
+    #[test]
+    fn test_parse_certificate() {
+        let ca = certificate(CA);
+        assert!(ca.can_sign_certificates());
+        assert!(matches!(ca.public_key(), PublicKey::EcP384(_)));
+        let leaf = certificate(LEAF);
+        assert!(!leaf.can_sign_certificates());
+        assert!(matches!(leaf.public_key(), PublicKey::EcP256(_)));
+        assert_eq!(leaf.dns_names, vec!["localhost".to_string()]);
+        assert!(leaf.is_issued_by(&ca));
+        assert!(!ca.is_issued_by(&leaf));
+    }
+
+    #[test]
+    fn test_verify_server() {
+        let mut roots = RootCertStore::empty();
+        assert_eq!(roots.add_pem(CA).unwrap(), 1);
+        let chain = [certificate(LEAF)];
+        let localhost = ServerName::new("localhost");
+        assert!(roots.verify_server(&chain, &localhost, NOW).is_ok());
+        assert!(roots
+            .verify_server(&chain, &ServerName::new("127.0.0.1"), NOW)
+            .is_ok());
+        assert!(roots
+            .verify_server(&chain, &ServerName::new("example.com"), NOW)
+            .is_err());
+        // not yet valid, and expired
+        assert!(roots.verify_server(&chain, &localhost, 0).is_err());
+        assert!(roots.verify_server(&chain, &localhost, NOW * 3).is_err());
+        assert!(RootCertStore::empty()
+            .verify_server(&chain, &localhost, NOW)
+            .is_err());
+        assert!(roots.verify_server(&[], &localhost, NOW).is_err());
+    }
+
+    #[test]
+    fn test_extended_key_usage() {
+        // a CA, an intermediate for email, a server certificate it issued, and client and server
+        // certificates the CA issued, all for localhost
+        let pem = include_str!("testdata/eku.pem");
+        let certificates: Vec<Certificate> = pem_certificates(pem)
+            .unwrap()
+            .iter()
+            .map(|der| Certificate::from_der(der).unwrap())
+            .collect();
+        let mut roots = RootCertStore::empty();
+        roots.roots.push(certificates[0].clone());
+        let localhost = ServerName::new("localhost");
+        let verify = |chain: &[Certificate]| roots.verify_server(chain, &localhost, NOW);
+        assert!(verify(&[certificates[4].clone()]).is_ok());
+        assert!(verify(&[certificates[3].clone()]).is_err());
+        assert!(verify(&[certificates[2].clone(), certificates[1].clone()]).is_err());
+    }
+
+    #[test]
+    fn test_dns_name_matches() {
+        assert!(dns_name_matches("example.com", "EXAMPLE.com."));
+        assert!(dns_name_matches("*.example.com", "www.example.com"));
+        assert!(!dns_name_matches("*.example.com", "example.com"));
+        assert!(!dns_name_matches("*.example.com", "a.b.example.com"));
+        assert!(!dns_name_matches("*.com", "example.com"));
+        assert!(!dns_name_matches("*.0.2.1", "192.0.2.1"));
+        assert!(!dns_name_matches(
+            "*.example.com",
+            "xn--bcher-kva.example.com"
+        ));
+    }
+}
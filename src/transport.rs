@@ -0,0 +1,78 @@
+use std::{
+    io::{self, Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+use crate::tls::{ClientConfig, ServerName, TlsStream};
+
+/**
+ * Byte stream to a server, either plain TCP or TLS over TCP
+ */
+#[derive(Debug)]
+pub enum Transport {
+    Tcp(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl Transport {
+    /// Performs a TLS handshake over `sock`, authenticating the server as `server_name`.
+    pub fn tls(
+        config: &ClientConfig,
+        server_name: &ServerName,
+        sock: TcpStream,
+    ) -> io::Result<Self> {
+        let stream = TlsStream::connect(config, server_name, sock)?;
+        Ok(Transport::Tls(Box::new(stream)))
+    }
+
+    /// Makes reads and writes fail after `timeout`, or blocks them indefinitely if it is None.
+    pub fn set_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        let sock = self.tcp_stream();
+        sock.set_read_timeout(timeout)?;
+        sock.set_write_timeout(timeout)
+    }
+
+    fn tcp_stream(&self) -> &TcpStream {
+        match self {
+            Transport::Tcp(stream) => stream,
+            Transport::Tls(stream) => stream.get_ref(),
+        }
+    }
+
+    /// Checks without blocking whether the server closed an idle connection or sent data nobody asked for.
+    pub fn is_stale(&self) -> bool {
+        let sock = self.tcp_stream();
+        if sock.set_nonblocking(true).is_err() {
+            return true;
+        }
+        let mut buf = [0u8; 1];
+        let idle = matches!(sock.peek(&mut buf), Err(e) if e.kind() == io::ErrorKind::WouldBlock);
+        sock.set_nonblocking(false).is_err() || !idle
+    }
+}
+
+impl Read for Transport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Transport::Tcp(stream) => stream.read(buf),
+            Transport::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Transport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Transport::Tcp(stream) => stream.write(buf),
+            Transport::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Transport::Tcp(stream) => stream.flush(),
+            Transport::Tls(stream) => stream.flush(),
+        }
+    }
+}